@@ -0,0 +1,101 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::DATA_DIR;
+use crate::error::Result;
+
+const ACHIEVEMENTS_FILE: &str = "achievements.json";
+
+/// What milestones are measured against, gathered from the current game.
+pub(crate) struct Progress {
+    pub(crate) turns: u32,
+    pub(crate) npcs_met: usize,
+    pub(crate) places_visited: usize,
+    pub(crate) died: bool,
+    pub(crate) finished: bool,
+}
+
+pub(crate) struct Achievement {
+    pub(crate) id: &'static str,
+    pub(crate) title: &'static str,
+    pub(crate) description: &'static str,
+    reached: fn(&Progress) -> bool,
+}
+
+pub(crate) static ACHIEVEMENTS: [Achievement; 5] = [
+    Achievement {
+        id: "first_death",
+        title: "Not So Immortal",
+        description: "Die for the first time.",
+        reached: |p| p.died,
+    },
+    Achievement {
+        id: "turns_100",
+        title: "In It for the Long Haul",
+        description: "Play 100 turns in one game.",
+        reached: |p| p.turns >= 100,
+    },
+    Achievement {
+        id: "npcs_10",
+        title: "People Person",
+        description: "Meet 10 characters.",
+        reached: |p| p.npcs_met >= 10,
+    },
+    Achievement {
+        id: "places_20",
+        title: "Cartographer",
+        description: "Visit 20 locations.",
+        reached: |p| p.places_visited >= 20,
+    },
+    Achievement {
+        id: "story_complete",
+        title: "The End",
+        description: "See a story through to its epilogue.",
+        reached: |p| p.finished,
+    },
+];
+
+/// Unlocked achievement ids, persisted across sessions in `.story-gen/achievements.json`.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Achievements {
+    pub(crate) unlocked: BTreeSet<String>,
+}
+
+impl Achievements {
+    fn path() -> PathBuf {
+        Path::new(DATA_DIR).join(ACHIEVEMENTS_FILE)
+    }
+
+    /// Loads unlocked achievements; a missing or unreadable file starts fresh.
+    pub(crate) fn load() -> Self {
+        fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        fs::create_dir_all(DATA_DIR)?;
+        let mut contents = serde_json::to_string_pretty(self)?;
+        contents.push('\n');
+        fs::write(Self::path(), contents)?;
+        Ok(())
+    }
+
+    /// Unlocks every achievement `progress` now meets, returning the new ones.
+    pub(crate) fn check(&mut self, progress: &Progress) -> Vec<&'static Achievement> {
+        let reached: Vec<&'static Achievement> = ACHIEVEMENTS
+            .iter()
+            .filter(|achievement| !self.unlocked.contains(achievement.id))
+            .filter(|achievement| (achievement.reached)(progress))
+            .collect();
+        for achievement in &reached {
+            self.unlocked.insert(achievement.id.to_string());
+        }
+        reached
+    }
+}
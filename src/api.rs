@@ -1,104 +1,517 @@
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use anyhow::{anyhow, Result};
-use reqwest::blocking::Client;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::blocking::{Client, RequestBuilder};
 use serde_json::{json, Value};
 
-use crate::app::GameState;
-use crate::config::{API_URL, MAIN_MAX_OUTPUT_TOKENS, MODEL, SYSTEM_PROMPT};
+use crate::app::{affinity_label, GameState};
+use crate::capabilities::Capabilities;
+use crate::clock;
+use crate::config::{
+    KeyRing, Provider, Settings, CRAFTING_MAX_OUTPUT_TOKENS, EXTRACTION_MAX_OUTPUT_TOKENS,
+    SCENE_IMAGE_PROMPT, SCENE_MAX_OUTPUT_TOKENS, SCENE_PROMPT, SUMMARY_MAX_OUTPUT_TOKENS,
+    SYSTEM_PROMPT,
+};
+use crate::crafting::{crafted_format, Crafted, CRAFTING_PROMPT};
+use crate::error::{Error, Result};
+use crate::flags::describe_flags;
+use crate::items::Item;
+use crate::map::describe_exits;
+use crate::quests::QuestStatus;
+use crate::structured::{
+    state_changes_format, turn_format, StateChanges, StructuredTurn, CHOICES_PROMPT,
+    EXTRACTION_PROMPT, STRUCTURED_PROMPT,
+};
+use crate::summary::SUMMARY_PROMPT;
+use crate::tools::{call_output, tool_definitions, ToolCall, MAX_TOOL_ROUNDS};
+use crate::tokens::{serialized_len, Usage};
+use crate::wirelog::WireLog;
 
-fn build_request_body_with_max(input: &[Value], max_output_tokens: u32) -> Value {
-    json!({
-        "model": MODEL,
+pub(crate) struct TurnOutput {
+    pub(crate) text: String,
+    /// Raw output items, appended to history so the next turn sees them verbatim.
+    pub(crate) output_items: Vec<Value>,
+    pub(crate) debug_summary: String,
+    /// Serialized size of the request input, paired with `usage` to calibrate estimates.
+    pub(crate) input_chars: usize,
+    pub(crate) usage: Option<Usage>,
+    pub(crate) exchange: Exchange,
+    /// Parsed reply when structured output was requested and the model complied.
+    pub(crate) structured: Option<StructuredTurn>,
+    /// State updates requested through function calling, in call order.
+    pub(crate) tool_calls: Vec<ToolCall>,
+}
+
+/// Pretty-printed request and response bodies of a turn, shown in the F12 inspector.
+#[derive(Clone)]
+pub(crate) struct Exchange {
+    pub(crate) request: String,
+    pub(crate) response: String,
+}
+
+pub(crate) type TurnResult = Result<TurnOutput>;
+
+/// A backend that produces the next narrator turn. Runs on a worker thread.
+pub(crate) trait Narrator: Send + Sync {
+    fn advance(&self, history: &[Vec<Value>], state: &GameState) -> TurnResult;
+    fn capabilities(&self) -> Capabilities;
+    fn list_models(&self) -> Result<Vec<String>>;
+    /// ASCII art for the scene described by `context`, from the scene model.
+    fn draw_scene(&self, context: &str) -> Result<String>;
+    /// A PNG picture of the scene, from the scene model's image generation tool.
+    fn draw_scene_image(&self, context: &str) -> Result<Vec<u8>>;
+    /// What `reply` changed about `state`, from the extraction model.
+    fn extract_state(&self, reply: &str, state: &GameState) -> Result<StateChanges>;
+    /// What combining items `a` and `b` makes, from the extraction model.
+    fn craft(&self, a: &str, b: &str, state: &GameState) -> Result<Crafted>;
+    /// `summary` extended with `transcript`, from the extraction model.
+    fn summarize(&self, summary: &str, transcript: &str) -> Result<String>;
+    /// An embedding of `text` for long-term memory.
+    fn embed(&self, text: &str) -> Result<Vec<f32>>;
+    /// Input tokens `items` take for the narration model, from the provider's tokenizer.
+    fn count_tokens(&self, items: &[Value]) -> Result<u64>;
+    /// When a rate-limited request will be resent, while the worker is waiting.
+    fn retry_at(&self) -> Option<Instant>;
+}
+
+pub(crate) fn build_narrator(
+    settings: &Settings,
+    keys: Arc<KeyRing>,
+    debug: bool,
+    wire_log: Option<Arc<WireLog>>,
+) -> Arc<dyn Narrator> {
+    Arc::new(ResponsesNarrator {
+        settings: settings.clone(),
+        keys,
+        debug,
+        wire_log,
+        retry_at: Mutex::new(None),
+    })
+}
+
+/// Narrator for any endpoint speaking the Responses API (OpenAI, OpenRouter, Azure).
+pub(crate) struct ResponsesNarrator {
+    settings: Settings,
+    keys: Arc<KeyRing>,
+    debug: bool,
+    wire_log: Option<Arc<WireLog>>,
+    retry_at: Mutex<Option<Instant>>,
+}
+
+impl ResponsesNarrator {
+    fn ctx(&self) -> RequestContext<'_> {
+        RequestContext {
+            settings: &self.settings,
+            keys: &self.keys,
+            retry_at: &self.retry_at,
+            wire_log: self.wire_log.as_deref(),
+        }
+    }
+}
+
+impl Narrator for ResponsesNarrator {
+    fn advance(&self, history: &[Vec<Value>], state: &GameState) -> TurnResult {
+        advance_turn(&self.ctx(), history, state, self.debug)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.settings.capabilities()
+    }
+
+    fn list_models(&self) -> Result<Vec<String>> {
+        list_models(&self.settings, self.keys.current(), self.wire_log.as_deref())
+    }
+
+    fn draw_scene(&self, context: &str) -> Result<String> {
+        draw_scene(&self.ctx(), context)
+    }
+
+    fn draw_scene_image(&self, context: &str) -> Result<Vec<u8>> {
+        draw_scene_image(&self.ctx(), context)
+    }
+
+    fn extract_state(&self, reply: &str, state: &GameState) -> Result<StateChanges> {
+        extract_state(&self.ctx(), reply, state)
+    }
+
+    fn craft(&self, a: &str, b: &str, state: &GameState) -> Result<Crafted> {
+        craft(&self.ctx(), a, b, state)
+    }
+
+    fn summarize(&self, summary: &str, transcript: &str) -> Result<String> {
+        summarize(&self.ctx(), summary, transcript)
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        embed(&self.settings, self.keys.current(), self.wire_log.as_deref(), text)
+    }
+
+    fn count_tokens(&self, items: &[Value]) -> Result<u64> {
+        count_tokens(&self.settings, self.keys.current(), self.wire_log.as_deref(), items)
+    }
+
+    fn retry_at(&self) -> Option<Instant> {
+        *self.retry_at.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// What `send_with_retry` needs besides the request itself.
+struct RequestContext<'a> {
+    settings: &'a Settings,
+    keys: &'a KeyRing,
+    retry_at: &'a Mutex<Option<Instant>>,
+    wire_log: Option<&'a WireLog>,
+}
+
+impl RequestContext<'_> {
+    fn set_retry_at(&self, at: Option<Instant>) {
+        *self.retry_at.lock().unwrap_or_else(|e| e.into_inner()) = at;
+    }
+}
+
+fn build_request_body_with_max(
+    settings: &Settings,
+    input: &[Value],
+    max_output_tokens: u32,
+) -> Value {
+    let mut body = json!({
+        "model": settings.request_model(),
         "input": input,
         "max_output_tokens": max_output_tokens,
-        "text": { "format": { "type": "text" } },
-        "reasoning": { "effort": "minimal" }
-    })
+        "text": { "format": { "type": "text" } }
+    });
+    let params = settings.generation;
+    if settings.capabilities().reasoning {
+        body["reasoning"] = json!({ "effort": params.reasoning_effort.label() });
+        if let Some(verbosity) = params.verbosity {
+            body["text"]["verbosity"] = json!(verbosity.label());
+        }
+    } else if let Some(temperature) = params.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    settings.extend_request_body(&mut body);
+    body
 }
 
-fn build_request_body(input: &[Value]) -> Value {
-    build_request_body_with_max(input, MAIN_MAX_OUTPUT_TOKENS)
+fn build_request_body(settings: &Settings, input: &[Value]) -> Value {
+    let mut body =
+        build_request_body_with_max(settings, input, settings.generation.max_output_tokens);
+    let capabilities = settings.capabilities();
+    if capabilities.structured_output {
+        body["text"]["format"] = turn_format();
+    }
+    if capabilities.tools {
+        body["tools"] = tool_definitions();
+    }
+    body
 }
 
-pub(crate) fn advance_turn(
-    api_key: &str,
-    history: &[Vec<Value>],
+/// Scene art requests go to the scene model with a fixed budget and no tools or schema.
+fn build_scene_request_body(settings: &Settings, context: &str) -> Value {
+    let model = settings.scene_request_model();
+    let prompt = format!("{SCENE_PROMPT}{}", settings.scene_style.prompt());
+    let mut body = json!({
+        "model": model,
+        "input": [
+            { "role": "system", "content": prompt },
+            { "role": "user", "content": context }
+        ],
+        "max_output_tokens": SCENE_MAX_OUTPUT_TOKENS,
+        "text": { "format": { "type": "text" } }
+    });
+    if Capabilities::detect(settings.provider, &model).reasoning {
+        body["reasoning"] = json!({ "effort": "minimal" });
+    }
+    settings.extend_request_body(&mut body);
+    body
+}
+
+/// Scene pictures come from the Responses API's `image_generation` tool, forced on.
+fn build_scene_image_request_body(settings: &Settings, context: &str) -> Value {
+    let mut body = json!({
+        "model": settings.scene_request_model(),
+        "input": format!("{SCENE_IMAGE_PROMPT}\n\n{context}"),
+        "tools": [{ "type": "image_generation", "size": "1536x1024", "quality": "low" }],
+        "tool_choice": { "type": "image_generation" }
+    });
+    settings.extend_request_body(&mut body);
+    body
+}
+
+fn build_summary_request_body(settings: &Settings, summary: &str, transcript: &str) -> Value {
+    let model = settings.extraction_request_model();
+    let summary = if summary.is_empty() { "None yet." } else { summary };
+    let mut body = json!({
+        "model": model,
+        "input": [
+            { "role": "system", "content": SUMMARY_PROMPT },
+            {
+                "role": "user",
+                "content": format!("Existing summary:\n{summary}\n\nOlder turns:\n{transcript}")
+            }
+        ],
+        "max_output_tokens": SUMMARY_MAX_OUTPUT_TOKENS,
+        "text": { "format": { "type": "text" } }
+    });
+    if Capabilities::detect(settings.provider, &model).reasoning {
+        body["reasoning"] = json!({ "effort": "minimal" });
+    }
+    settings.extend_request_body(&mut body);
+    body
+}
+
+fn build_extraction_request_body(settings: &Settings, reply: &str, state: &GameState) -> Value {
+    let model = settings.extraction_request_model();
+    let capabilities = Capabilities::detect(settings.provider, &model);
+    let format = if capabilities.structured_output {
+        state_changes_format()
+    } else {
+        json!({ "type": "text" })
+    };
+    let mut body = json!({
+        "model": model,
+        "input": [
+            { "role": "system", "content": EXTRACTION_PROMPT },
+            {
+                "role": "user",
+                "content": format!("{}\n\nNarrator reply:\n{reply}", build_state_block(state))
+            }
+        ],
+        "max_output_tokens": EXTRACTION_MAX_OUTPUT_TOKENS,
+        "text": { "format": format }
+    });
+    if capabilities.reasoning {
+        body["reasoning"] = json!({ "effort": "minimal" });
+    }
+    settings.extend_request_body(&mut body);
+    body
+}
+
+fn build_crafting_request_body(
+    settings: &Settings,
+    a: &str,
+    b: &str,
     state: &GameState,
-    debug: bool,
-) -> Result<(String, Vec<Value>, String)> {
-    let client = Client::builder()
-        .timeout(Duration::from_secs(60))
-        .build()?;
+) -> Value {
+    let model = settings.extraction_request_model();
+    let capabilities = Capabilities::detect(settings.provider, &model);
+    let format = if capabilities.structured_output {
+        crafted_format()
+    } else {
+        json!({ "type": "text" })
+    };
+    let mut body = json!({
+        "model": model,
+        "input": [
+            { "role": "system", "content": CRAFTING_PROMPT },
+            {
+                "role": "user",
+                "content": format!("{}\n\nCombine: {a} + {b}", build_state_block(state))
+            }
+        ],
+        "max_output_tokens": CRAFTING_MAX_OUTPUT_TOKENS,
+        "text": { "format": format }
+    });
+    if capabilities.reasoning {
+        body["reasoning"] = json!({ "effort": "minimal" });
+    }
+    settings.extend_request_body(&mut body);
+    body
+}
 
-    let mut input_items = Vec::new();
+/// Per-turn game state, sent after the history so the prefix before it stays cacheable.
+pub(crate) fn build_state_block(state: &GameState) -> String {
+    let relationships: Vec<String> = state
+        .affinity
+        .iter()
+        .map(|(npc, score)| format!("{npc} {score:+} ({})", affinity_label(*score)))
+        .collect();
+    let quests: Vec<String> = state
+        .quests
+        .iter()
+        .filter(|quest| quest.status == QuestStatus::Active)
+        .map(|quest| format!("{} ({})", quest.title, quest.objective))
+        .collect();
+    let lines = [
+        format!("Current turn: {}", state.turn),
+        format!("Time: {}", clock::describe(state.minutes)),
+        format!("Weather: {}", state.weather.label()),
+        format!("Player character: {}", state.character.summary()),
+        format!("Health: {}/{}", state.hp, state.max_hp),
+        format!("Relationships: {}", or_none(&relationships, ", ")),
+        format!("Quests: {}", or_none(&quests, "; ")),
+        format!("Lore: {}", or_none(&state.lore, "; ")),
+        format!("Known exits: {}", describe_exits(state)),
+        format!("Location: {}", state.location),
+        format!(
+            "Inventory: {}",
+            if state.inventory.is_empty() {
+                "Empty".to_string()
+            } else {
+                let items: Vec<String> = state.inventory.iter().map(Item::label).collect();
+                items.join(", ")
+            }
+        ),
+        format!("Equipped: {}", state.equipment.summary()),
+        format!("Gold: {}", state.gold),
+        format!("Flags: {}", describe_flags(&state.flags)),
+        format!(
+            "Current speaker: {}",
+            state.active_speaker.as_deref().unwrap_or("Narrator")
+        ),
+    ];
+    lines.join("\n")
+}
 
-    let system_with_state = format!(
-        "{SYSTEM_PROMPT}\nCurrent turn: {}\nLocation: {}\nInventory: {}\nFlags: {}\nCurrent speaker: {}",
-        state.turn,
-        state.location,
-        if state.inventory.is_empty() {
-            "Empty".to_string()
-        } else {
-            state.inventory.join(", ")
-        },
-        if state.flags.is_empty() {
-            "None".to_string()
-        } else {
-            state.flags.join(", ")
-        },
-        state
-            .active_speaker
-            .as_deref()
-            .unwrap_or("Narrator")
-    );
+fn or_none(values: &[String], separator: &str) -> String {
+    if values.is_empty() {
+        "None".to_string()
+    } else {
+        values.join(separator)
+    }
+}
 
-    input_items.push(json!({
+/// The narrator's system prompt for these settings, before any per-game edit.
+pub(crate) fn system_prompt(settings: &Settings) -> String {
+    let difficulty = settings.difficulty.prompt();
+    if settings.capabilities().structured_output {
+        let choices = if settings.choice_mode { CHOICES_PROMPT } else { "" };
+        format!("{SYSTEM_PROMPT}{difficulty}{STRUCTURED_PROMPT}{choices}")
+    } else {
+        format!("{SYSTEM_PROMPT}{difficulty}")
+    }
+}
+
+fn build_static_system_item(settings: &Settings, state: &GameState) -> Value {
+    let prompt = match &state.system_prompt {
+        Some(prompt) => prompt.clone(),
+        None => system_prompt(settings),
+    };
+    if settings.prompt_cache && settings.provider == Provider::OpenRouter {
+        // OpenRouter forwards explicit cache breakpoints to vendors that need them.
+        return json!({
+            "role": "system",
+            "content": [{
+                "type": "input_text",
+                "text": prompt,
+                "cache_control": { "type": "ephemeral" }
+            }]
+        });
+    }
+    json!({
         "role": "system",
-        "content": system_with_state
-    }));
+        "content": prompt
+    })
+}
 
+fn advance_turn(
+    ctx: &RequestContext,
+    history: &[Vec<Value>],
+    state: &GameState,
+    debug: bool,
+) -> TurnResult {
+    let settings = ctx.settings;
+    let client = settings
+        .client_builder()?
+        .timeout(settings.request_timeout())
+        .build()?;
+
+    // Static prompt first and history next form a prefix that only grows between
+    // turns, so providers can reuse their prompt cache; the changing state goes last.
+    let mut input_items = vec![build_static_system_item(settings, state)];
+    if !state.premise.is_empty() {
+        input_items.push(json!({
+            "role": "system",
+            "content": state.premise
+        }));
+    }
+    if !state.summary.is_empty() {
+        input_items.push(json!({
+            "role": "system",
+            "content": format!("Story so far (older turns, summarized):\n{}", state.summary)
+        }));
+    }
     for chunk in history {
         for item in chunk {
             input_items.push(item.clone());
         }
     }
 
-    let mut retry_items = input_items.clone();
-    retry_items.push(json!({
-        "role": "user",
-        "content": "Please respond with visible text only."
+    let mut state_block = build_state_block(state);
+    if let Some(target) = settings.word_target {
+        state_block.push_str(&format!(
+            "\nTarget length: {}-{} words per reply.",
+            target.min, target.max
+        ));
+    }
+    input_items.push(json!({
+        "role": "system",
+        "content": state_block
     }));
-    let body = build_request_body(&input_items);
-    let retry_body = build_request_body(&retry_items);
 
     let mut last_debug = String::new();
     let mut last_json = String::new();
+    let mut turn_items = Vec::new();
+    let mut tool_calls = Vec::new();
     for attempt in 0..2 {
-        let body_ref = if attempt == 0 { &body } else { &retry_body };
-        let response = client
-            .post(API_URL)
-            .bearer_auth(api_key)
-            .json(body_ref)
-            .send()?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().unwrap_or_default();
-            return Err(anyhow!("OpenAI API error ({status}): {text}"));
+        if attempt == 1 {
+            input_items.push(json!({
+                "role": "user",
+                "content": "Please respond with visible text only."
+            }));
         }
+        let mut rounds = 0;
+        loop {
+            let body = build_request_body(settings, &input_items);
+            let value = send_with_retry(&client, ctx, &body)?;
+            if debug {
+                last_json = serde_json::to_string_pretty(&value).unwrap_or_default();
+            }
+            let extracted = extract_output(&value);
+            last_debug = extracted.debug_summary;
 
-        let value: Value = response.json()?;
-        if debug {
-            last_json = serde_json::to_string_pretty(&value).unwrap_or_default();
-        }
-        let (text_opt, output_items, debug_summary) = extract_output_text_and_items(&value);
-        last_debug = debug_summary;
-        if let Some(text) = text_opt {
-            return Ok((text, output_items, last_debug));
-        }
-        if attempt == 0 {
-            continue;
+            // Every function call needs an output item before the model sees it again.
+            for call in &extracted.calls {
+                tool_calls.extend(ToolCall::from_item(call));
+            }
+            let outputs: Vec<Value> = extracted.calls.iter().map(call_output).collect();
+            turn_items.extend(extracted.items.iter().cloned());
+            turn_items.extend(outputs.iter().cloned());
+
+            if let Some(mut text) = extracted.text {
+                let structured = if settings.capabilities().structured_output {
+                    StructuredTurn::parse(&text)
+                } else {
+                    None
+                };
+                if let Some(turn) = &structured {
+                    text = turn.to_labelled_text();
+                }
+                return Ok(TurnOutput {
+                    text,
+                    output_items: turn_items,
+                    debug_summary: last_debug,
+                    input_chars: serialized_len(&input_items),
+                    usage: Usage::from_response(&value),
+                    exchange: Exchange {
+                        request: serde_json::to_string_pretty(&body).unwrap_or_default(),
+                        response: serde_json::to_string_pretty(&value).unwrap_or_default(),
+                    },
+                    structured,
+                    tool_calls,
+                });
+            }
+            // Calls from the last round are applied too, so the follow-up attempt must see them.
+            let called = !outputs.is_empty();
+            input_items.extend(extracted.items);
+            input_items.extend(outputs);
+            if !called || rounds >= MAX_TOOL_ROUNDS {
+                break;
+            }
+            rounds += 1;
         }
     }
 
@@ -113,7 +526,282 @@ pub(crate) fn advance_turn(
     } else {
         "No output text found in response.".to_string()
     };
-    Err(anyhow!(message))
+    Err(Error::Parse(message))
+}
+
+fn draw_scene(ctx: &RequestContext, context: &str) -> Result<String> {
+    let client = ctx
+        .settings
+        .client_builder()?
+        .timeout(ctx.settings.request_timeout())
+        .build()?;
+    let body = build_scene_request_body(ctx.settings, context);
+    let value = send_with_retry(&client, ctx, &body)?;
+    let (text, _, debug_summary) = extract_output_text_and_items(&value);
+    text.map(|art| strip_code_fences(&art))
+        .filter(|art| !art.trim().is_empty())
+        .ok_or_else(|| Error::Parse(format!("Scene response had no art. {debug_summary}")))
+}
+
+fn draw_scene_image(ctx: &RequestContext, context: &str) -> Result<Vec<u8>> {
+    let client = ctx
+        .settings
+        .client_builder()?
+        .timeout(ctx.settings.request_timeout())
+        .build()?;
+    let body = build_scene_image_request_body(ctx.settings, context);
+    let value = send_with_retry(&client, ctx, &body)?;
+    let image = value
+        .get("output")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|item| item.get("type").and_then(Value::as_str) == Some("image_generation_call"))
+        .find_map(|item| item.get("result").and_then(Value::as_str))
+        .ok_or_else(|| Error::Parse("Scene image response had no picture.".to_string()))?;
+    STANDARD
+        .decode(image)
+        .map_err(|err| Error::Parse(format!("Scene image was not valid base64: {err}")))
+}
+
+fn extract_state(ctx: &RequestContext, reply: &str, state: &GameState) -> Result<StateChanges> {
+    let client = ctx
+        .settings
+        .client_builder()?
+        .timeout(ctx.settings.request_timeout())
+        .build()?;
+    let body = build_extraction_request_body(ctx.settings, reply, state);
+    let value = send_with_retry(&client, ctx, &body)?;
+    let (text, _, _) = extract_output_text_and_items(&value);
+    text.as_deref()
+        .and_then(StateChanges::parse_lenient)
+        .ok_or_else(|| Error::Parse("State extraction returned no usable JSON.".to_string()))
+}
+
+fn summarize(ctx: &RequestContext, summary: &str, transcript: &str) -> Result<String> {
+    let client = ctx
+        .settings
+        .client_builder()?
+        .timeout(ctx.settings.request_timeout())
+        .build()?;
+    let body = build_summary_request_body(ctx.settings, summary, transcript);
+    let value = send_with_retry(&client, ctx, &body)?;
+    let (text, _, debug_summary) = extract_output_text_and_items(&value);
+    text.map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+        .ok_or_else(|| Error::Parse(format!("Summary response had no text. {debug_summary}")))
+}
+
+fn craft(ctx: &RequestContext, a: &str, b: &str, state: &GameState) -> Result<Crafted> {
+    let client = ctx
+        .settings
+        .client_builder()?
+        .timeout(ctx.settings.request_timeout())
+        .build()?;
+    let body = build_crafting_request_body(ctx.settings, a, b, state);
+    let value = send_with_retry(&client, ctx, &body)?;
+    let (text, _, _) = extract_output_text_and_items(&value);
+    text.as_deref()
+        .and_then(Crafted::parse_lenient)
+        .ok_or_else(|| Error::Parse("Crafting returned no usable JSON.".to_string()))
+}
+
+/// Models sometimes fence art despite the prompt; keep only what is inside.
+fn strip_code_fences(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_string()
+}
+
+/// Posts `body`, retrying transient failures per the configured policy.
+/// Hard client errors (4xx other than 429) are returned immediately. A 429 first
+/// rotates through the remaining API keys before falling back to backoff, and
+/// publishes the resend time so the status line can count it down.
+fn send_with_retry(client: &Client, ctx: &RequestContext, body: &Value) -> Result<Value> {
+    let RequestContext {
+        settings,
+        keys,
+        wire_log,
+        ..
+    } = *ctx;
+    let policy = settings.retry;
+    let mut retry = 0;
+    let mut rotations = 0;
+    loop {
+        let err = match send_once(client, settings, keys.current(), body, wire_log) {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        if matches!(err, Error::RateLimited { .. })
+            && rotations + 1 < keys.len()
+            && keys.rotate()
+        {
+            rotations += 1;
+            continue;
+        }
+        retry += 1;
+        if !err.is_retryable() || retry >= policy.max_attempts.max(1) {
+            return Err(err);
+        }
+        let delay = match &err {
+            Error::RateLimited {
+                retry_after: Some(wait),
+                ..
+            } => *wait,
+            _ => policy.delay(retry),
+        };
+        let rate_limited = matches!(err, Error::RateLimited { .. });
+        if rate_limited {
+            ctx.set_retry_at(Some(Instant::now() + delay));
+        }
+        thread::sleep(delay);
+        if rate_limited {
+            ctx.set_retry_at(None);
+        }
+    }
+}
+
+fn send_once(
+    client: &Client,
+    settings: &Settings,
+    api_key: &str,
+    body: &Value,
+    wire_log: Option<&WireLog>,
+) -> Result<Value> {
+    let url = settings.responses_url();
+    if let Some(log) = wire_log {
+        log.request("POST", &url, Some(body));
+    }
+    let request = client.post(&url).json(body);
+    let result = receive_json(settings, settings.authorize(request, api_key));
+    log_response(wire_log, &url, &result);
+    result
+}
+
+fn receive_json(settings: &Settings, request: RequestBuilder) -> Result<Value> {
+    let response = request.send()?;
+    if !response.status().is_success() {
+        return Err(Error::from_response(settings.provider.label(), response));
+    }
+    Ok(response.json()?)
+}
+
+fn log_response(wire_log: Option<&WireLog>, url: &str, result: &Result<Value>) {
+    if let Some(log) = wire_log {
+        log.response(url, result.as_ref().map_err(|err| err.to_string()));
+    }
+}
+
+fn embed(
+    settings: &Settings,
+    api_key: &str,
+    wire_log: Option<&WireLog>,
+    text: &str,
+) -> Result<Vec<f32>> {
+    let client = settings
+        .client_builder()?
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let url = settings.embeddings_url();
+    let body = json!({ "model": settings.embedding_request_model(), "input": text });
+    if let Some(log) = wire_log {
+        log.request("POST", &url, Some(&body));
+    }
+    let request = client.post(&url).json(&body);
+    let result = receive_json(settings, settings.authorize(request, api_key));
+    log_response(wire_log, &url, &result);
+    let value = result?;
+    value
+        .pointer("/data/0/embedding")
+        .and_then(|v| v.as_array())
+        .map(|values| values.iter().filter_map(|x| x.as_f64()).map(|x| x as f32).collect())
+        .ok_or_else(|| Error::Parse("Embedding response has no vector.".to_string()))
+}
+
+fn count_tokens(
+    settings: &Settings,
+    api_key: &str,
+    wire_log: Option<&WireLog>,
+    items: &[Value],
+) -> Result<u64> {
+    let url = settings.input_tokens_url().ok_or_else(|| {
+        Error::Config(format!("{} cannot count tokens.", settings.provider.label()))
+    })?;
+    let client = settings
+        .client_builder()?
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let body = json!({ "model": settings.request_model(), "input": items });
+    if let Some(log) = wire_log {
+        log.request("POST", &url, Some(&body));
+    }
+    let request = client.post(&url).json(&body);
+    let result = receive_json(settings, settings.authorize(request, api_key));
+    log_response(wire_log, &url, &result);
+    result?
+        .get("input_tokens")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| Error::Parse("Token count response has no input_tokens.".to_string()))
+}
+
+fn list_models(
+    settings: &Settings,
+    api_key: &str,
+    wire_log: Option<&WireLog>,
+) -> Result<Vec<String>> {
+    let client = settings
+        .client_builder()?
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let url = settings.models_url();
+    if let Some(log) = wire_log {
+        log.request("GET", &url, None);
+    }
+    let request = client.get(&url);
+    let result = receive_json(settings, settings.authorize(request, api_key));
+    log_response(wire_log, &url, &result);
+    let value = result?;
+    let data = value
+        .get("data")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| Error::Parse("Model list response has no data array.".to_string()))?;
+    let mut models: Vec<String> = data
+        .iter()
+        .filter_map(|item| item.get("id").and_then(|v| v.as_str()))
+        .map(|id| id.to_string())
+        .collect();
+    models.sort();
+    models.dedup();
+    Ok(models)
+}
+
+/// What a Responses API reply contained.
+struct ExtractedOutput {
+    text: Option<String>,
+    /// Raw output items, appended to history so the next turn sees them verbatim.
+    items: Vec<Value>,
+    /// `function_call` items, also present in `items`.
+    calls: Vec<Value>,
+    debug_summary: String,
+}
+
+fn extract_output(value: &Value) -> ExtractedOutput {
+    let (text, items, debug_summary) = extract_output_text_and_items(value);
+    let calls = items
+        .iter()
+        .filter(|item| item.get("type").and_then(|v| v.as_str()) == Some("function_call"))
+        .cloned()
+        .collect();
+    ExtractedOutput {
+        // Tool-only replies can carry an empty `output_text`.
+        text: text.filter(|text| !text.trim().is_empty()),
+        items,
+        calls,
+        debug_summary,
+    }
 }
 
 fn extract_output_text_and_items(value: &Value) -> (Option<String>, Vec<Value>, String) {
@@ -150,15 +838,18 @@ fn extract_output_text_and_items(value: &Value) -> (Option<String>, Vec<Value>,
             for part in content {
                 if let Some(ty) = part.get("type").and_then(|v| v.as_str()) {
                     content_types.push(ty.to_string());
-                    if ty == "refusal" {
-                        if let Some(text) = part.get("refusal").and_then(|v| v.as_str()) {
-                            refusals.push(text.to_string());
-                        }
+                    if ty == "refusal"
+                        && let Some(text) = part.get("refusal").and_then(|v| v.as_str())
+                    {
+                        refusals.push(text.to_string());
                     }
                 }
             }
         }
-        if content_types.is_empty() {
+        if item_type == "function_call" {
+            let name = item.get("name").and_then(|v| v.as_str()).unwrap_or("?");
+            debug_lines.push(format!("output: type=function_call name={name}"));
+        } else if content_types.is_empty() {
             debug_lines.push(format!("output: type={item_type} role={item_role} content=[]"));
         } else {
             debug_lines.push(format!(
@@ -170,10 +861,10 @@ fn extract_output_text_and_items(value: &Value) -> (Option<String>, Vec<Value>,
         if let Some(content) = item.get("content").and_then(|v| v.as_array()) {
             for part in content {
                 let part_type = part.get("type").and_then(|v| v.as_str());
-                if matches!(part_type, Some("output_text") | Some("text")) {
-                    if let Some(text) = part.get("text").and_then(|v| v.as_str()) {
-                        texts.push(text.to_string());
-                    }
+                if matches!(part_type, Some("output_text") | Some("text"))
+                    && let Some(text) = part.get("text").and_then(|v| v.as_str())
+                {
+                    texts.push(text.to_string());
                 }
             }
         }
@@ -1,10 +1,50 @@
-use std::sync::mpsc::Receiver;
-use std::time::Instant;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use arboard::Clipboard;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use ratatui::layout::Rect;
+use serde::Serialize;
 use serde_json::{json, Value};
 
-use crate::config::MAX_HISTORY_ITEMS;
+use crate::achievements::{Achievements, Progress};
+use crate::api::{system_prompt, Exchange};
+use crate::capabilities::Capabilities;
+use crate::chapters::{self, Chapter};
+use crate::character::{CharacterSheet, CreationStep};
+use crate::checks::SkillCheck;
+use crate::clock::{self, START_MINUTES};
+use crate::conflicts::{Change, Claims, Conflict};
+use crate::config::{
+    Difficulty, ModelPrice, PaneLayout, SceneStyle, SettingChange, Settings, WordTarget,
+};
+use crate::crafting::{Crafted, Recipe};
+use crate::dice::DiceExpr;
+use crate::encounters::EncounterTable;
+use crate::ending::{self, EPILOGUE_NOTE};
+use crate::equipment::{Equipment, Slot};
+use crate::flags::{parse_assignment, FlagValue};
+use crate::gallery::{self, GalleryScene, GalleryView, SceneArt};
+use crate::graphics::{Image, Protocol};
+use crate::items::Item;
+use crate::keymap::Keymap;
+use crate::line_editor::LineEditor;
+use crate::map::{check_travel, is_travel, Exit};
+use crate::memory::Memory;
+use crate::palette::CommandPalette;
+use crate::quests::{Quest, QuestStatus};
+use crate::scenarios::{self, Scenario};
+use crate::scene_cache;
+use crate::sketch;
+use crate::structured::{Offer, StateChanges, StructuredTurn};
+use crate::tasks::{TaskKind, TaskRegistry};
+use crate::theme::{self, Theme};
+use crate::tokens::{TokenEstimator, Usage};
+use crate::tools::ToolCall;
+use crate::weather::Weather;
+use crate::wiki::WikiView;
 
 #[derive(Clone, Copy)]
 pub(crate) enum LogKind {
@@ -14,134 +54,2081 @@ pub(crate) enum LogKind {
     Error,
 }
 
+#[derive(Clone)]
 pub(crate) struct LogEntry {
     pub(crate) kind: LogKind,
     pub(crate) speaker: Option<String>,
     pub(crate) text: String,
+    /// The 1-based turn being played when the entry was logged.
+    pub(crate) turn: u32,
+}
+
+/// Which log entries the Story pane shows; set with `/filter` or cycled with Ctrl+F.
+#[derive(Clone, PartialEq)]
+pub(crate) enum LogFilter {
+    Narrator,
+    Player,
+    /// System and error lines.
+    System,
+    /// One NPC's dialogue, matched case-insensitively.
+    Speaker(String),
+}
+
+impl LogFilter {
+    pub(crate) fn parse(text: &str) -> Self {
+        let text = text.trim();
+        match text.to_lowercase().as_str() {
+            "narrator" => Self::Narrator,
+            "you" | "player" => Self::Player,
+            "system" => Self::System,
+            _ => Self::Speaker(text.to_string()),
+        }
+    }
+
+    pub(crate) fn label(&self) -> &str {
+        match self {
+            Self::Narrator => "Narrator",
+            Self::Player => "You",
+            Self::System => "System",
+            Self::Speaker(name) => name,
+        }
+    }
+
+    pub(crate) fn matches(&self, entry: &LogEntry) -> bool {
+        let speaker = entry.speaker.as_deref();
+        match self {
+            Self::Narrator => {
+                matches!(entry.kind, LogKind::Assistant)
+                    && speaker.is_none_or(is_narrator_label)
+            }
+            Self::Player => matches!(entry.kind, LogKind::User),
+            Self::System => matches!(entry.kind, LogKind::System | LogKind::Error),
+            Self::Speaker(name) => {
+                matches!(entry.kind, LogKind::Assistant)
+                    && speaker.is_some_and(|speaker| speaker.trim().eq_ignore_ascii_case(name))
+            }
+        }
+    }
+}
+
+/// Progress of the typewriter reveal over log entries from `from` onwards.
+pub(crate) struct Reveal {
+    pub(crate) from: usize,
+    pub(crate) shown: usize,
+}
+
+/// A wipe from the previous scene art to the new one, advanced on draw ticks.
+pub(crate) struct SceneTransition {
+    pub(crate) previous: String,
+    pub(crate) step: u8,
+}
+
+impl SceneTransition {
+    /// Ticks the wipe takes; the tick is 200ms.
+    pub(crate) const STEPS: u8 = 5;
+}
+
+/// The pane that scroll keys act on; Tab cycles it.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum Focus {
+    #[default]
+    Input,
+    Story,
+    Scene,
+}
+
+/// Which list the lower half of the side panel shows.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum SideTab {
+    #[default]
+    Inventory,
+    Quests,
+    Npcs,
+    Flags,
+}
+
+impl SideTab {
+    pub(crate) const ALL: [Self; 4] = [Self::Inventory, Self::Quests, Self::Npcs, Self::Flags];
+
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "inventory" | "items" => Some(Self::Inventory),
+            "quests" => Some(Self::Quests),
+            "npcs" | "people" => Some(Self::Npcs),
+            "flags" => Some(Self::Flags),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Inventory => "Inventory",
+            Self::Quests => "Quests",
+            Self::Npcs => "NPCs",
+            Self::Flags => "Flags",
+        }
+    }
+
+    pub(crate) fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|tab| *tab == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// A tab operation asked for by a key or `/tab`, carried out by the main loop.
+pub(crate) enum TabRequest {
+    New,
+    Close,
+    Next,
+    /// Zero-based tab index.
+    Switch(usize),
+}
+
+const MAIN_BRANCH: &str = "main";
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+const BANNER_DURATION: Duration = Duration::from_secs(6);
+const STARTING_HP: i32 = 10;
+const AFFINITY_LIMIT: i32 = 10;
+const STARTING_GOLD: u32 = 20;
+/// Consecutive off-target replies before a corrective note is sent.
+const WORD_DRIFT_STREAK: u32 = 2;
+
+pub(crate) struct Toast {
+    pub(crate) text: String,
+    pub(crate) shown_at: Instant,
+}
+
+#[derive(Clone, Serialize)]
+pub(crate) struct GameState {
+    /// Unix time the game started; keeps files from different games apart.
+    pub(crate) game_id: u64,
+    pub(crate) turn: u32,
+    /// Setting from the scenario picked at `/new`, sent ahead of the history; empty for custom games.
+    pub(crate) premise: String,
+    /// Replaces the default system prompt for this game once edited with `/prompt`.
+    pub(crate) system_prompt: Option<String>,
+    /// In-game minutes since midnight of day 1; see `clock`.
+    pub(crate) minutes: u32,
+    pub(crate) weather: Weather,
+    pub(crate) location: String,
+    /// Every location entered this game, in first-visit order.
+    pub(crate) visited: Vec<String>,
+    /// Directed exits between places, recorded as the player moves or reported by the model.
+    pub(crate) exits: Vec<Exit>,
+    pub(crate) inventory: Vec<Item>,
+    /// Items gained over the game, counting each unit of a stack.
+    pub(crate) items_collected: u32,
+    pub(crate) equipment: Equipment,
+    pub(crate) flags: BTreeMap<String, FlagValue>,
+    pub(crate) active_speaker: Option<String>,
+    pub(crate) character: CharacterSheet,
+    pub(crate) hp: i32,
+    pub(crate) max_hp: i32,
+    /// How each NPC feels about the player, from -10 (hostile) to 10 (devoted).
+    pub(crate) affinity: BTreeMap<String, i32>,
+    pub(crate) quests: Vec<Quest>,
+    pub(crate) gold: u32,
+    /// What is for sale where the player last saw a merchant; replaced by each new offer list.
+    pub(crate) offers: Vec<Offer>,
+    /// Pinned world facts (`/lore add`), sent every turn so history trimming cannot drop them.
+    pub(crate) lore: Vec<String>,
+    /// Characters who have spoken to the player.
+    pub(crate) met: BTreeSet<String>,
+    /// Rolling summary of history trimmed out of context, sent ahead of the history.
+    pub(crate) summary: String,
+    /// The chapter in progress, from 1.
+    pub(crate) chapter: u32,
+    pub(crate) chapters: Vec<Chapter>,
+}
+
+impl GameState {
+    pub(crate) fn new() -> Self {
+        Self {
+            game_id: gallery::unix_time(),
+            turn: 0,
+            premise: String::new(),
+            system_prompt: None,
+            minutes: START_MINUTES,
+            weather: Weather::default(),
+            location: "Unknown".to_string(),
+            visited: Vec::new(),
+            exits: Vec::new(),
+            inventory: Vec::new(),
+            items_collected: 0,
+            equipment: Equipment::default(),
+            flags: BTreeMap::new(),
+            active_speaker: None,
+            character: CharacterSheet::default(),
+            hp: STARTING_HP,
+            max_hp: STARTING_HP,
+            affinity: BTreeMap::new(),
+            quests: Vec::new(),
+            gold: STARTING_GOLD,
+            offers: Vec::new(),
+            lore: Vec::new(),
+            met: BTreeSet::new(),
+            summary: String::new(),
+            chapter: 1,
+            chapters: Vec::new(),
+        }
+    }
+
+    pub(crate) fn set_location(&mut self, location: &str) {
+        let from = std::mem::replace(&mut self.location, location.to_string());
+        if self.visited.contains(&from) {
+            self.add_exit(&from, location, None);
+        }
+        if !self.visited.iter().any(|place| place == location) {
+            self.visited.push(location.to_string());
+        }
+    }
+
+    pub(crate) fn exits_from<'a>(&'a self, place: &'a str) -> impl Iterator<Item = &'a Exit> {
+        self.exits.iter().filter(move |exit| exit.from == place)
+    }
+
+    /// Records an exit, filling in the direction of an existing one; returns whether it is new.
+    pub(crate) fn add_exit(&mut self, from: &str, to: &str, direction: Option<&str>) -> bool {
+        let (from, to) = (from.trim(), to.trim());
+        let direction = direction.map(str::trim).filter(|d| !d.is_empty());
+        if from.is_empty() || to.is_empty() || from == to {
+            return false;
+        }
+        if let Some(exit) = self.exits.iter_mut().find(|e| e.from == from && e.to == to) {
+            if exit.direction.is_none() {
+                exit.direction = direction.map(str::to_lowercase);
+            }
+            return false;
+        }
+        self.exits.push(Exit {
+            from: from.to_string(),
+            to: to.to_string(),
+            direction: direction.map(str::to_lowercase),
+        });
+        true
+    }
+
+    /// Returns the NPC's new score.
+    pub(crate) fn change_affinity(&mut self, npc: &str, amount: i32) -> i32 {
+        let score = self.affinity.entry(npc.trim().to_string()).or_insert(0);
+        *score = (*score + amount).clamp(-AFFINITY_LIMIT, AFFINITY_LIMIT);
+        *score
+    }
+
+    /// Adds an active quest unless one with the same title exists.
+    pub(crate) fn add_quest(&mut self, title: &str, objective: &str) -> bool {
+        let title = title.trim();
+        if title.is_empty() || self.quests.iter().any(|q| q.title.eq_ignore_ascii_case(title)) {
+            return false;
+        }
+        self.quests.push(Quest {
+            title: title.to_string(),
+            objective: objective.trim().to_string(),
+            status: QuestStatus::Active,
+        });
+        true
+    }
+
+    /// Completes the quest with a matching title or, failing that, 1-based list number.
+    pub(crate) fn complete_quest(&mut self, key: &str) -> Option<String> {
+        let key = key.trim();
+        let index = self
+            .quests
+            .iter()
+            .position(|q| q.title.eq_ignore_ascii_case(key))
+            .or_else(|| key.parse::<usize>().ok()?.checked_sub(1))?;
+        let quest = self.quests.get_mut(index)?;
+        if quest.status == QuestStatus::Completed {
+            return None;
+        }
+        quest.status = QuestStatus::Completed;
+        Some(quest.title.clone())
+    }
+
+    // Model-driven updates below ignore blanks and no-ops and report whether anything changed.
+
+    pub(crate) fn move_to(&mut self, location: &str) -> bool {
+        let location = location.trim();
+        if location.is_empty() || location == self.location {
+            return false;
+        }
+        self.set_location(location);
+        true
+    }
+
+    pub(crate) fn item(&self, name: &str) -> Option<&Item> {
+        self.inventory.iter().find(|item| item.is(name))
+    }
+
+    pub(crate) fn gain_item(&mut self, name: &str) -> bool {
+        self.gain(Item::new(name))
+    }
+
+    /// Adds a newly acquired `item`, counting it toward `items_collected`.
+    pub(crate) fn gain(&mut self, item: Item) -> bool {
+        let qty = item.qty;
+        let gained = self.stow(item);
+        if gained {
+            self.items_collected = self.items_collected.saturating_add(qty);
+        }
+        gained
+    }
+
+    /// Adds `item` to the inventory, stacking it onto an existing item of the same name.
+    /// Items only moving around, like unequipped gear, go here rather than `gain`.
+    pub(crate) fn stow(&mut self, item: Item) -> bool {
+        if item.name.is_empty() || item.qty == 0 {
+            return false;
+        }
+        match self.inventory.iter_mut().find(|i| i.is(&item.name)) {
+            Some(existing) => existing.absorb(item),
+            None => self.inventory.push(item),
+        }
+        true
+    }
+
+    /// Removes one of `name` from its stack, dropping the stack when it runs out.
+    /// Equipped items are lost only when none are carried.
+    pub(crate) fn lose_item(&mut self, name: &str) -> bool {
+        let Some(pos) = self.inventory.iter().position(|item| item.is(name)) else {
+            let Some(slot) = self.equipment.find(name) else {
+                return false;
+            };
+            *self.equipment.slot_mut(slot) = None;
+            return true;
+        };
+        let item = &mut self.inventory[pos];
+        item.qty = item.qty.saturating_sub(1);
+        if item.qty == 0 {
+            self.inventory.remove(pos);
+        }
+        true
+    }
+
+    /// Applies `name` or `name=value`; returns whether the flag changed.
+    pub(crate) fn raise_flag(&mut self, flag: &str) -> bool {
+        let Some((name, value)) = parse_assignment(flag) else {
+            return false;
+        };
+        self.flags.insert(name, value.clone()) != Some(value)
+    }
+
+    pub(crate) fn clear_flag(&mut self, flag: &str) -> bool {
+        let name = flag.split_once('=').map_or(flag, |(name, _)| name);
+        self.flags.remove(name.trim()).is_some()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct WordStats {
+    pub(crate) replies: u32,
+    pub(crate) total_words: u64,
+    pub(crate) within: u32,
+    pub(crate) too_long: u32,
+    pub(crate) too_short: u32,
+    pub(crate) corrections: u32,
+    long_streak: u32,
+    short_streak: u32,
+}
+
+/// Per-session totals for the `/stats` dashboard.
+#[derive(Default)]
+pub(crate) struct SessionStats {
+    pub(crate) turns: u32,
+    pub(crate) input_tokens: u64,
+    pub(crate) output_tokens: u64,
+    pub(crate) total_latency: Duration,
+    /// USD spent on priced turns.
+    pub(crate) cost: f64,
+    /// Some turn had no usage or no price, so `cost` is a lower bound.
+    pub(crate) unpriced: bool,
+}
+
+impl SessionStats {
+    pub(crate) fn record_turn(
+        &mut self,
+        latency: Duration,
+        usage: Option<&Usage>,
+        price: Option<ModelPrice>,
+    ) {
+        self.turns += 1;
+        self.total_latency += latency;
+        if let Some(usage) = usage {
+            self.input_tokens += usage.input_tokens;
+            self.output_tokens += usage.output_tokens;
+        }
+        match (usage, price) {
+            (Some(usage), Some(price)) => {
+                self.cost += (usage.input_tokens as f64 * price.input
+                    + usage.output_tokens as f64 * price.output)
+                    / 1_000_000.0;
+            }
+            _ => self.unpriced = true,
+        }
+    }
+
+    /// Session cost for display, e.g. `$0.0123`, or `$?` when nothing could be priced.
+    pub(crate) fn cost_label(&self) -> String {
+        match (self.cost > 0.0, self.unpriced) {
+            (false, true) => "$?".to_string(),
+            (true, true) => format!("${:.4}+", self.cost),
+            _ => format!("${:.4}", self.cost),
+        }
+    }
+
+    pub(crate) fn average_latency(&self) -> Duration {
+        self.total_latency.checked_div(self.turns).unwrap_or_default()
+    }
+}
+
+/// Overlay listing models returned by `/models`.
+pub(crate) struct ModelPicker {
+    pub(crate) models: Vec<String>,
+    pub(crate) selected: usize,
+}
+
+/// The values the settings overlay edits.
+#[derive(Clone, PartialEq)]
+pub(crate) struct SettingsDraft {
+    pub(crate) model: String,
+    pub(crate) theme: String,
+    pub(crate) timeout_secs: u64,
+    pub(crate) show_scene: bool,
+    pub(crate) difficulty: Difficulty,
+}
+
+/// Overlay opened by the settings key; Enter writes the changed values to the config file.
+pub(crate) struct SettingsMenu {
+    pub(crate) selected: usize,
+    pub(crate) draft: SettingsDraft,
+    saved: SettingsDraft,
+    themes: Vec<String>,
+}
+
+impl SettingsMenu {
+    pub(crate) const ROWS: [&'static str; 5] =
+        ["Model", "Theme", "Request timeout", "Scene pane", "Difficulty"];
+    const TIMEOUT_STEP: u64 = 10;
+
+    pub(crate) fn new(settings: &Settings) -> Self {
+        let draft = SettingsDraft {
+            model: settings.request_model().to_string(),
+            theme: settings.theme.clone(),
+            timeout_secs: settings.timeout_secs,
+            show_scene: settings.show_scene,
+            difficulty: settings.difficulty,
+        };
+        Self {
+            selected: 0,
+            saved: draft.clone(),
+            draft,
+            themes: theme::names(&settings.themes),
+        }
+    }
+
+    /// The selected row's value as shown in the overlay.
+    pub(crate) fn value(&self, row: usize) -> String {
+        let draft = &self.draft;
+        match row {
+            0 => draft.model.clone(),
+            1 => draft.theme.clone(),
+            2 => format!("{}s", draft.timeout_secs),
+            3 => if draft.show_scene { "on" } else { "off" }.to_string(),
+            _ => draft.difficulty.label().to_string(),
+        }
+    }
+
+    /// Steps the selected value forward or back; the model is typed instead.
+    pub(crate) fn adjust(&mut self, forward: bool) {
+        let draft = &mut self.draft;
+        match self.selected {
+            1 => draft.theme = cycle(&self.themes, &draft.theme, forward),
+            2 => {
+                draft.timeout_secs = if forward {
+                    draft.timeout_secs + Self::TIMEOUT_STEP
+                } else {
+                    draft.timeout_secs.saturating_sub(Self::TIMEOUT_STEP).max(Self::TIMEOUT_STEP)
+                };
+            }
+            3 => draft.show_scene = !draft.show_scene,
+            4 => draft.difficulty = cycle(&Difficulty::ALL, &draft.difficulty, forward),
+            _ => {}
+        }
+    }
+
+    pub(crate) fn model_changed(&self) -> bool {
+        self.draft.model != self.saved.model
+    }
+
+    /// One change per edited value, in row order.
+    pub(crate) fn changes(&self) -> Vec<SettingChange> {
+        let (draft, saved) = (&self.draft, &self.saved);
+        let mut changes = Vec::new();
+        let model = draft.model.trim();
+        if model != saved.model && !model.is_empty() {
+            changes.push(SettingChange::Model(model.to_string()));
+        }
+        if draft.theme != saved.theme {
+            changes.push(SettingChange::Theme(draft.theme.clone()));
+        }
+        if draft.timeout_secs != saved.timeout_secs {
+            changes.push(SettingChange::TimeoutSecs(draft.timeout_secs));
+        }
+        if draft.show_scene != saved.show_scene {
+            changes.push(SettingChange::ShowScene(draft.show_scene));
+        }
+        if draft.difficulty != saved.difficulty {
+            changes.push(SettingChange::Difficulty(draft.difficulty));
+        }
+        changes
+    }
+}
+
+/// The entry after (or before) `current`, wrapping; the first when `current` is not listed.
+fn cycle<T: Clone + PartialEq>(options: &[T], current: &T, forward: bool) -> T {
+    let Some(index) = options.iter().position(|option| option == current) else {
+        return options[0].clone();
+    };
+    let next = if forward {
+        (index + 1) % options.len()
+    } else {
+        (index + options.len() - 1) % options.len()
+    };
+    options[next].clone()
+}
+
+/// Game state saved when the player acts, restored after a death or for `/retry`.
+pub(crate) struct Checkpoint {
+    state: GameState,
+    history: Vec<Vec<Value>>,
+    log_len: usize,
+}
+
+/// A parallel timeline saved by `/fork`; the active one lives in the App's own fields.
+pub(crate) struct Branch {
+    pub(crate) name: String,
+    state: GameState,
+    history: Vec<Vec<Value>>,
+    log: Vec<LogEntry>,
+    /// Chunks still waiting to be folded into this branch's summary.
+    trimmed: Vec<Vec<Value>>,
+    memory: Memory,
+}
+
+/// Overlay listing branches for `/branches`.
+pub(crate) struct BranchPicker {
+    pub(crate) selected: usize,
+}
+
+/// Overlay listing scenarios for `/new`; the last row starts a blank custom game.
+pub(crate) struct ScenarioPicker {
+    pub(crate) selected: usize,
+}
+
+/// Overlay toggled with F12 showing the last turn's raw JSON.
+#[derive(Default)]
+pub(crate) struct Inspector {
+    pub(crate) scroll: u16,
 }
 
-#[derive(Clone)]
-pub(crate) struct GameState {
-    pub(crate) turn: u32,
-    pub(crate) location: String,
-    pub(crate) inventory: Vec<String>,
-    pub(crate) flags: Vec<String>,
-    pub(crate) active_speaker: Option<String>,
-}
+/// Full-screen transcript opened with `/read`.
+#[derive(Default)]
+pub(crate) struct Reader {
+    pub(crate) scroll: u16,
+    /// Rows on screen at the last draw, for paging.
+    pub(crate) rows: u16,
+}
+
+pub(crate) struct App {
+    pub(crate) input: LineEditor,
+    pub(crate) log: Vec<LogEntry>,
+    pub(crate) history: Vec<Vec<Value>>,
+    pub(crate) scroll: u16,
+    /// Keep the Story pane on the newest lines; cleared when the player scrolls up.
+    pub(crate) follow: bool,
+    /// Log length when the bottom was last in view, to flag unread lines.
+    pub(crate) seen_log: usize,
+    /// Characters revealed per tick; 0 disables the typewriter.
+    pub(crate) typewriter_chars: u32,
+    pub(crate) reveal: Option<Reveal>,
+    pub(crate) busy: bool,
+    pub(crate) pending_input: Option<String>,
+    pub(crate) last_sent_input: Option<String>,
+    pub(crate) state: GameState,
+    pub(crate) status: String,
+    pub(crate) thinking_started: Option<Instant>,
+    /// Set while narration waits out a 429 before resending.
+    pub(crate) retry_at: Option<Instant>,
+    pub(crate) toasts: Vec<Toast>,
+    /// Place or chapter title lettered over the Scene pane for a few seconds.
+    pub(crate) banner: Option<Toast>,
+    /// Location the last arrival banner was shown for.
+    arrived_at: Option<String>,
+    pub(crate) word_target: Option<WordTarget>,
+    pub(crate) word_stats: WordStats,
+    pub(crate) session_stats: SessionStats,
+    /// Whether the `/stats` dashboard overlay is open.
+    pub(crate) show_stats: bool,
+    pub(crate) choice_mode: bool,
+    /// Numbered actions suggested with the last reply in choice mode.
+    pub(crate) choices: Vec<String>,
+    pub(crate) model: String,
+    pub(crate) price: Option<ModelPrice>,
+    pub(crate) capabilities: Capabilities,
+    pub(crate) pending_settings: Vec<SettingChange>,
+    pub(crate) models_requested: bool,
+    /// Latest scene art shown in the Scene pane.
+    pub(crate) scene_ascii: Option<String>,
+    pub(crate) scene_transition: Option<SceneTransition>,
+    /// Latest scene picture, drawn instead of the text art when graphics are on.
+    pub(crate) scene_image: Option<Image>,
+    /// Bumped with each new picture so the main loop knows to send it again.
+    pub(crate) scene_image_id: u64,
+    /// Where the picture belongs on the last draw; unset while hidden or covered.
+    pub(crate) scene_image_area: Option<Rect>,
+    /// A scene picture request failed; text art is drawn for the rest of the session.
+    pub(crate) pictures_failed: bool,
+    /// The terminal's inline image protocol, if scene art should be a picture.
+    pub(crate) graphics: Option<Protocol>,
+    /// A scene art request waiting to be sent by the main loop.
+    pub(crate) scene_pending_response: bool,
+    /// Turn and place the art being drawn was asked for, recorded with it in the gallery.
+    scene_asked: Option<(u32, String)>,
+    /// Scene cache key (place and time of day) the current art was requested for.
+    pub(crate) scene_key: Option<String>,
+    /// Skips the scene cache for the pending request, for an explicit redraw.
+    pub(crate) scene_fresh: bool,
+    pub(crate) scene_style: SceneStyle,
+    /// Cache key of the text art being drawn now, stored once it arrives.
+    pub(crate) scene_drawing: Option<String>,
+    /// `/scene image` is waiting to be sent by the main loop.
+    pub(crate) illustration_requested: bool,
+    pub(crate) show_scene: bool,
+    pub(crate) tab_request: Option<TabRequest>,
+    /// Opened on first `/copy` and kept, since on X11 the text is served by its owner.
+    clipboard: Option<Clipboard>,
+    pub(crate) tasks: TaskRegistry,
+    pub(crate) model_picker: Option<ModelPicker>,
+    pub(crate) settings_menu: Option<SettingsMenu>,
+    pub(crate) reader: Option<Reader>,
+    /// The quit confirmation is showing.
+    pub(crate) confirm_quit: bool,
+    /// The player agreed to quit this game; other tabs with unsaved progress still ask.
+    pub(crate) leaving: bool,
+    /// The quit confirmation is showing for `/tab close`, closing only this tab.
+    pub(crate) closing: bool,
+    /// Failures reported by turn hooks this tab ran, shown as toasts in this tab.
+    pub(crate) hook_tx: Sender<String>,
+    hook_rx: Receiver<String>,
+    /// State the player set by hand, checked against the narrator's updates.
+    pub(crate) claims: Claims,
+    /// Narrator updates that contradict `claims`, settled one at a time.
+    pub(crate) conflicts: VecDeque<Conflict>,
+    /// Log length at the last `/export`, to tell whether quitting loses anything.
+    pub(crate) exported_log: usize,
+    pub(crate) side_tab: SideTab,
+    /// Set by the settings key; the main loop opens the overlay with the current settings.
+    pub(crate) settings_requested: bool,
+    /// Set when a reply arrives, for the main loop to notify a player who looked away.
+    pub(crate) turn_finished: bool,
+    /// Set by the line input key; the main loop reads a line with the TUI suspended.
+    pub(crate) line_input_requested: bool,
+    pub(crate) wiki: Option<WikiView>,
+    /// Generated scene art in the order it was shown.
+    pub(crate) scenes: Vec<GalleryScene>,
+    pub(crate) gallery: Option<GalleryView>,
+    pub(crate) palette: Option<CommandPalette>,
+    pub(crate) inspector: Option<Inspector>,
+    pub(crate) last_exchange: Option<Exchange>,
+    pub(crate) history_token_budget: u64,
+    /// Chunks trimmed from history that still need folding into the summary.
+    pub(crate) trimmed: Vec<Vec<Value>>,
+    /// Chunks the running summary or chapter task is folding in, requeued if it fails
+    /// or is cancelled.
+    pub(crate) summarizing: Vec<Vec<Value>>,
+    /// Set when summarizing failed; the next trimmed chunk tries again.
+    pub(crate) summary_paused: bool,
+    pub(crate) tokens: TokenEstimator,
+    /// Seed of `rng`, kept so a session's rolls can be reproduced.
+    pub(crate) seed: u64,
+    /// Seed given with `--seed`; new games reuse it instead of drawing a fresh one.
+    fixed_seed: Option<u64>,
+    /// Source of all game randomness (dice, checks); never the model.
+    pub(crate) rng: StdRng,
+    pub(crate) skill_checks: bool,
+    pub(crate) difficulty: Difficulty,
+    pub(crate) encounters: EncounterTable,
+    pub(crate) checkpoint: Option<Checkpoint>,
+    /// Everything as it was just before the last reply was applied, for `/retry`.
+    pub(crate) retry_point: Option<Checkpoint>,
+    /// Name of the active timeline; "main" until the player forks.
+    pub(crate) branch: String,
+    /// Inactive timelines, switched to with `/branches` or `/switch`.
+    pub(crate) branches: Vec<Branch>,
+    pub(crate) branch_picker: Option<BranchPicker>,
+    pub(crate) scenarios: Vec<Scenario>,
+    pub(crate) scenario_picker: Option<ScenarioPicker>,
+    /// The scenario being played; `None` for custom games.
+    pub(crate) scenario: Option<Scenario>,
+    /// Text of the last reply while `/edit` has it open.
+    pub(crate) reply_editor: Option<String>,
+    /// System prompt text while `/prompt` has it open.
+    pub(crate) prompt_editor: Option<String>,
+    /// What the system prompt is when the game has not edited it; follows setting changes.
+    pub(crate) default_prompt: String,
+    /// The character creation question awaiting an answer; Enter on blank skips it.
+    pub(crate) creation: Option<CreationStep>,
+    /// Set when HP reaches zero; the game-over overlay blocks input until the player chooses.
+    pub(crate) game_over: bool,
+    /// Set while the narrator writes the epilogue after `/end` or a model-signaled ending.
+    pub(crate) epilogue_pending: bool,
+    /// Set once the epilogue is in; the ending overlay shows final stats and export.
+    pub(crate) story_over: bool,
+    pub(crate) achievements: Achievements,
+    /// Whether the Map pane is shown beside the Scene pane (`/map`).
+    pub(crate) show_map: bool,
+    pub(crate) log_filter: Option<LogFilter>,
+    /// Turn the Story pane scrolls to on the next draw, from `/goto turn`.
+    pub(crate) goto_turn: Option<u32>,
+    /// Show turn numbers in a gutter beside the Story pane.
+    pub(crate) show_turns: bool,
+    /// Rows the Story pane showed on the last draw, for paging.
+    pub(crate) story_rows: u16,
+    pub(crate) focus: Focus,
+    /// Rows scrolled in the Scene pane's art; clamped when drawn.
+    pub(crate) scene_scroll: u16,
+    pub(crate) scene_rows: u16,
+    pub(crate) layout: PaneLayout,
+    pub(crate) recipes: Vec<Recipe>,
+    pub(crate) quick_actions: Vec<String>,
+    pub(crate) keys: Keymap,
+    pub(crate) theme: Theme,
+    /// Embedded past turns; switched off for the session if embedding fails.
+    pub(crate) memory: Memory,
+    pub(crate) memory_enabled: bool,
+    pub(crate) memory_top_k: usize,
+    /// Set by `/chapter end` until the main loop sends the chapter summary request.
+    pub(crate) chapter_requested: bool,
+    /// Ingredients of a `/craft` waiting to be resolved by the model.
+    pub(crate) craft_requested: Option<(String, String)>,
+}
+
+impl App {
+    pub(crate) fn new(
+        settings: &Settings,
+        capabilities: Capabilities,
+        fixed_seed: Option<u64>,
+    ) -> Self {
+        let seed = fixed_seed.unwrap_or_else(rand::random);
+        let (hook_tx, hook_rx) = mpsc::channel();
+        let mut app = Self {
+            input: LineEditor::default(),
+            log: Vec::new(),
+            history: Vec::new(),
+            scroll: 0,
+            follow: true,
+            seen_log: 0,
+            typewriter_chars: settings.typewriter_chars,
+            reveal: None,
+            busy: false,
+            pending_input: None,
+            last_sent_input: None,
+            state: GameState::new(),
+            status: "Ready".to_string(),
+            thinking_started: None,
+            retry_at: None,
+            toasts: Vec::new(),
+            banner: None,
+            arrived_at: None,
+            word_target: settings.word_target,
+            word_stats: WordStats::default(),
+            session_stats: SessionStats::default(),
+            show_stats: false,
+            choice_mode: settings.choice_mode,
+            choices: Vec::new(),
+            model: settings.request_model().to_string(),
+            price: settings.price(),
+            capabilities,
+            pending_settings: Vec::new(),
+            models_requested: false,
+            scene_ascii: None,
+            scene_transition: None,
+            scene_image: None,
+            scene_image_id: 0,
+            scene_image_area: None,
+            pictures_failed: false,
+            graphics: settings.graphics.protocol(),
+            scene_pending_response: false,
+            scene_asked: None,
+            scene_key: None,
+            scene_fresh: false,
+            scene_style: settings.scene_style,
+            scene_drawing: None,
+            illustration_requested: false,
+            show_scene: settings.show_scene,
+            tab_request: None,
+            clipboard: None,
+            tasks: TaskRegistry::default(),
+            model_picker: None,
+            settings_menu: None,
+            reader: None,
+            confirm_quit: false,
+            leaving: false,
+            closing: false,
+            hook_tx,
+            hook_rx,
+            claims: Claims::default(),
+            conflicts: VecDeque::new(),
+            exported_log: 0,
+            side_tab: SideTab::default(),
+            settings_requested: false,
+            line_input_requested: false,
+            turn_finished: false,
+            wiki: None,
+            scenes: Vec::new(),
+            gallery: None,
+            palette: None,
+            inspector: None,
+            last_exchange: None,
+            history_token_budget: settings.history_token_budget(),
+            trimmed: Vec::new(),
+            summarizing: Vec::new(),
+            summary_paused: false,
+            tokens: TokenEstimator::default(),
+            seed,
+            fixed_seed,
+            rng: StdRng::seed_from_u64(seed),
+            skill_checks: settings.skill_checks,
+            difficulty: settings.difficulty,
+            encounters: settings.encounters.clone(),
+            checkpoint: None,
+            retry_point: None,
+            branch: MAIN_BRANCH.to_string(),
+            branches: Vec::new(),
+            branch_picker: None,
+            scenarios: scenarios::builtin(),
+            scenario_picker: Some(ScenarioPicker { selected: 0 }),
+            scenario: None,
+            reply_editor: None,
+            prompt_editor: None,
+            default_prompt: system_prompt(settings),
+            creation: None,
+            game_over: false,
+            epilogue_pending: false,
+            story_over: false,
+            achievements: Achievements::load(),
+            show_map: false,
+            log_filter: None,
+            goto_turn: None,
+            show_turns: false,
+            story_rows: 0,
+            focus: Focus::default(),
+            scene_scroll: 0,
+            scene_rows: 0,
+            layout: settings.layout,
+            recipes: settings.recipes.clone(),
+            quick_actions: settings.quick_actions.clone(),
+            keys: settings.keys.clone(),
+            theme: settings.theme(),
+            memory: Memory::default(),
+            memory_enabled: settings.memory.enabled,
+            memory_top_k: settings.memory.top_k,
+            craft_requested: None,
+            chapter_requested: false,
+        };
+        app.push_log(LogKind::System, "Welcome!");
+        app.begin_creation();
+        app
+    }
+
+    pub(crate) fn push_log(&mut self, kind: LogKind, text: impl Into<String>) {
+        self.log.push(LogEntry {
+            kind,
+            speaker: None,
+            text: text.into(),
+            turn: self.state.turn + 1,
+        });
+    }
+
+    pub(crate) fn push_toast(&mut self, text: impl Into<String>) {
+        self.toasts.retain(|toast| toast.shown_at.elapsed() < TOAST_DURATION);
+        self.toasts.push(Toast {
+            text: text.into(),
+            shown_at: Instant::now(),
+        });
+    }
+
+    pub(crate) fn show_banner(&mut self, text: impl Into<String>) {
+        self.banner = Some(Toast {
+            text: text.into(),
+            shown_at: Instant::now(),
+        });
+    }
+
+    pub(crate) fn active_banner(&self) -> Option<&str> {
+        self.banner
+            .as_ref()
+            .filter(|banner| banner.shown_at.elapsed() < BANNER_DURATION)
+            .map(|banner| banner.text.as_str())
+    }
+
+    pub(crate) fn active_toast(&self) -> Option<&Toast> {
+        self.toasts
+            .iter()
+            .rev()
+            .find(|toast| toast.shown_at.elapsed() < TOAST_DURATION)
+    }
+
+    /// Steps the Story pane through Narrator, each NPC heard so far, You, System, then everything.
+    pub(crate) fn cycle_log_filter(&mut self) {
+        let mut filters = vec![LogFilter::Narrator];
+        for entry in &self.log {
+            if let (LogKind::Assistant, Some(speaker)) = (&entry.kind, entry.speaker.as_deref())
+                && !is_narrator_label(speaker)
+                && !filters.iter().any(|filter| filter.label().eq_ignore_ascii_case(speaker.trim()))
+            {
+                filters.push(LogFilter::Speaker(speaker.trim().to_string()));
+            }
+        }
+        filters.extend([LogFilter::Player, LogFilter::System]);
+        let next = match &self.log_filter {
+            None => filters.into_iter().next(),
+            Some(current) => filters
+                .iter()
+                .position(|filter| filter == current)
+                .and_then(|index| filters.get(index + 1).cloned()),
+        };
+        self.set_log_filter(next);
+    }
+
+    /// Label for this game in the tab bar, marked while the narrator is working.
+    pub(crate) fn tab_title(&self) -> String {
+        let name = self.scenario.as_ref().map_or("Custom", |scenario| scenario.name.as_str());
+        if self.busy { format!("{name}*") } else { name.to_string() }
+    }
+
+    /// Moves the Scene/Story divider by `delta` percent.
+    pub(crate) fn resize_scene(&mut self, delta: i16) {
+        let range = PaneLayout::SCENE_PERCENT;
+        let percent = self.layout.scene_percent.saturating_add_signed(delta);
+        self.layout.scene_percent = percent.clamp(*range.start(), *range.end());
+    }
+
+    /// Widens or narrows the side panel by `delta` columns, showing it if hidden.
+    pub(crate) fn resize_side_panel(&mut self, delta: i16) {
+        let range = PaneLayout::SIDE_WIDTH;
+        let width = self.layout.side_width.saturating_add_signed(delta);
+        self.layout.side_width = width.clamp(*range.start(), *range.end());
+        self.layout.side_panel = true;
+    }
+
+    /// Narration of `turn`, or the latest reply when `None`, joined by blank lines.
+    pub(crate) fn narration_text(&self, turn: Option<u32>) -> Option<String> {
+        let mut narration = self
+            .log
+            .iter()
+            .filter(|entry| matches!(entry.kind, LogKind::Assistant));
+        let text = match turn {
+            Some(turn) => narration
+                .filter(|entry| entry.turn == turn)
+                .map(|entry| entry.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            None => narration.next_back()?.text.clone(),
+        };
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Puts `text` on the system clipboard, reporting the outcome as a toast.
+    pub(crate) fn copy_to_clipboard(&mut self, text: String) {
+        let clipboard = match self.clipboard.take() {
+            Some(clipboard) => Ok(clipboard),
+            None => Clipboard::new(),
+        };
+        let result = clipboard.and_then(|mut clipboard| {
+            let result = clipboard.set_text(text.as_str());
+            self.clipboard = Some(clipboard);
+            result
+        });
+        match result {
+            Ok(()) => {
+                let words = text.split_whitespace().count();
+                self.push_toast(format!("Copied {words} words to the clipboard."));
+            }
+            Err(err) => self.push_log(LogKind::Error, format!("Could not copy: {err}")),
+        }
+    }
+
+    /// Moves focus to the next pane, skipping the Scene pane while it is hidden.
+    pub(crate) fn cycle_focus(&mut self) {
+        self.focus = match self.focus {
+            Focus::Input => Focus::Story,
+            Focus::Story if self.show_scene => Focus::Scene,
+            Focus::Story | Focus::Scene => Focus::Input,
+        };
+    }
+
+    /// Scrolls the focused pane by `delta` rows; the Input pane scrolls the story.
+    /// `i32::MIN` and `i32::MAX` jump to the top and bottom.
+    pub(crate) fn scroll_focused(&mut self, delta: i32) {
+        if self.focus == Focus::Scene {
+            self.scene_scroll = match delta {
+                i32::MIN => 0,
+                i32::MAX => u16::MAX,
+                _ => self.scene_scroll.saturating_add_signed(delta.clamp(-9999, 9999) as i16),
+            };
+            return;
+        }
+        match delta {
+            i32::MIN => {
+                self.follow = false;
+                self.scroll = 0;
+            }
+            i32::MAX => self.follow = true,
+            _ => {
+                if delta < 0 {
+                    self.follow = false;
+                }
+                self.scroll = self.scroll.saturating_add_signed(delta.clamp(-9999, 9999) as i16);
+            }
+        }
+    }
+
+    /// Whether a popup has the keyboard instead of the Input pane.
+    pub(crate) fn has_overlay(&self) -> bool {
+        self.confirm_quit
+            || !self.conflicts.is_empty()
+            || self.reader.is_some()
+            || self.inspector.is_some()
+            || self.reply_editor.is_some()
+            || self.prompt_editor.is_some()
+            || self.show_stats
+            || self.scenario_picker.is_some()
+            || self.game_over
+            || self.story_over
+            || self.model_picker.is_some()
+            || self.settings_menu.is_some()
+            || self.branch_picker.is_some()
+            || self.wiki.is_some()
+            || self.gallery.is_some()
+            || self.palette.is_some()
+    }
+
+    /// Switches the side panel's list, showing the panel if hidden.
+    pub(crate) fn set_side_tab(&mut self, tab: SideTab) {
+        self.side_tab = tab;
+        self.layout.side_panel = true;
+    }
+
+    pub(crate) fn set_log_filter(&mut self, filter: Option<LogFilter>) {
+        let message = match &filter {
+            Some(filter) => format!("Showing only {} lines.", filter.label()),
+            None => "Showing the whole story.".to_string(),
+        };
+        self.log_filter = filter;
+        self.follow = true;
+        self.push_toast(message);
+    }
+
+    /// Starts revealing the entries logged from here on, when the typewriter is on.
+    pub(crate) fn begin_reveal(&mut self) {
+        self.reveal = (self.typewriter_chars > 0).then_some(Reveal {
+            from: self.log.len(),
+            shown: 0,
+        });
+    }
+
+    /// Advances the reveal by one tick, ending it once everything is shown.
+    pub(crate) fn tick_reveal(&mut self) {
+        let Some(reveal) = self.reveal.as_mut() else {
+            return;
+        };
+        reveal.shown += self.typewriter_chars as usize;
+        let total: usize = self
+            .log
+            .iter()
+            .skip(reveal.from)
+            .map(|entry| entry.text.chars().count())
+            .sum();
+        if reveal.shown >= total {
+            self.reveal = None;
+        }
+    }
+
+    /// Log entries the Story pane shows, after the speaker filter, with text cut
+    /// to what the typewriter has revealed. Entries not reached yet are left out.
+    pub(crate) fn visible_log(&self) -> Vec<(&LogEntry, &str)> {
+        let mut budget = self.reveal.as_ref().map(|reveal| reveal.shown);
+        let mut visible = Vec::new();
+        for (index, entry) in self.log.iter().enumerate() {
+            let mut text = entry.text.as_str();
+            if let (Some(reveal), Some(left)) = (&self.reveal, budget.as_mut())
+                && index >= reveal.from
+            {
+                if *left == 0 {
+                    break;
+                }
+                let end = text.char_indices().nth(*left).map_or(text.len(), |(i, _)| i);
+                *left -= text[..end].chars().count();
+                text = &text[..end];
+            }
+            if self.log_filter.as_ref().is_none_or(|filter| filter.matches(entry)) {
+                visible.push((entry, text));
+            }
+        }
+        visible
+    }
+
+    pub(crate) fn push_speaker_log(
+        &mut self,
+        kind: LogKind,
+        speaker: impl Into<String>,
+        text: impl Into<String>,
+    ) {
+        let speaker = speaker.into();
+        if matches!(kind, LogKind::Assistant) && speaker != "Narrator" {
+            self.state.met.insert(speaker.clone());
+        }
+        self.log.push(LogEntry {
+            kind,
+            speaker: Some(speaker),
+            text: text.into(),
+            turn: self.state.turn + 1,
+        });
+    }
+
+    pub(crate) fn push_user_log(&mut self, text: impl Into<String>) {
+        self.push_speaker_log(LogKind::User, "You", text);
+    }
+
+    pub(crate) fn push_assistant_reply(&mut self, reply: &str) {
+        let (reply, health) = strip_health_tags(reply);
+        self.push_reply_text(reply.trim());
+        self.change_health(health);
+    }
+
+    fn push_reply_text(&mut self, reply: &str) {
+        if reply.is_empty() {
+            return;
+        }
+
+        let parsed = parse_speaker_lines(reply);
+        if parsed.entries.is_empty() {
+            let sanitized = strip_disallowed_speaker_lines(reply);
+            if !sanitized.trim().is_empty() {
+                self.push_speaker_log(LogKind::Assistant, "Narrator", sanitized.trim());
+                self.state.active_speaker = None;
+            }
+            return;
+        }
+        for entry in parsed.entries {
+            self.push_speaker_log(LogKind::Assistant, entry.speaker, entry.text);
+        }
+
+        if let Some(last_speaker) = parsed.last_speaker {
+            if is_narrator_label(&last_speaker) {
+                self.state.active_speaker = None;
+            } else {
+                self.state.active_speaker = Some(last_speaker);
+            }
+        }
+    }
+
+    /// Logs a schema-constrained reply and applies its state changes.
+    pub(crate) fn push_structured_reply(&mut self, turn: StructuredTurn) {
+        self.choices = if self.choice_mode {
+            let choices = turn.choices.iter().map(|choice| choice.trim());
+            choices.filter(|c| !c.is_empty()).take(3).map(str::to_string).collect()
+        } else {
+            Vec::new()
+        };
+        let (narration, health) = strip_health_tags(&turn.narration);
+        let narration = narration.trim();
+        if !narration.is_empty() {
+            self.push_speaker_log(LogKind::Assistant, "Narrator", narration);
+            self.state.active_speaker = None;
+        }
+        for line in &turn.dialogue {
+            let speaker = line.speaker.trim();
+            let text = line.text.trim();
+            if speaker.is_empty() || text.is_empty() || is_disallowed_speaker(speaker) {
+                continue;
+            }
+            self.push_speaker_log(LogKind::Assistant, speaker, text);
+            self.state.active_speaker = (!is_narrator_label(speaker)).then(|| speaker.to_string());
+        }
+
+        self.apply_state_changes(&turn.state_changes);
+        self.change_health(health);
+    }
+
+    /// Merges model-reported state changes and logs what actually changed.
+    pub(crate) fn apply_state_changes(&mut self, changes: &StateChanges) {
+        let proposed = changes
+            .location
+            .iter()
+            .map(|place| Change::SetLocation(place.trim().to_string()))
+            .chain(changes.add_items.iter().map(|name| Change::AddItem(Item::new(name))))
+            .chain(changes.remove_items.iter().map(|name| Change::RemoveItem(name.clone())))
+            .chain(changes.set_flags.iter().map(|flag| Change::SetFlag(flag.clone())))
+            .chain(changes.clear_flags.iter().map(|flag| Change::ClearFlag(flag.clone())));
+        let mut notes: Vec<String> = proposed.filter_map(|change| self.propose(change)).collect();
+        for quest in &changes.new_quests {
+            if self.state.add_quest(&quest.title, &quest.objective) {
+                notes.push(format!("new quest {}", quest.title.trim()));
+            }
+        }
+        for title in &changes.completed_quests {
+            if let Some(title) = self.state.complete_quest(title) {
+                notes.push(format!("completed {title}"));
+            }
+        }
+        if changes.gold_change != 0 {
+            let gold = &mut self.state.gold;
+            *gold = gold.saturating_add_signed(changes.gold_change);
+            notes.push(format!("{:+} gold", changes.gold_change));
+        }
+        let offers: Vec<Offer> = changes
+            .offers
+            .iter()
+            .filter(|offer| !offer.item.trim().is_empty())
+            .filter_map(|offer| {
+                Some(Offer {
+                    item: offer.item.trim().to_string(),
+                    price: u32::try_from(offer.price).ok()?,
+                })
+            })
+            .collect();
+        if !offers.is_empty() {
+            let list: Vec<String> = offers
+                .iter()
+                .map(|offer| format!("{} ({}g)", offer.item, offer.price))
+                .collect();
+            notes.push(format!("for sale: {}", list.join(", ")));
+            self.state.offers = offers;
+        }
+        if !notes.is_empty() {
+            self.push_log(LogKind::System, format!("State: {}", notes.join(", ")));
+        }
+    }
+
+    /// Applies a model-driven change, or holds it for the player when it contradicts
+    /// something they set by hand. Returns a note on what changed.
+    fn propose(&mut self, change: Change) -> Option<String> {
+        match self.claims.dispute(&change) {
+            None => self.apply_change(&change),
+            Some(conflict) => {
+                self.push_log(LogKind::System, format!("Conflict: {}", conflict.theirs));
+                self.conflicts.push_back(conflict);
+                None
+            }
+        }
+    }
+
+    fn apply_change(&mut self, change: &Change) -> Option<String> {
+        match change {
+            Change::AddItem(item) => {
+                let note = format!("+{}", item.label());
+                self.state.gain(item.clone()).then_some(note)
+            }
+            Change::RemoveItem(item) => {
+                self.state.lose_item(item).then(|| format!("-{}", item.trim()))
+            }
+            Change::SetLocation(place) => {
+                self.state.move_to(place).then(|| format!("location: {}", place.trim()))
+            }
+            Change::SetFlag(flag) => {
+                self.state.raise_flag(flag).then(|| format!("flag {}", flag.trim()))
+            }
+            Change::ClearFlag(flag) => {
+                self.state.clear_flag(flag).then(|| format!("unflag {}", flag.trim()))
+            }
+        }
+    }
+
+    /// Settles the oldest conflict: `Some(true)` takes the narrator's version,
+    /// `Some(false)` keeps the player's, and `None` puts the change in the input to edit.
+    pub(crate) fn settle_conflict(&mut self, accept: Option<bool>) {
+        let Some(conflict) = self.conflicts.pop_front() else {
+            return;
+        };
+        match accept {
+            Some(true) => {
+                self.claims.release(&conflict.change);
+                if let Some(note) = self.apply_change(&conflict.change) {
+                    self.push_log(LogKind::System, format!("State: {note}"));
+                }
+            }
+            Some(false) => self.push_log(LogKind::System, format!("Kept: {}", conflict.mine)),
+            None => self.input.set(conflict.change.command()),
+        }
+    }
+
+    /// Applies a state update the model made through function calling.
+    pub(crate) fn apply_tool_call(&mut self, call: &ToolCall) {
+        let note = match call {
+            ToolCall::AddItem(item) => self.propose(Change::AddItem(item.clone())),
+            ToolCall::RemoveItem(item) => self.propose(Change::RemoveItem(item.clone())),
+            ToolCall::SetLocation(place) => self.propose(Change::SetLocation(place.clone())),
+            ToolCall::SetFlag(flag) => self.propose(Change::SetFlag(flag.clone())),
+            ToolCall::ChangeHealth(amount) => {
+                self.change_health(*amount);
+                None
+            }
+            ToolCall::AddExit {
+                from,
+                direction,
+                to,
+            } => self
+                .state
+                .add_exit(from, to, Some(direction))
+                .then(|| format!("exit {from} -> {to}")),
+            ToolCall::ChangeAffinity { npc, amount } => {
+                let score = self.state.change_affinity(npc, *amount);
+                Some(format!("{npc} {amount:+} ({})", affinity_label(score)))
+            }
+        };
+        if let Some(note) = note {
+            self.push_log(LogKind::System, format!("State: {note}"));
+        }
+    }
+
+    /// Applies damage or healing and ends the game when HP reaches zero.
+    pub(crate) fn change_health(&mut self, amount: i32) {
+        if amount == 0 || self.game_over {
+            return;
+        }
+        let state = &mut self.state;
+        state.hp = (state.hp + amount).clamp(0, state.max_hp);
+        let message = format!("Health {amount:+} ({}/{})", state.hp, state.max_hp);
+        self.push_log(LogKind::System, message);
+        if self.state.hp == 0 {
+            self.game_over = true;
+            self.push_log(LogKind::Error, "You have died.");
+        }
+    }
+
+    pub(crate) fn save_checkpoint(&mut self) {
+        self.checkpoint = Some(Checkpoint {
+            state: self.state.clone(),
+            history: self.history.clone(),
+            log_len: self.log.len(),
+        });
+    }
+
+    pub(crate) fn save_retry_point(&mut self) {
+        self.retry_point = Some(Checkpoint {
+            state: self.state.clone(),
+            history: self.history.clone(),
+            log_len: self.log.len(),
+        });
+    }
+
+    /// Drops the last reply and everything it changed, then resends the same context.
+    pub(crate) fn retry_last(&mut self) {
+        if self.busy {
+            self.push_log(LogKind::System, "Wait for the current reply before retrying.");
+            return;
+        }
+        let Some(point) = self.retry_point.take() else {
+            self.push_log(LogKind::System, "There is no reply to retry.");
+            return;
+        };
+        self.state = point.state;
+        self.history = point.history;
+        self.log.truncate(point.log_len);
+        self.game_over = false;
+        // Retrying the epilogue should end the story again once the new one arrives.
+        self.epilogue_pending = self.story_over;
+        self.story_over = false;
+        self.tasks.cancel_kind(TaskKind::Extraction);
+        self.push_toast("Regenerating the last reply...");
+        self.pending_input = Some(self.last_sent_input.clone().unwrap_or_default());
+    }
+
+    /// Copies the current timeline into a new branch called `name` and switches to it.
+    pub(crate) fn fork(&mut self, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            self.push_log(LogKind::System, "Usage: /fork <name>");
+            return;
+        }
+        if name == self.branch || self.branches.iter().any(|branch| branch.name == name) {
+            self.push_log(LogKind::System, format!("A branch called {name} already exists."));
+            return;
+        }
+        if self.busy {
+            self.push_log(LogKind::System, "Wait for the current reply before forking.");
+            return;
+        }
+        let previous = std::mem::replace(&mut self.branch, name.to_string());
+        self.branches.push(Branch {
+            name: previous.clone(),
+            state: self.state.clone(),
+            history: self.history.clone(),
+            log: self.log.clone(),
+            trimmed: self.pending_summary(),
+            memory: self.memory.clone(),
+        });
+        self.push_log(LogKind::System, format!("Forked {previous} into {name}."));
+    }
+
+    /// Shelves the current timeline and resumes the branch called `name`.
+    pub(crate) fn switch_branch(&mut self, name: &str) {
+        let name = name.trim();
+        if name == self.branch {
+            return;
+        }
+        if self.busy {
+            self.push_log(LogKind::System, "Wait for the current reply before switching.");
+            return;
+        }
+        let Some(pos) = self.branches.iter().position(|branch| branch.name == name) else {
+            self.push_log(LogKind::System, format!("No branch called {name}."));
+            return;
+        };
+        let target = self.branches.remove(pos);
+        // Work for the shelved timeline would land in the resumed one; stop it and keep
+        // the chunks it was summarizing with the branch they came from.
+        let trimmed = self.pending_summary();
+        self.tasks.cancel_kind(TaskKind::Summary);
+        self.tasks.cancel_kind(TaskKind::Chapter);
+        self.tasks.cancel_kind(TaskKind::Embedding);
+        self.summarizing.clear();
+        self.summary_paused = false;
+        let current = Branch {
+            name: std::mem::replace(&mut self.branch, target.name),
+            state: std::mem::replace(&mut self.state, target.state),
+            history: std::mem::replace(&mut self.history, target.history),
+            log: std::mem::replace(&mut self.log, target.log),
+            trimmed,
+            memory: std::mem::replace(&mut self.memory, target.memory),
+        };
+        self.trimmed = target.trimmed;
+        self.branches.insert(pos, current);
+        self.checkpoint = None;
+        self.retry_point = None;
+        self.game_over = false;
+        self.story_over = false;
+        self.follow = true;
+        self.tasks.cancel_kind(TaskKind::Extraction);
+        let message = format!("Switched to branch {}.", self.branch);
+        self.push_log(LogKind::System, message);
+    }
+
+    /// Chunks not yet in the summary, including any a running task is folding in.
+    fn pending_summary(&self) -> Vec<Vec<Value>> {
+        self.summarizing.iter().chain(&self.trimmed).cloned().collect()
+    }
+
+    /// Opens the last reply in the editor as speaker-labelled lines.
+    pub(crate) fn open_reply_editor(&mut self) {
+        if self.busy {
+            self.push_log(LogKind::System, "Wait for the current reply before editing.");
+            return;
+        }
+        let Some(start) = self.retry_point.as_ref().map(|point| point.log_len) else {
+            self.push_log(LogKind::System, "There is no reply to edit.");
+            return;
+        };
+        let lines: Vec<String> = self.log[start.min(self.log.len())..]
+            .iter()
+            .filter(|entry| matches!(entry.kind, LogKind::Assistant))
+            .map(|entry| match &entry.speaker {
+                Some(speaker) => format!("{speaker}: {}", entry.text),
+                None => entry.text.clone(),
+            })
+            .collect();
+        self.reply_editor = Some(lines.join("\n"));
+    }
+
+    /// Replaces the last reply in both the log and history with `text`.
+    pub(crate) fn apply_reply_edit(&mut self, text: &str) {
+        let text = text.trim();
+        let Some(start) = self.retry_point.as_ref().map(|point| point.log_len) else {
+            return;
+        };
+        if text.is_empty() {
+            self.push_toast("An empty reply was not saved.");
+            return;
+        }
+        let mut after = self.log.split_off(start.min(self.log.len()));
+        let first = after
+            .iter()
+            .position(|entry| matches!(entry.kind, LogKind::Assistant))
+            .unwrap_or(after.len());
+        self.log.extend(after.drain(..first));
+        self.push_reply_text(text);
+        let kept = after.into_iter().filter(|entry| !matches!(entry.kind, LogKind::Assistant));
+        self.log.extend(kept);
+
+        let is_reply = |item: &Value| item.get("role").and_then(Value::as_str) == Some("assistant");
+        let reply_chunk = self.history.iter_mut().rev().find(|chunk| chunk.iter().any(is_reply));
+        if let Some(chunk) = reply_chunk {
+            *chunk = vec![json!({ "role": "assistant", "content": text })];
+        }
+        self.push_toast("Reply updated.");
+    }
 
-impl GameState {
-    pub(crate) fn new() -> Self {
-        Self {
-            turn: 0,
-            location: "Unknown".to_string(),
-            inventory: Vec::new(),
-            flags: Vec::new(),
-            active_speaker: None,
+    /// Opens the system prompt in the editor, with any edit this game has made.
+    pub(crate) fn open_prompt_editor(&mut self) {
+        let prompt = self.state.system_prompt.as_ref().unwrap_or(&self.default_prompt);
+        self.prompt_editor = Some(prompt.clone());
+    }
+
+    /// Uses `text` as the system prompt for the rest of this game.
+    pub(crate) fn apply_prompt_edit(&mut self, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            self.push_toast("An empty system prompt was not saved.");
+            return;
+        }
+        if text == self.default_prompt.trim() {
+            self.state.system_prompt = None;
+        } else {
+            self.state.system_prompt = Some(text.to_string());
         }
+        self.push_toast("System prompt updated for this game.");
     }
-}
 
-pub(crate) struct App {
-    pub(crate) input: String,
-    pub(crate) log: Vec<LogEntry>,
-    pub(crate) history: Vec<Vec<Value>>,
-    pub(crate) scroll: u16,
-    pub(crate) busy: bool,
-    pub(crate) pending_input: Option<String>,
-    pub(crate) last_sent_input: Option<String>,
-    pub(crate) pending_response: Option<Receiver<Result<(String, Vec<Value>, String)>>>,
-    pub(crate) state: GameState,
-    pub(crate) status: String,
-    pub(crate) thinking_started: Option<Instant>,
-}
+    /// Rewinds to the last checkpoint; returns `false` when there is none.
+    pub(crate) fn restore_checkpoint(&mut self) -> bool {
+        let Some(checkpoint) = self.checkpoint.take() else {
+            return false;
+        };
+        self.state = checkpoint.state;
+        self.history = checkpoint.history;
+        self.log.truncate(checkpoint.log_len);
+        self.retry_point = None;
+        self.game_over = false;
+        self.tasks.cancel_kind(TaskKind::Extraction);
+        let turn = self.state.turn;
+        self.push_log(LogKind::System, format!("Restored the checkpoint from turn {turn}."));
+        true
+    }
 
-impl App {
-    pub(crate) fn new() -> Self {
-        let mut app = Self {
-            input: String::new(),
-            log: Vec::new(),
-            history: Vec::new(),
-            scroll: 0,
-            busy: false,
-            pending_input: None,
-            last_sent_input: None,
-            pending_response: None,
-            state: GameState::new(),
-            status: "Ready".to_string(),
-            thinking_started: None,
+    /// Description of the current scene sent to the scene model.
+    pub(crate) fn build_scene_context(&self) -> String {
+        let mut context = format!("Location: {}", self.state.location);
+        let recent = self
+            .log
+            .iter()
+            .rev()
+            .filter(|entry| matches!(entry.kind, LogKind::Assistant))
+            .find(|entry| entry.speaker.as_deref().is_none_or(is_narrator_label));
+        if let Some(entry) = recent {
+            context.push_str(&format!("\nLatest narration: {}", entry.text));
+        }
+        context
+    }
+
+    /// Scenario games share art for the same place; a custom world is its own game's.
+    fn current_scene_key(&self) -> String {
+        let world = match &self.scenario {
+            Some(scenario) => scenario.name.clone(),
+            None => format!("game {}", self.state.game_id),
         };
-        app.push_log(LogKind::System, "Welcome! Describe what you do to begin.");
-        app
+        let part_of_day = clock::part_of_day(self.state.minutes);
+        scene_cache::key(&world, &self.state.location, part_of_day, self.scene_style)
     }
 
-    pub(crate) fn push_log(&mut self, kind: LogKind, text: impl Into<String>) {
-        self.log.push(LogEntry {
-            kind,
-            speaker: None,
-            text: text.into(),
-        });
+    /// Asks for scene art when the player has moved, the time of day has turned or the
+    /// art style has changed since the last request.
+    pub(crate) fn refresh_scene_if_moved(&mut self) {
+        let location = &self.state.location;
+        if self.arrived_at.as_ref() != Some(location) && location != "Unknown" {
+            self.arrived_at = Some(location.clone());
+            self.show_banner(location.clone());
+        }
+        if self.scene_key.as_deref() != Some(self.current_scene_key().as_str()) {
+            self.request_scene(false);
+        }
     }
 
-    pub(crate) fn push_speaker_log(
-        &mut self,
-        kind: LogKind,
-        speaker: impl Into<String>,
-        text: impl Into<String>,
-    ) {
-        self.log.push(LogEntry {
-            kind,
-            speaker: Some(speaker.into()),
-            text: text.into(),
+    /// Queues scene art for the current place; the main loop sends it. `fresh` skips
+    /// the cache and always asks the model.
+    pub(crate) fn request_scene(&mut self, fresh: bool) {
+        self.scene_key = Some(self.current_scene_key());
+        self.scene_fresh = fresh;
+        self.scene_pending_response = true;
+        self.scene_asked = Some((self.state.turn, self.state.location.clone()));
+    }
+
+    /// Locally drawn art for the current place, shown until or instead of generated art.
+    pub(crate) fn sketch_scene(&self) -> String {
+        let part_of_day = clock::part_of_day(self.state.minutes);
+        sketch::draw(&self.state.location, part_of_day, self.state.weather)
+    }
+
+    /// Asks for new art in a different composition, for `/scene` and the redraw key.
+    pub(crate) fn redraw_scene(&mut self) {
+        if !self.show_scene {
+            self.push_log(LogKind::System, "The Scene pane is off; /scene on shows it.");
+        } else if self.tasks.is_running(TaskKind::Scene) {
+            self.push_toast("The scene is already being drawn.");
+        } else {
+            self.request_scene(true);
+            self.push_toast("Redrawing the scene...");
+        }
+    }
+
+    /// Shows generated art and keeps it for `/gallery`.
+    pub(crate) fn show_generated_scene(&mut self, art: String) {
+        let repeated = self
+            .scenes
+            .last()
+            .is_some_and(|scene| matches!(&scene.art, SceneArt::Text(last) if *last == art));
+        if !repeated {
+            self.keep_scene(SceneArt::Text(art.clone()));
+        }
+        self.set_scene_ascii(art);
+    }
+
+    fn keep_scene(&mut self, art: SceneArt) {
+        let (turn, location) = self
+            .scene_asked
+            .clone()
+            .unwrap_or_else(|| (self.state.turn, self.state.location.clone()));
+        self.scenes.push(GalleryScene {
+            turn,
+            location,
+            art,
         });
     }
 
-    pub(crate) fn push_user_log(&mut self, text: impl Into<String>) {
-        self.push_speaker_log(LogKind::User, "You", text);
+    pub(crate) fn export_gallery(&mut self) {
+        match gallery::export(&self.tab_title(), &self.scenes) {
+            Ok(path) => self.push_toast(format!("Gallery exported to {}", path.display())),
+            Err(err) => self.push_toast(format!("Export failed: {err}")),
+        }
     }
 
-    pub(crate) fn push_assistant_reply(&mut self, reply: &str) {
-        let reply = reply.trim();
-        if reply.is_empty() {
-            return;
+    /// Text art replaces any picture, e.g. when image generation failed.
+    pub(crate) fn set_scene_ascii(&mut self, art: String) {
+        if let Some(previous) = self.scene_ascii.take().filter(|previous| *previous != art) {
+            self.scene_transition = Some(SceneTransition { previous, step: 0 });
         }
+        self.scene_ascii = Some(art);
+        self.scene_image = None;
+    }
 
-        let parsed = parse_speaker_lines(reply);
-        if parsed.entries.is_empty() {
-            let sanitized = strip_disallowed_speaker_lines(reply);
-            if !sanitized.trim().is_empty() {
-                self.push_speaker_log(LogKind::Assistant, "Narrator", sanitized.trim());
-                self.state.active_speaker = None;
+    pub(crate) fn tick_scene_transition(&mut self) {
+        if let Some(transition) = self.scene_transition.as_mut() {
+            transition.step += 1;
+            if transition.step >= SceneTransition::STEPS {
+                self.scene_transition = None;
             }
+        }
+    }
+
+    /// The scene art to draw: mid-transition, the new art's left part over the old
+    /// art's right, split by a moving edge.
+    pub(crate) fn scene_frame(&self) -> Option<String> {
+        let art = self.scene_ascii.as_deref()?;
+        let Some(transition) = &self.scene_transition else {
+            return Some(art.to_string());
+        };
+        let old: Vec<&str> = transition.previous.lines().collect();
+        let new: Vec<&str> = art.lines().collect();
+        let width = old.iter().chain(&new).map(|line| line.chars().count()).max().unwrap_or(0);
+        let edge = width * usize::from(transition.step + 1) / usize::from(SceneTransition::STEPS);
+        let rows = old.len().max(new.len());
+        let frame: Vec<String> = (0..rows)
+            .map(|row| {
+                let padded = |lines: &[&str]| {
+                    let line = lines.get(row).copied().unwrap_or_default();
+                    let pad = width.saturating_sub(line.chars().count());
+                    line.chars().chain(std::iter::repeat_n(' ', pad)).collect::<Vec<char>>()
+                };
+                let (old, new) = (padded(&old), padded(&new));
+                new[..edge].iter().chain(&old[edge..]).collect::<String>().trim_end().to_string()
+            })
+            .collect();
+        Some(frame.join("\n"))
+    }
+
+    /// Shows a generated picture and keeps it for `/gallery`.
+    pub(crate) fn set_scene_image(&mut self, image: Image) {
+        self.keep_scene(SceneArt::Picture(image.png().to_vec()));
+        self.scene_image = Some(image);
+        self.scene_image_id += 1;
+    }
+
+    /// Warns the narrator when a movement command contradicts the known exits.
+    pub(crate) fn validate_travel(&mut self, input: &str) {
+        let Some(note) = check_travel(&self.state, input) else {
+            return;
+        };
+        self.push_log(LogKind::System, "Travel: that route is not on the map yet.");
+        self.push_history_chunk(vec![json!({
+            "role": "system",
+            "content": note
+        })]);
+    }
+
+    /// Rolls the encounter table when the player sets off, telling the narrator what happens.
+    pub(crate) fn roll_encounter(&mut self, input: &str) {
+        if !is_travel(input) {
             return;
         }
-        for entry in parsed.entries {
-            self.push_speaker_log(LogKind::Assistant, entry.speaker, entry.text);
+        let table = self.scenario.as_ref().and_then(|s| s.encounters.as_ref());
+        let Some(encounter) = table.unwrap_or(&self.encounters).roll(&mut self.rng) else {
+            return;
+        };
+        let description = encounter.description.clone();
+        self.push_log(LogKind::System, format!("Encounter: {description}"));
+        self.push_history_chunk(vec![json!({
+            "role": "system",
+            "content": format!(
+                "A random encounter occurs: {description}. Work it into this turn's narration."
+            )
+        })]);
+    }
+
+    /// Buys an offered item if the player can afford it and tells the narrator.
+    pub(crate) fn buy(&mut self, name: &str) {
+        let name = name.trim();
+        let Some(pos) = self
+            .state
+            .offers
+            .iter()
+            .position(|offer| offer.item.eq_ignore_ascii_case(name))
+        else {
+            self.push_log(LogKind::System, format!("Nobody is selling {name} here."));
+            return;
+        };
+        let offer = self.state.offers[pos].clone();
+        if offer.price > self.state.gold {
+            let gold = self.state.gold;
+            let message = format!("{} costs {}g; you have {gold}g.", offer.item, offer.price);
+            self.push_log(LogKind::System, message);
+            return;
         }
+        self.state.gold -= offer.price;
+        self.state.offers.remove(pos);
+        self.state.gain_item(&offer.item);
+        let message = format!(
+            "Bought {} for {}g ({}g left).",
+            offer.item, offer.price, self.state.gold
+        );
+        self.push_log(LogKind::System, message.clone());
+        self.push_history_chunk(vec![json!({
+            "role": "system",
+            "content": format!("The player completed a purchase. {message} Acknowledge it briefly.")
+        })]);
+    }
 
-        if let Some(last_speaker) = parsed.last_speaker {
-            if is_narrator_label(&last_speaker) {
-                self.state.active_speaker = None;
-            } else {
-                self.state.active_speaker = Some(last_speaker);
+    /// Combines two carried items: a matching recipe is applied at once, anything else
+    /// is queued for the model to resolve.
+    pub(crate) fn craft(&mut self, a: &str, b: &str) {
+        let mut ingredients = Vec::new();
+        for name in [a.trim(), b.trim()] {
+            match self.state.item(name) {
+                Some(item) => ingredients.push(item.name.clone()),
+                None => {
+                    self.push_log(LogKind::System, format!("You are not carrying {name}."));
+                    return;
+                }
+            }
+        }
+        let (a, b) = (ingredients.remove(0), ingredients.remove(0));
+        if a.eq_ignore_ascii_case(&b) {
+            self.push_log(LogKind::System, "Crafting needs two different items.");
+            return;
+        }
+        if self.tasks.is_running(TaskKind::Crafting) || self.craft_requested.is_some() {
+            self.push_log(LogKind::System, "Already crafting; wait for the result.");
+            return;
+        }
+        let recipe = self.recipes.iter().find(|recipe| recipe.matches(&a, &b)).cloned();
+        match recipe {
+            Some(recipe) => {
+                let crafted = Crafted {
+                    success: true,
+                    result: recipe.result,
+                    description: String::new(),
+                };
+                self.finish_craft(&a, &b, &crafted);
+            }
+            None => {
+                self.push_log(LogKind::System, format!("Trying to combine {a} and {b}..."));
+                self.craft_requested = Some((a, b));
             }
         }
     }
 
+    /// Applies a crafting outcome, consuming both ingredients on success.
+    pub(crate) fn finish_craft(&mut self, a: &str, b: &str, crafted: &Crafted) {
+        let result = crafted.result.trim();
+        if !crafted.success || result.is_empty() {
+            let message = match crafted.description.trim() {
+                "" => format!("{a} and {b} cannot be combined."),
+                description => description.to_string(),
+            };
+            self.push_log(LogKind::System, message);
+            return;
+        }
+        if self.state.item(a).is_none() || self.state.item(b).is_none() {
+            self.push_log(LogKind::System, format!("{a} or {b} is no longer carried."));
+            return;
+        }
+        self.state.lose_item(a);
+        self.state.lose_item(b);
+        let mut item = Item::new(result);
+        item.description = crafted.description.trim().to_string();
+        item.tags.push("crafted".to_string());
+        self.state.gain(item);
+        let mut message = format!("Crafted {result} from {a} and {b}.");
+        if !crafted.description.trim().is_empty() {
+            message = format!("{message} {}", crafted.description.trim());
+        }
+        self.push_log(LogKind::System, message.clone());
+        self.push_history_chunk(vec![json!({
+            "role": "system",
+            "content": format!("The player crafted an item. {message} Treat it as done.")
+        })]);
+    }
+
+    /// Moves one of `name` from the inventory into `slot`, or the slot its tags suggest.
+    /// Whatever the slot held goes back into the inventory.
+    pub(crate) fn equip(&mut self, name: &str, slot: Option<Slot>) {
+        let Some(item) = self.state.item(name) else {
+            self.push_log(LogKind::System, format!("You are not carrying {}.", name.trim()));
+            return;
+        };
+        let Some(slot) = slot.or_else(|| Slot::for_item(item)) else {
+            let message = format!("Which slot? Use /equip weapon|armor|trinket {}.", item.name);
+            self.push_log(LogKind::System, message);
+            return;
+        };
+        let mut equipped = item.clone();
+        equipped.qty = 1;
+        self.state.lose_item(name);
+        let message = format!("Equipped {} as {}.", equipped.name, slot.label());
+        if let Some(previous) = self.state.equipment.slot_mut(slot).replace(equipped) {
+            self.state.stow(previous);
+        }
+        self.push_log(LogKind::System, message);
+    }
+
+    /// Returns whatever is in `target` (a slot name or an equipped item) to the inventory.
+    pub(crate) fn unequip(&mut self, target: &str) {
+        let slot = Slot::parse(target).or_else(|| self.state.equipment.find(target));
+        let Some(item) = slot.and_then(|slot| self.state.equipment.slot_mut(slot).take()) else {
+            self.push_log(LogKind::System, format!("Nothing equipped matches {}.", target.trim()));
+            return;
+        };
+        let message = format!("Unequipped {}.", item.name);
+        self.state.stow(item);
+        self.push_log(LogKind::System, message);
+    }
+
+    /// Moves the weather on by one turn, noting changes in the log.
+    pub(crate) fn advance_weather(&mut self) {
+        let next = self.state.weather.next(&mut self.rng);
+        if next != self.state.weather {
+            self.state.weather = next;
+            self.push_log(LogKind::System, format!("Weather: {}", next.label()));
+        }
+    }
+
+    /// Rolls dice, logs the result and tells the narrator it must honor it.
+    pub(crate) fn roll_dice(&mut self, expr: DiceExpr) {
+        let result = expr.roll(&mut self.rng).describe();
+        self.push_log(LogKind::System, format!("Roll {result}"));
+        self.push_history_chunk(vec![json!({
+            "role": "system",
+            "content": format!(
+                "The player rolled {result}. Treat this result as binding when narrating the outcome."
+            )
+        })]);
+    }
+
+    /// Resolves a risky player action locally and tells the narrator the outcome.
+    pub(crate) fn resolve_skill_check(&mut self, input: &str) {
+        if !self.skill_checks {
+            return;
+        }
+        let dc = self.difficulty.check_dc();
+        let Some(check) = SkillCheck::attempt(input, &self.state.character, dc, &mut self.rng)
+        else {
+            return;
+        };
+        let description = check.describe(&self.state.character);
+        self.push_log(LogKind::System, format!("Check: {description}"));
+        self.push_history_chunk(vec![json!({
+            "role": "system",
+            "content": check.system_note()
+        })]);
+    }
+
+    /// Measures a reply against the word target and queues a corrective system note
+    /// once replies drift the same way several turns in a row.
+    pub(crate) fn record_reply_length(&mut self, reply: &str) {
+        let words = reply.split_whitespace().count() as u32;
+        let stats = &mut self.word_stats;
+        stats.replies += 1;
+        stats.total_words += u64::from(words);
+        let Some(target) = self.word_target else {
+            return;
+        };
+
+        if words > target.max {
+            stats.too_long += 1;
+            stats.long_streak += 1;
+            stats.short_streak = 0;
+        } else if words < target.min {
+            stats.too_short += 1;
+            stats.short_streak += 1;
+            stats.long_streak = 0;
+        } else {
+            stats.within += 1;
+            stats.long_streak = 0;
+            stats.short_streak = 0;
+        }
+
+        let note = if stats.long_streak >= WORD_DRIFT_STREAK {
+            format!(
+                "Note: recent replies ran long. Keep the next reply under {} words.",
+                target.max
+            )
+        } else if stats.short_streak >= WORD_DRIFT_STREAK {
+            format!(
+                "Note: recent replies ran short. Write at least {} words next time.",
+                target.min
+            )
+        } else {
+            return;
+        };
+        stats.long_streak = 0;
+        stats.short_streak = 0;
+        stats.corrections += 1;
+        self.push_history_chunk(vec![json!({
+            "role": "system",
+            "content": note
+        })]);
+    }
+
     pub(crate) fn push_user_message(&mut self, content: impl Into<String>) {
         let item = json!({
             "role": "user",
             "content": content.into()
         });
-        if self.state.active_speaker.is_some() {
-            if let Some(text) = item.get("content").and_then(|v| v.as_str()) {
-                if is_dialogue_exit(text) {
-                    self.state.active_speaker = None;
-                }
-            }
+        if self.state.active_speaker.is_some()
+            && let Some(text) = item.get("content").and_then(|v| v.as_str())
+            && is_dialogue_exit(text)
+        {
+            self.state.active_speaker = None;
         }
         self.push_history_chunk(vec![item]);
     }
 
+    /// Archives the current chapter under `summary`, clears the detailed history and log,
+    /// and starts the next chapter from the summary alone.
+    pub(crate) fn end_chapter(&mut self, summary: String) {
+        let chapter = Chapter {
+            number: self.state.chapter,
+            summary: summary.clone(),
+        };
+        let archived = chapters::archive(self.state.game_id, &chapter, &self.log);
+        self.state.chapters.push(chapter);
+        self.state.chapter += 1;
+        self.state.summary = summary;
+        self.history.clear();
+        self.trimmed.clear();
+        self.checkpoint = None;
+        self.retry_point = None;
+        self.log.clear();
+        self.follow = true;
+        let number = self.state.chapter;
+        match archived {
+            Ok(path) => {
+                let message = format!("Chapter {} archived to {}.", number - 1, path.display());
+                self.push_log(LogKind::System, message);
+            }
+            Err(err) => {
+                let message = format!("Could not archive chapter {}: {err}", number - 1);
+                self.push_log(LogKind::Error, message);
+            }
+        }
+        let recap = self.state.summary.clone();
+        self.push_log(LogKind::System, format!("Chapter {number}\n\nPreviously: {recap}"));
+        self.show_banner(format!("Chapter {number}"));
+        self.push_history_chunk(vec![json!({
+            "role": "system",
+            "content": format!("Chapter {number} begins; pick up from the story so far.")
+        })]);
+    }
+
+    /// Asks the narrator for the epilogue; the ending screen follows when it arrives.
+    pub(crate) fn begin_epilogue(&mut self) {
+        self.epilogue_pending = true;
+        self.push_log(LogKind::System, "The story draws to a close...");
+        self.push_history_chunk(vec![json!({
+            "role": "system",
+            "content": EPILOGUE_NOTE
+        })]);
+        self.last_sent_input = None;
+        self.pending_input = Some(String::new());
+    }
+
+    /// Unlocks achievements the game has reached, toasting and saving any new ones.
+    pub(crate) fn check_achievements(&mut self) {
+        let progress = Progress {
+            turns: self.state.turn,
+            npcs_met: self.state.met.len(),
+            places_visited: self.state.visited.len(),
+            died: self.game_over,
+            finished: self.story_over,
+        };
+        let unlocked = self.achievements.check(&progress);
+        if unlocked.is_empty() {
+            return;
+        }
+        for achievement in unlocked {
+            self.push_toast(format!("Achievement unlocked: {}", achievement.title));
+        }
+        if let Err(err) = self.achievements.save() {
+            self.push_log(LogKind::Error, format!("Could not save achievements: {err}"));
+        }
+    }
+
+    /// Returns whether the export succeeded.
+    pub(crate) fn export_story(&mut self) -> bool {
+        match ending::export_story(&self.state, &self.log) {
+            Ok(path) => {
+                self.exported_log = self.log.len();
+                self.push_toast(format!("Story exported to {}", path.display()));
+                true
+            }
+            Err(err) => {
+                self.push_toast(format!("Export failed: {err}"));
+                false
+            }
+        }
+    }
+
+    /// The story has moved on since it was last exported.
+    pub(crate) fn has_unsaved_progress(&self) -> bool {
+        !self.history.is_empty() && self.log.len() > self.exported_log
+    }
+
+    /// Shows failures from this tab's turn hooks.
+    pub(crate) fn receive_hook_errors(&mut self) {
+        while let Ok(message) = self.hook_rx.try_recv() {
+            self.push_toast(message);
+        }
+    }
+
+    /// Quits at once when nothing would be lost; otherwise asks first.
+    pub(crate) fn request_quit(&mut self) -> bool {
+        if self.has_unsaved_progress() {
+            self.confirm_quit = true;
+            return false;
+        }
+        true
+    }
+
     pub(crate) fn push_history_chunk(&mut self, items: Vec<Value>) {
         if items.is_empty() {
             return;
@@ -152,30 +2139,207 @@ impl App {
 
     pub(crate) fn reset(&mut self) {
         self.input.clear();
+        self.claims = Claims::default();
+        self.conflicts.clear();
         self.log.clear();
+        self.exported_log = 0;
         self.history.clear();
-        self.scroll = 0;
+        self.follow = true;
         self.busy = false;
+        self.choices.clear();
         self.pending_input = None;
         self.last_sent_input = None;
-        self.pending_response = None;
+        self.tasks.cancel_kind(TaskKind::Narration);
+        self.checkpoint = None;
+        self.retry_point = None;
+        self.branch = MAIN_BRANCH.to_string();
+        self.branches.clear();
+        self.game_over = false;
+        self.epilogue_pending = false;
+        self.story_over = false;
+        self.tasks.cancel_kind(TaskKind::Scene);
+        self.tasks.cancel_kind(TaskKind::Extraction);
+        self.tasks.cancel_kind(TaskKind::Crafting);
+        self.tasks.cancel_kind(TaskKind::Embedding);
+        self.tasks.cancel_kind(TaskKind::Summary);
+        self.tasks.cancel_kind(TaskKind::Chapter);
+        self.chapter_requested = false;
+        self.trimmed.clear();
+        self.summarizing.clear();
+        self.summary_paused = false;
+        self.memory = Memory::default();
+        self.craft_requested = None;
+        self.scene_ascii = None;
+        self.scene_transition = None;
+        self.scene_image = None;
+        self.scene_pending_response = false;
+        self.scene_asked = None;
+        self.scene_key = None;
+        self.scene_fresh = false;
+        self.scene_drawing = None;
+        self.illustration_requested = false;
+        self.tasks.cancel_kind(TaskKind::Illustration);
+        self.scenes.clear();
+        self.gallery = None;
+        self.banner = None;
+        self.arrived_at = None;
         self.state = GameState::new();
+        self.scenario = None;
+        self.seed = self.fixed_seed.unwrap_or_else(rand::random);
+        self.rng = StdRng::seed_from_u64(self.seed);
         self.status = "Ready".to_string();
         self.thinking_started = None;
-        self.push_log(LogKind::System, "New game. Describe what you do to begin.");
+        self.word_stats = WordStats::default();
+        self.push_log(LogKind::System, "New game.");
+        self.begin_creation();
+    }
+
+    /// Starts a new game from `scenario`, or a blank one for a custom game.
+    pub(crate) fn start_scenario(&mut self, scenario: Option<Scenario>) {
+        self.reset();
+        let Some(scenario) = scenario else {
+            return;
+        };
+        self.log.clear();
+        let state = &mut self.state;
+        state.premise = scenario.premise();
+        state.move_to(&scenario.location);
+        state.inventory = scenario.inventory.clone();
+        if let Some(gold) = scenario.gold {
+            state.gold = gold;
+        }
+        if let Some(hp) = scenario.hp {
+            state.hp = hp;
+            state.max_hp = hp;
+        }
+        for npc in &scenario.npcs {
+            state.change_affinity(&npc.name, npc.affinity);
+        }
+        self.push_log(LogKind::System, format!("New game: {}.", scenario.name));
+        if !scenario.opening.is_empty() {
+            self.push_speaker_log(LogKind::Assistant, "Narrator", scenario.opening.as_str());
+            self.push_history_chunk(vec![json!({
+                "role": "assistant",
+                "content": format!("Narrator: {}", scenario.opening)
+            })]);
+        }
+        self.scenario = Some(scenario);
+        self.begin_creation();
+    }
+
+    /// Starts character creation from the first question.
+    pub(crate) fn begin_creation(&mut self) {
+        self.creation = Some(CreationStep::Name);
+        let question = CreationStep::Name.question();
+        self.push_log(LogKind::System, format!("{question} (Enter skips, Esc skips all)"));
+    }
+
+    /// Records the answer to the current creation question and asks the next one.
+    /// After the last answer the narrator is asked for an opening scene tailored to the
+    /// character, unless the scenario already opened with its own.
+    pub(crate) fn answer_creation(&mut self, answer: &str) {
+        let Some(step) = self.creation else {
+            return;
+        };
+        let answer = answer.trim();
+        if !answer.is_empty() {
+            self.push_user_log(answer);
+            let sheet = &mut self.state.character;
+            match step {
+                CreationStep::Name => sheet.name = answer.to_string(),
+                CreationStep::Background => sheet.background = answer.to_string(),
+                CreationStep::Goal => sheet.goal = answer.to_string(),
+                // Starting gear, so it does not count towards items collected.
+                CreationStep::Item => {
+                    self.state.stow(Item::new(answer));
+                }
+            }
+        }
+        self.creation = step.next();
+        if let Some(next) = self.creation {
+            self.push_log(LogKind::System, next.question());
+            return;
+        }
+        let summary = self.state.character.summary();
+        self.push_log(LogKind::System, format!("Character: {summary}"));
+        if self.scenario.as_ref().is_some_and(|scenario| !scenario.opening.is_empty()) {
+            self.push_history_chunk(vec![json!({
+                "role": "system",
+                "content": format!("The player created their character: {summary}. Keep it in mind as the story continues from the opening scene.")
+            })]);
+            self.push_log(LogKind::System, "Describe what you do to begin.");
+            return;
+        }
+        self.push_history_chunk(vec![json!({
+            "role": "system",
+            "content": format!(
+                "The player created their character: {summary}. Open the story with a scene that introduces them, reflects their background, hints at their goal and gives them a reason to act, then ask what they do."
+            )
+        })]);
+        self.last_sent_input = None;
+        self.pending_input = Some(String::new());
+    }
+
+    /// Abandons character creation, keeping whatever was answered.
+    pub(crate) fn skip_creation(&mut self) {
+        if self.creation.take().is_some() {
+            self.push_log(LogKind::System, "Describe what you do to begin.");
+        }
+    }
+
+    /// Adds a scenario loaded from a file to the picker, replacing one with the same name.
+    pub(crate) fn add_scenario(&mut self, scenario: Scenario) {
+        match self.scenarios.iter_mut().find(|s| s.name == scenario.name) {
+            Some(existing) => *existing = scenario,
+            None => self.scenarios.push(scenario),
+        }
+    }
+
+    /// Starts the epilogue once any of the scenario's win conditions is met.
+    pub(crate) fn check_win(&mut self) {
+        let Some(scenario) = &self.scenario else {
+            return;
+        };
+        let Some(win) = scenario.win.iter().find(|win| win.is_met(&self.state)) else {
+            return;
+        };
+        let message = format!("Victory: {}", win.description.trim());
+        self.push_log(LogKind::System, message);
+        self.begin_epilogue();
     }
 
-    fn trim_history(&mut self) {
-        while self.history_item_count() > MAX_HISTORY_ITEMS {
-            if self.history.is_empty() {
-                break;
+    pub(crate) fn cancel_task(&mut self, id: u64) -> Option<TaskKind> {
+        let kind = self.tasks.cancel(id)?;
+        match kind {
+            TaskKind::Narration => {
+                self.busy = false;
+                self.thinking_started = None;
+                self.status = "Cancelled".to_string();
             }
-            self.history.remove(0);
+            TaskKind::Summary | TaskKind::Chapter => {
+                let chunks = std::mem::take(&mut self.summarizing);
+                self.trimmed.splice(0..0, chunks);
+            }
+            _ => {}
+        }
+        Some(kind)
+    }
+
+    /// Moves the oldest chunks out until the history size fits the token budget,
+    /// queueing them for summarization. The newest chunk is always kept.
+    pub(crate) fn trim_history(&mut self) {
+        while self.history.len() > 1 && self.history_tokens() > self.history_token_budget {
+            let chunk = self.history.remove(0);
+            self.trimmed.push(chunk);
+            self.summary_paused = false;
         }
     }
 
-    fn history_item_count(&self) -> usize {
-        self.history.iter().map(|chunk| chunk.len()).sum()
+    pub(crate) fn history_tokens(&self) -> u64 {
+        self.history
+            .iter()
+            .map(|chunk| self.tokens.estimate_items(chunk))
+            .sum()
     }
 }
 
@@ -205,19 +2369,19 @@ fn parse_speaker_lines(text: &str) -> ParsedReply {
             }
             current_text.clear();
 
-            if !is_narrator_label(&speaker) {
-                if let Some((narration, dialogue)) = split_misattributed_narration(&rest) {
-                    if !narration.is_empty() {
-                        push_or_merge_entry(&mut entries, "Narrator".to_string(), &narration);
-                        last_speaker = Some("Narrator".to_string());
-                    }
-                    if let Some(dialogue) = dialogue {
-                        current_speaker = Some(speaker.clone());
-                        current_text.push_str(&dialogue);
-                        last_speaker = Some(speaker);
-                    }
-                    continue;
+            if !is_narrator_label(&speaker)
+                && let Some((narration, dialogue)) = split_misattributed_narration(&rest)
+            {
+                if !narration.is_empty() {
+                    push_or_merge_entry(&mut entries, "Narrator".to_string(), &narration);
+                    last_speaker = Some("Narrator".to_string());
                 }
+                if let Some(dialogue) = dialogue {
+                    current_speaker = Some(speaker.clone());
+                    current_text.push_str(&dialogue);
+                    last_speaker = Some(speaker);
+                }
+                continue;
             }
 
             last_speaker = Some(speaker.clone());
@@ -270,6 +2434,37 @@ fn parse_speaker_label(line: &str) -> Option<(String, String)> {
     Some((normalized, rest.trim_start().to_string()))
 }
 
+pub(crate) fn affinity_label(score: i32) -> &'static str {
+    match score {
+        i32::MIN..=-6 => "hostile",
+        -5..=-2 => "wary",
+        -1..=1 => "neutral",
+        2..=5 => "friendly",
+        _ => "devoted",
+    }
+}
+
+/// Removes `[HP -3]`-style tags, used when tools are unavailable, and sums their amounts.
+fn strip_health_tags(text: &str) -> (String, i32) {
+    let mut cleaned = String::with_capacity(text.len());
+    let mut total = 0;
+    let mut rest = text;
+    while let Some(start) = rest.to_ascii_lowercase().find("[hp") {
+        let Some(len) = rest[start..].find(']') else {
+            break;
+        };
+        let inner = rest[start + 3..start + len].trim().replace(' ', "");
+        cleaned.push_str(&rest[..start]);
+        match inner.parse::<i32>() {
+            Ok(amount) => total += amount,
+            Err(_) => cleaned.push_str(&rest[start..=start + len]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    cleaned.push_str(rest);
+    (cleaned, total)
+}
+
 fn is_narrator_label(label: &str) -> bool {
     label.trim().eq_ignore_ascii_case("narrator")
 }
@@ -368,17 +2563,17 @@ fn starts_with_you_action(text: &str) -> bool {
         "lift", "set", "place", "climb", "kneel", "sit", "stand", "backflip", "sprint", "brush",
         "touch", "aim", "throw", "swing", "carry", "stow", "hold",
     ];
-    action_verbs.iter().any(|action| *action == verb)
+    action_verbs.contains(&verb)
 }
 
 fn split_first_sentence(text: &str) -> (String, Option<String>) {
     let boundaries = [". ", "? ", "! "];
     let mut best: Option<(usize, usize)> = None;
     for boundary in boundaries {
-        if let Some(idx) = text.find(boundary) {
-            if best.map_or(true, |(best_idx, _)| idx < best_idx) {
-                best = Some((idx, boundary.len()));
-            }
+        if let Some(idx) = text.find(boundary)
+            && best.is_none_or(|(best_idx, _)| idx < best_idx)
+        {
+            best = Some((idx, boundary.len()));
         }
     }
     if let Some((idx, boundary_len)) = best {
@@ -399,12 +2594,12 @@ fn push_or_merge_entry(entries: &mut Vec<ParsedEntry>, speaker: String, text: &s
     if trimmed.is_empty() {
         return;
     }
-    if let Some(last) = entries.last_mut() {
-        if last.speaker.eq_ignore_ascii_case(&speaker) {
-            last.text.push('\n');
-            last.text.push_str(trimmed);
-            return;
-        }
+    if let Some(last) = entries.last_mut()
+        && last.speaker.eq_ignore_ascii_case(&speaker)
+    {
+        last.text.push('\n');
+        last.text.push_str(trimmed);
+        return;
     }
     entries.push(ParsedEntry {
         speaker,
@@ -415,10 +2610,10 @@ fn push_or_merge_entry(entries: &mut Vec<ParsedEntry>, speaker: String, text: &s
 fn strip_disallowed_speaker_lines(text: &str) -> String {
     let mut kept = Vec::new();
     for line in text.lines() {
-        if let Some((label, _)) = parse_speaker_label(line) {
-            if is_disallowed_speaker(&label) {
-                continue;
-            }
+        if let Some((label, _)) = parse_speaker_label(line)
+            && is_disallowed_speaker(&label)
+        {
+            continue;
         }
         kept.push(line);
     }
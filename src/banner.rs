@@ -0,0 +1,77 @@
+/// Rows of each glyph in the banner font.
+const ROWS: usize = 3;
+
+/// A small FIGlet-style font; every row of a glyph has the same width.
+fn glyph(ch: char) -> Option<[&'static str; ROWS]> {
+    Some(match ch {
+        'A' => [" _ ", "|_|", "| |"],
+        'B' => [" _ ", "|_)", "|_)"],
+        'C' => [" __", "|  ", "|__"],
+        'D' => [" _ ", "| \\", "|_/"],
+        'E' => [" __", "|_ ", "|__"],
+        'F' => [" __", "|_ ", "|  "],
+        'G' => [" __", "/ _", "\\_|"],
+        'H' => ["   ", "|_|", "| |"],
+        'I' => ["___", " | ", "_|_"],
+        'J' => ["  _", "  |", "\\_|"],
+        'K' => ["   ", "|/ ", "|\\ "],
+        'L' => ["   ", "|  ", "|__"],
+        'M' => ["    ", "|\\/|", "|  |"],
+        'N' => ["    ", "|\\ |", "| \\|"],
+        'O' => [" _ ", "/ \\", "\\_/"],
+        'P' => [" _ ", "|_)", "|  "],
+        'Q' => [" _ ", "/ \\", "\\_X"],
+        'R' => [" _ ", "|_)", "| \\"],
+        'S' => [" __", "(_ ", "__)"],
+        'T' => ["___", " | ", " | "],
+        'U' => ["   ", "| |", "|_|"],
+        'V' => ["   ", "\\ /", " V "],
+        'W' => ["    ", "|  |", "|/\\|"],
+        'X' => ["   ", "\\_/", "/ \\"],
+        'Y' => ["   ", "\\_/", " | "],
+        'Z' => ["__", " /", "/_"],
+        '0' => [" _ ", "| |", "|_|"],
+        '1' => ["  ", "/|", " |"],
+        '2' => [" _ ", " _)", "/__"],
+        '3' => ["__ ", "__)", "__)"],
+        '4' => ["   ", "|_|", "  |"],
+        '5' => [" __", "|_ ", "__)"],
+        '6' => [" _ ", "|_ ", "|_)"],
+        '7' => ["__ ", "  /", " / "],
+        '8' => [" _ ", "(_)", "(_)"],
+        '9' => [" _ ", "(_|", "  |"],
+        ' ' => ["  ", "  ", "  "],
+        '-' => ["  ", "__", "  "],
+        '\'' => ["|", " ", " "],
+        '.' => [" ", " ", "."],
+        ',' => [" ", " ", ","],
+        '!' => ["|", "|", "."],
+        '?' => ["__ ", " _)", " . "],
+        _ => return None,
+    })
+}
+
+/// `text` lettered in the banner font and centered in `width` columns. Characters the
+/// font lacks are left out; when the banner is too wide, the plain title is used.
+pub(crate) fn render(text: &str, width: usize) -> Vec<String> {
+    let glyphs: Vec<[&str; ROWS]> =
+        text.trim().to_uppercase().chars().filter_map(glyph).collect();
+    let rows: Vec<String> = (0..ROWS)
+        .map(|row| {
+            let parts: Vec<&str> = glyphs.iter().map(|glyph| glyph[row]).collect();
+            parts.join(" ")
+        })
+        .collect();
+    let banner_width = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+    if banner_width == 0 || banner_width > width {
+        return vec![center(&text.trim().to_uppercase(), width)];
+    }
+    rows.iter()
+        .map(|row| center(row, width).trim_end().to_string())
+        .collect()
+}
+
+fn center(text: &str, width: usize) -> String {
+    let pad = width.saturating_sub(text.chars().count()) / 2;
+    format!("{}{text}", " ".repeat(pad))
+}
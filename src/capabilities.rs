@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::Provider;
+
+/// What the active provider/model combination supports. Features check this and fall
+/// back to prompt-only behavior instead of sending fields the backend would reject.
+#[derive(Clone, Copy)]
+pub(crate) struct Capabilities {
+    pub(crate) tools: bool,
+    pub(crate) structured_output: bool,
+    pub(crate) images: bool,
+    pub(crate) reasoning: bool,
+    /// Tokens the model accepts in one request.
+    pub(crate) context_window: u64,
+}
+
+/// Per-capability overrides from the config file for models the heuristics get wrong.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct CapabilityOverrides {
+    pub(crate) tools: Option<bool>,
+    pub(crate) structured_output: Option<bool>,
+    pub(crate) images: Option<bool>,
+    pub(crate) reasoning: Option<bool>,
+    pub(crate) context_window: Option<u64>,
+}
+
+impl Capabilities {
+    pub(crate) fn detect(provider: Provider, model: &str) -> Self {
+        let reasoning = is_reasoning_model(model);
+        let context_window = context_window(model);
+        match provider {
+            Provider::OpenAi => Self {
+                tools: true,
+                structured_output: true,
+                images: true,
+                reasoning,
+                context_window,
+            },
+            Provider::Azure => Self {
+                tools: true,
+                structured_output: true,
+                images: false,
+                reasoning,
+                context_window,
+            },
+            // OpenRouter proxies many vendors; assume only what nearly all of them accept.
+            Provider::OpenRouter => Self {
+                tools: true,
+                structured_output: false,
+                images: false,
+                reasoning,
+                context_window,
+            },
+        }
+    }
+
+    pub(crate) fn with_overrides(mut self, overrides: CapabilityOverrides) -> Self {
+        self.tools = overrides.tools.unwrap_or(self.tools);
+        self.structured_output = overrides
+            .structured_output
+            .unwrap_or(self.structured_output);
+        self.images = overrides.images.unwrap_or(self.images);
+        self.reasoning = overrides.reasoning.unwrap_or(self.reasoning);
+        self.context_window = overrides.context_window.unwrap_or(self.context_window);
+        self
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        let names = [
+            (self.tools, "tools"),
+            (self.structured_output, "structured output"),
+            (self.images, "images"),
+            (self.reasoning, "reasoning"),
+        ];
+        let enabled: Vec<&str> = names
+            .iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, name)| *name)
+            .collect();
+        if enabled.is_empty() {
+            "text only".to_string()
+        } else {
+            enabled.join(", ")
+        }
+    }
+}
+
+fn is_reasoning_model(model: &str) -> bool {
+    let name = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+    name.starts_with("gpt-5")
+        || name.starts_with("o1")
+        || name.starts_with("o3")
+        || name.starts_with("o4")
+}
+
+/// Published context window by model family; unknown models get the common 128k.
+fn context_window(model: &str) -> u64 {
+    let name = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+    if name.starts_with("gpt-5") {
+        400_000
+    } else if name.starts_with("gpt-4.1") {
+        1_047_576
+    } else if name.starts_with("o1") || name.starts_with("o3") || name.starts_with("o4") {
+        200_000
+    } else {
+        128_000
+    }
+}
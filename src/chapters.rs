@@ -0,0 +1,51 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::app::{LogEntry, LogKind};
+use crate::config::DATA_DIR;
+use crate::error::Result;
+
+const CHAPTERS_DIR: &str = "chapters";
+
+/// A finished chapter; its summary seeds the next one.
+#[derive(Clone, Serialize)]
+pub(crate) struct Chapter {
+    pub(crate) number: u32,
+    pub(crate) summary: String,
+}
+
+/// Writes the chapter's summary and story log to
+/// `.story-gen/chapters/game-<game id>/chapter-<n>.md`.
+pub(crate) fn archive(game_id: u64, chapter: &Chapter, log: &[LogEntry]) -> Result<PathBuf> {
+    let dir = Path::new(DATA_DIR).join(CHAPTERS_DIR).join(format!("game-{game_id}"));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("chapter-{}.md", chapter.number));
+    let contents = format!(
+        "# Chapter {}\n\n## Summary\n\n{}\n\n## Story\n\n{}",
+        chapter.number,
+        chapter.summary,
+        render_story(log)
+    );
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// Player actions and narration as Markdown paragraphs, without system messages.
+pub(crate) fn render_story(log: &[LogEntry]) -> String {
+    let mut contents = String::new();
+    for entry in log {
+        let line = match entry.kind {
+            LogKind::User => format!("> {}", entry.text),
+            LogKind::Assistant => match &entry.speaker {
+                Some(speaker) => format!("{speaker}: {}", entry.text),
+                None => entry.text.clone(),
+            },
+            LogKind::System | LogKind::Error => continue,
+        };
+        contents.push_str(&line);
+        contents.push_str("\n\n");
+    }
+    contents
+}
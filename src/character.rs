@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// Attribute and skill values are clamped to these ranges.
+const ATTRIBUTE_RANGE: (i32, i32) = (1, 10);
+const SKILL_RANGE: (i32, i32) = (0, 5);
+
+/// The player character's attributes and skills, shown in the side panel and sent each turn.
+#[derive(Clone, Serialize)]
+pub(crate) struct CharacterSheet {
+    pub(crate) name: String,
+    /// Who the character was before the story, from character creation.
+    pub(crate) background: String,
+    /// What the character wants, from character creation.
+    pub(crate) goal: String,
+    pub(crate) strength: i32,
+    pub(crate) agility: i32,
+    pub(crate) wits: i32,
+    /// Free-form skills keyed by lowercase name.
+    pub(crate) skills: BTreeMap<String, i32>,
+}
+
+impl Default for CharacterSheet {
+    fn default() -> Self {
+        Self {
+            name: "Adventurer".to_string(),
+            background: String::new(),
+            goal: String::new(),
+            strength: 5,
+            agility: 5,
+            wits: 5,
+            skills: BTreeMap::new(),
+        }
+    }
+}
+
+impl CharacterSheet {
+    /// Value of `strength`, `agility` or `wits`; 0 for unknown names.
+    pub(crate) fn attribute(&self, name: &str) -> i32 {
+        match name {
+            "strength" => self.strength,
+            "agility" => self.agility,
+            "wits" => self.wits,
+            _ => 0,
+        }
+    }
+
+    /// Sets an attribute, or a skill when `name` is not an attribute; returns the stored value.
+    pub(crate) fn set(&mut self, name: &str, value: i32) -> i32 {
+        let clamp = |(min, max): (i32, i32)| value.clamp(min, max);
+        let slot = match name.to_lowercase().as_str() {
+            "strength" | "str" => &mut self.strength,
+            "agility" | "agi" => &mut self.agility,
+            "wits" | "wit" => &mut self.wits,
+            skill => {
+                let value = clamp(SKILL_RANGE);
+                self.skills.insert(skill.to_string(), value);
+                return value;
+            }
+        };
+        *slot = clamp(ATTRIBUTE_RANGE);
+        *slot
+    }
+
+    pub(crate) fn remove_skill(&mut self, name: &str) -> bool {
+        self.skills.remove(&name.to_lowercase()).is_some()
+    }
+
+    /// One-line summary for the system prompt and `/sheet`.
+    pub(crate) fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} (Strength {}, Agility {}, Wits {})",
+            self.name, self.strength, self.agility, self.wits
+        );
+        if !self.skills.is_empty() {
+            let skills: Vec<String> = self
+                .skills
+                .iter()
+                .map(|(skill, value)| format!("{skill} +{value}"))
+                .collect();
+            summary.push_str(&format!("; skills: {}", skills.join(", ")));
+        }
+        if !self.background.is_empty() {
+            summary.push_str(&format!("; background: {}", self.background));
+        }
+        if !self.goal.is_empty() {
+            summary.push_str(&format!("; goal: {}", self.goal));
+        }
+        summary
+    }
+}
+
+/// Steps of the character creation asked at the start of each game, in order.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CreationStep {
+    Name,
+    Background,
+    Goal,
+    Item,
+}
+
+impl CreationStep {
+    pub(crate) fn question(self) -> &'static str {
+        match self {
+            Self::Name => "What is your character's name?",
+            Self::Background => "What is their background? (e.g. disgraced knight, dock thief)",
+            Self::Goal => "What do they want most?",
+            Self::Item => "Name one item they carry.",
+        }
+    }
+
+    /// The step after this one; `None` once creation is complete.
+    pub(crate) fn next(self) -> Option<Self> {
+        match self {
+            Self::Name => Some(Self::Background),
+            Self::Background => Some(Self::Goal),
+            Self::Goal => Some(Self::Item),
+            Self::Item => None,
+        }
+    }
+}
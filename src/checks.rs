@@ -0,0 +1,132 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::character::CharacterSheet;
+
+/// Verbs and verb phrases that mark an action as risky, grouped by the attribute that
+/// resolves it. The first word of each may be inflected (`climbs`, `dodging`).
+const RISKY_VERBS: [(&str, &[&str]); 3] = [
+    (
+        "strength",
+        &[
+            "climb", "lift", "force", "break", "smash", "push", "wrestle", "attack", "fight",
+            "kick down",
+        ],
+    ),
+    (
+        "agility",
+        &[
+            "sneak", "jump", "leap", "dodge", "steal", "pickpocket", "balance", "hide", "flee",
+            "pick the lock", "pick a lock",
+        ],
+    ),
+    (
+        "wits",
+        &[
+            "persuade", "convince", "lie to", "deceive", "bluff", "decipher", "bargain",
+            "haggle", "talk my way",
+        ],
+    ),
+];
+
+pub(crate) struct SkillCheck {
+    pub(crate) attribute: &'static str,
+    pub(crate) skill: Option<(String, i32)>,
+    pub(crate) roll: i32,
+    pub(crate) total: i32,
+    /// d20 + attribute + skill must reach this.
+    pub(crate) dc: i32,
+}
+
+impl SkillCheck {
+    /// Rolls a check if `input` describes a risky action; `None` for ordinary actions.
+    pub(crate) fn attempt(
+        input: &str,
+        sheet: &CharacterSheet,
+        dc: i32,
+        rng: &mut StdRng,
+    ) -> Option<Self> {
+        let lower = input.to_lowercase();
+        let words: Vec<&str> = lower
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+        let attribute = RISKY_VERBS.iter().find_map(|(attribute, verbs)| {
+            verbs
+                .iter()
+                .any(|verb| mentions(&words, verb))
+                .then_some(*attribute)
+        })?;
+        let skill = sheet
+            .skills
+            .iter()
+            .find(|(name, _)| words.contains(&name.as_str()))
+            .map(|(name, bonus)| (name.clone(), *bonus));
+        let roll = rng.random_range(1..=20);
+        let total = roll + sheet.attribute(attribute) + skill.as_ref().map_or(0, |(_, b)| *b);
+        Some(Self {
+            attribute,
+            skill,
+            roll,
+            total,
+            dc,
+        })
+    }
+
+    pub(crate) fn margin(&self) -> i32 {
+        self.total - self.dc
+    }
+
+    /// e.g. `agility check: d20 12 + 5 + stealth 2 = 19 vs 13, success by 6`
+    pub(crate) fn describe(&self, sheet: &CharacterSheet) -> String {
+        let skill = match &self.skill {
+            Some((name, bonus)) => format!(" + {name} {bonus}"),
+            None => String::new(),
+        };
+        let outcome = if self.margin() >= 0 {
+            format!("success by {}", self.margin())
+        } else {
+            format!("failure by {}", -self.margin())
+        };
+        format!(
+            "{} check: d20 {} + {}{skill} = {} vs {}, {outcome}",
+            self.attribute,
+            self.roll,
+            sheet.attribute(self.attribute),
+            self.total,
+            self.dc
+        )
+    }
+
+    /// The binding instruction sent to the narrator.
+    pub(crate) fn system_note(&self) -> String {
+        let verdict = if self.margin() >= 0 { "succeeds" } else { "fails" };
+        format!(
+            "Skill check ({}) for the player's last action: the attempt {verdict} with margin {}. \
+             Narrate an outcome consistent with this; a larger margin means a clearer result.",
+            self.attribute,
+            self.margin().abs()
+        )
+    }
+}
+
+/// Whether `phrase` appears in `words` on word boundaries, its verb in any simple form.
+fn mentions(words: &[&str], phrase: &str) -> bool {
+    let parts: Vec<&str> = phrase.split_whitespace().collect();
+    let Some((verb, rest)) = parts.split_first() else {
+        return false;
+    };
+    words.windows(parts.len()).any(|window| {
+        inflects(window[0], verb) && window[1..].iter().zip(rest).all(|(word, part)| word == part)
+    })
+}
+
+/// `climb`, `climbs`, `climbed`, `climbing`; `dodge`, `dodged`, `dodging`.
+pub(crate) fn inflects(word: &str, verb: &str) -> bool {
+    if word == verb {
+        return true;
+    }
+    let stem = verb.strip_suffix('e').unwrap_or(verb);
+    let suffix = word.strip_prefix(verb).or_else(|| word.strip_prefix(stem));
+    matches!(suffix, Some("s" | "es" | "ed" | "ing"))
+}
@@ -0,0 +1,25 @@
+const MINUTES_PER_DAY: u32 = 24 * 60;
+/// Games start on day 1 at 08:00.
+pub(crate) const START_MINUTES: u32 = 8 * 60;
+
+/// e.g. `Day 2, 14:30 (afternoon)` for minutes elapsed since day 1 midnight.
+pub(crate) fn describe(minutes: u32) -> String {
+    let day = minutes / MINUTES_PER_DAY + 1;
+    let of_day = minutes % MINUTES_PER_DAY;
+    format!(
+        "Day {day}, {:02}:{:02} ({})",
+        of_day / 60,
+        of_day % 60,
+        part_of_day(minutes)
+    )
+}
+
+pub(crate) fn part_of_day(minutes: u32) -> &'static str {
+    match (minutes % MINUTES_PER_DAY) / 60 {
+        5..=7 => "dawn",
+        8..=11 => "morning",
+        12..=16 => "afternoon",
+        17..=20 => "evening",
+        _ => "night",
+    }
+}
@@ -1,19 +1,62 @@
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::io;
 use std::io::Write;
-use std::path::Path;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
-use anyhow::{anyhow, Result};
-use reqwest::blocking::Client;
-use serde_json::json;
+use reqwest::blocking::{Client, ClientBuilder, RequestBuilder};
+use reqwest::Proxy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::capabilities::{Capabilities, CapabilityOverrides};
+use crate::crafting::Recipe;
+use crate::encounters::EncounterTable;
+use crate::error::{Error, Result};
+use crate::graphics::GraphicsMode;
+use crate::keymap::Keymap;
+use crate::theme::{self, Theme};
 
 pub(crate) const MODEL: &str = "gpt-5-mini";
 pub(crate) const API_URL: &str = "https://api.openai.com/v1/responses";
+pub(crate) const API_MODELS_URL: &str = "https://api.openai.com/v1/models";
+pub(crate) const API_EMBEDDINGS_URL: &str = "https://api.openai.com/v1/embeddings";
 pub(crate) const API_INPUT_TOKENS_URL: &str = "https://api.openai.com/v1/responses/input_tokens";
-pub(crate) const MAX_HISTORY_ITEMS: usize = 60;
+pub(crate) const OPENROUTER_API_URL: &str = "https://openrouter.ai/api/v1/responses";
+pub(crate) const OPENROUTER_MODELS_URL: &str = "https://openrouter.ai/api/v1/models";
+pub(crate) const OPENROUTER_EMBEDDINGS_URL: &str = "https://openrouter.ai/api/v1/embeddings";
+pub(crate) const OPENROUTER_KEY_URL: &str = "https://openrouter.ai/api/v1/key";
+pub(crate) const AZURE_API_VERSION: &str = "2025-04-01-preview";
+pub(crate) const ENV_FILE: &str = ".env";
+pub(crate) const DATA_DIR: &str = ".story-gen";
+pub(crate) const CONFIG_FILE: &str = "config.json";
+/// Routes every game to the same prompt cache; the cacheable prefix is identical across games.
+pub(crate) const PROMPT_CACHE_KEY: &str = "story-gen-narrator";
+/// Highest `<KEY_VAR>_<n>` suffix read for key rotation.
+pub(crate) const MAX_ROTATION_KEYS: usize = 20;
 pub(crate) const MAIN_MAX_OUTPUT_TOKENS: u32 = 800;
+/// Default model for scene art and state extraction, which need far less than narration.
+pub(crate) const UTILITY_MODEL: &str = "gpt-5-nano";
+pub(crate) const EMBEDDING_MODEL: &str = "text-embedding-3-small";
+pub(crate) const SCENE_MAX_OUTPUT_TOKENS: u32 = 600;
+pub(crate) const EXTRACTION_MAX_OUTPUT_TOKENS: u32 = 400;
+pub(crate) const CRAFTING_MAX_OUTPUT_TOKENS: u32 = 300;
+pub(crate) const SUMMARY_MAX_OUTPUT_TOKENS: u32 = 500;
+pub(crate) const SCENE_PROMPT: &str = r#"You draw scenes for a text adventure as ASCII art.
+Draw the described scene in at most 60 columns and 16 rows using plain ASCII characters.
+Output only the art: no title, caption, explanation, or markdown code fences.
+"#;
+
+/// Added to the scene context when the player asks for a redraw.
+pub(crate) const SCENE_REDRAW_NOTE: &str =
+    "Redraw: use a different composition and viewpoint from the previous art.";
+
+pub(crate) const SCENE_IMAGE_PROMPT: &str = "Paint this scene from a text adventure as a \
+wide, atmospheric illustration. No text, captions or borders.";
 
 pub(crate) const SYSTEM_PROMPT: &str = r#"You are a text adventure game narrator.
 Write in second person, present tense.
@@ -28,34 +71,971 @@ During dialogue, the Narrator should stay silent unless ending the dialogue; use
 Narrator describes actions and scene changes; characters only speak dialogue. If both are needed, use two lines: Narrator first, then the character.
 When a character speaks, use quotation marks around their words.
 Keep character names consistent when labeling lines.
+Merchants name a clear price in gold for each item they offer. The game handles purchases and the player's gold, so never complete a sale yourself.
+Facts in the "Lore" field are canon: never contradict them, even if earlier turns are no longer visible.
+Keep narration consistent with the "Time" and "Weather" fields: light, activity, who is around and the sky should fit them.
+Respect the "Known exits" field: places connect only as established. When narration reveals a new way between places, call add_exit.
+Characters remember how the player treated them: follow the "Relationships" field, and call change_affinity when the player helps, flatters, insults or harms someone.
+Only what the "Equipped" field lists is in the player's hands or on their body; other items are packed away until equipped.
+When the player is hurt or healed, call the change_health tool if you have it; otherwise add a tag like [HP -3] or [HP +2] at the end of the reply.
 Keep responses concise: 1-2 short paragraphs, then ask what the player does next.
 Do not use markdown code fences or JSON in your response.
 Avoid meta commentary about being an AI.
 "#;
 
-pub(crate) fn load_or_prompt_api_key() -> Result<String> {
-    let env_path = Path::new(".env");
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Provider {
+    #[default]
+    OpenAi,
+    OpenRouter,
+    Azure,
+}
+
+impl Provider {
+    pub(crate) fn key_var(self) -> &'static str {
+        match self {
+            Provider::OpenAi => "OPENAI_API_KEY",
+            Provider::OpenRouter => "OPENROUTER_API_KEY",
+            Provider::Azure => "AZURE_OPENAI_API_KEY",
+        }
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Provider::OpenAi => "OpenAI",
+            Provider::OpenRouter => "OpenRouter",
+            Provider::Azure => "Azure OpenAI",
+        }
+    }
+}
+
+/// OpenRouter routing preferences, sent as the `provider` object of each request.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct OpenRouterRouting {
+    pub(crate) order: Vec<String>,
+    pub(crate) allow_fallbacks: Option<bool>,
+    pub(crate) max_price: Option<MaxPrice>,
+}
+
+/// Price caps in USD per million tokens.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct MaxPrice {
+    pub(crate) prompt: Option<f64>,
+    pub(crate) completion: Option<f64>,
+}
+
+/// Azure OpenAI resource details; requests are addressed to a deployment, not a model.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct AzureSettings {
+    /// Resource endpoint, e.g. `https://my-resource.openai.azure.com`.
+    pub(crate) endpoint: String,
+    pub(crate) deployment: String,
+    pub(crate) api_version: String,
+}
+
+impl Default for AzureSettings {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            deployment: String::new(),
+            api_version: AZURE_API_VERSION.to_string(),
+        }
+    }
+}
+
+/// A shell command or webhook run after every completed turn with the turn JSON as input.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct TurnHook {
+    /// Run through the platform shell with the turn JSON on stdin.
+    pub(crate) command: Option<String>,
+    /// POSTed the turn JSON as the request body.
+    pub(crate) webhook: Option<String>,
+}
+
+/// Desired reply length in words; the narrator is told the range and nudged when it drifts.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct WordTarget {
+    pub(crate) min: u32,
+    pub(crate) max: u32,
+}
+
+/// What a model charges, in USD per million tokens, for the session cost in the status bar.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct ModelPrice {
+    pub(crate) input: f64,
+    pub(crate) output: f64,
+}
+
+/// Exponential backoff for transient request failures (network, timeouts, 5xx, 429).
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct RetryPolicy {
+    /// Total attempts including the first; 1 disables retries.
+    pub(crate) max_attempts: u32,
+    pub(crate) base_delay_ms: u64,
+    pub(crate) max_delay_ms: u64,
+    /// Fraction of each delay randomized in either direction, 0.0-1.0.
+    pub(crate) jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay_ms: 500,
+            max_delay_ms: 8_000,
+            jitter: 0.25,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (1-based).
+    pub(crate) fn delay(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(16);
+        let base = self
+            .base_delay_ms
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay_ms) as f64;
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 + rand::random_range(-jitter..=jitter);
+        Duration::from_millis((base * factor) as u64)
+    }
+}
+
+/// How hard reasoning models think before replying; `minimal` keeps turns fast.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ReasoningEffort {
+    #[default]
+    Minimal,
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "minimal" => Some(Self::Minimal),
+            "low" => Some(Self::Low),
+            "medium" => Some(Self::Medium),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Minimal => "minimal",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
+/// Reply verbosity hint understood by GPT-5 family models.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Verbosity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Verbosity {
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "medium" => Some(Self::Medium),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
+/// How forgiving the narrator and local mechanics are.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Brutal,
+}
+
+impl Difficulty {
+    pub(crate) const ALL: [Self; 3] = [Self::Easy, Self::Normal, Self::Brutal];
+
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "easy" => Some(Self::Easy),
+            "normal" => Some(Self::Normal),
+            "brutal" => Some(Self::Brutal),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Easy => "easy",
+            Self::Normal => "normal",
+            Self::Brutal => "brutal",
+        }
+    }
+
+    /// The DC skill checks roll against.
+    pub(crate) fn check_dc(self) -> i32 {
+        match self {
+            Self::Easy => 10,
+            Self::Normal => 13,
+            Self::Brutal => 16,
+        }
+    }
+
+    /// Appended to the system prompt; normal difficulty adds nothing.
+    pub(crate) fn prompt(self) -> &'static str {
+        match self {
+            Self::Easy => "\nDifficulty: easy. Be forgiving: failures cost time or pride rather than blood, injuries are light, and the player character never dies from a single mistake.",
+            Self::Normal => "",
+            Self::Brutal => "\nDifficulty: brutal. Consequences are lethal and lasting: enemies fight to win, injuries are severe, resources run short, and reckless actions can kill the player character outright.",
+        }
+    }
+}
+
+/// Sampling and length parameters sent with every narration request.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct GenerationParams {
+    pub(crate) max_output_tokens: u32,
+    /// Only sent to reasoning models.
+    pub(crate) reasoning_effort: ReasoningEffort,
+    /// 0.0-2.0; only sent to non-reasoning models, which are the ones that accept it.
+    pub(crate) temperature: Option<f32>,
+    /// Only sent to reasoning models.
+    pub(crate) verbosity: Option<Verbosity>,
+}
+
+impl Default for GenerationParams {
+    fn default() -> Self {
+        Self {
+            max_output_tokens: MAIN_MAX_OUTPUT_TOKENS,
+            reasoning_effort: ReasoningEffort::default(),
+            temperature: None,
+            verbosity: None,
+        }
+    }
+}
+
+/// Long-term memory: past turns are embedded and the most relevant ones recalled each turn.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct MemorySettings {
+    /// Off by default: recalling embeds the player's input before each narration request,
+    /// adding a round trip to every turn.
+    pub(crate) enabled: bool,
+    /// Embedding model; on Azure, the embedding deployment name.
+    pub(crate) model: Option<String>,
+    /// Past moments recalled per turn.
+    pub(crate) top_k: usize,
+}
+
+impl Default for MemorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: None,
+            top_k: 3,
+        }
+    }
+}
+
+/// How to announce a finished turn while the terminal is in the background.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Notifications {
+    pub(crate) bell: bool,
+    /// Also show a desktop notification (`notify-send` on Linux, `osascript` on macOS).
+    pub(crate) desktop: bool,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self {
+            bell: true,
+            desktop: false,
+        }
+    }
+}
+
+/// Starting pane sizes; Ctrl+arrows and Ctrl+B adjust them for the session.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct PaneLayout {
+    /// Share of the left column's height given to the Scene pane, 10-90.
+    pub(crate) scene_percent: u16,
+    /// Width of the side panel in columns.
+    pub(crate) side_width: u16,
+    pub(crate) side_panel: bool,
+}
+
+impl PaneLayout {
+    pub(crate) const SCENE_PERCENT: RangeInclusive<u16> = 10..=90;
+    pub(crate) const SIDE_WIDTH: RangeInclusive<u16> = 20..=80;
+}
 
-    if let Some(key) = read_env_key() {
-        match validate_api_key(&key) {
+impl Default for PaneLayout {
+    fn default() -> Self {
+        Self {
+            scene_percent: 50,
+            side_width: 30,
+            side_panel: true,
+        }
+    }
+}
+
+/// How scene art is drawn, chosen with `/scene style`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SceneStyle {
+    #[default]
+    Classic,
+    Dense,
+    Minimal,
+    Box,
+    Banner,
+}
+
+impl SceneStyle {
+    pub(crate) const ALL: [Self; 5] =
+        [Self::Classic, Self::Dense, Self::Minimal, Self::Box, Self::Banner];
+
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        Self::ALL.into_iter().find(|style| style.label() == text)
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Classic => "classic",
+            Self::Dense => "dense",
+            Self::Minimal => "minimal",
+            Self::Box => "box",
+            Self::Banner => "banner",
+        }
+    }
+
+    /// Appended to the scene prompt; classic adds nothing.
+    pub(crate) fn prompt(self) -> &'static str {
+        match self {
+            Self::Classic => "",
+            Self::Dense => "\nStyle: dense shading. Fill the frame, building light and shadow from a ramp of characters such as .:-=+*#%@.",
+            Self::Minimal => "\nStyle: minimalist line art. Use sparse outlines with lots of empty space and no shading.",
+            Self::Box => "\nStyle: box-drawing. Build the scene from Unicode box-drawing and block characters (such as ─│┌┐└┘═║░▒▓█) instead of plain ASCII.",
+            Self::Banner => "\nStyle: banner. Letter the location's name in large block text across the top, with a small vignette of the scene beneath it.",
+        }
+    }
+}
+
+/// A runtime change requested by a slash command, applied and persisted by the main loop.
+pub(crate) enum SettingChange {
+    Model(String),
+    TimeoutSecs(u64),
+    ConnectTimeoutSecs(u64),
+    MaxOutputTokens(u32),
+    ReasoningEffort(ReasoningEffort),
+    Temperature(Option<f32>),
+    Verbosity(Option<Verbosity>),
+    MinutesPerTurn(u32),
+    ChoiceMode(bool),
+    Difficulty(Difficulty),
+    Theme(String),
+    Typewriter(u32),
+    ShowScene(bool),
+    SceneStyle(SceneStyle),
+}
+
+/// User settings loaded from `.story-gen/config.json`; every field is optional.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    pub(crate) provider: Provider,
+    pub(crate) api_url: Option<String>,
+    pub(crate) model: String,
+    pub(crate) headers: BTreeMap<String, String>,
+    pub(crate) openrouter: OpenRouterRouting,
+    pub(crate) azure: AzureSettings,
+    pub(crate) hooks: Vec<TurnHook>,
+    pub(crate) word_target: Option<WordTarget>,
+    pub(crate) capabilities: CapabilityOverrides,
+    pub(crate) retry: RetryPolicy,
+    /// Whole-request timeout for narration; reasoning models can need several minutes.
+    pub(crate) timeout_secs: u64,
+    pub(crate) connect_timeout_secs: u64,
+    /// Proxy for all API traffic (`http://`, `https://` or `socks5://`). When unset,
+    /// `HTTPS_PROXY`, `HTTP_PROXY` and `ALL_PROXY` from the environment are used.
+    pub(crate) proxy: Option<String>,
+    /// Tokens of history kept in context; the oldest turns are dropped beyond it. When
+    /// unset, history gets a quarter of the model's context window.
+    pub(crate) history_token_budget: Option<u64>,
+    /// Ask the provider to cache the static system prompt and history prefix.
+    pub(crate) prompt_cache: bool,
+    pub(crate) generation: GenerationParams,
+    /// Model for scene art. Defaults to a low-cost model; on Azure, to the narration deployment.
+    pub(crate) scene_model: Option<String>,
+    /// Run a state extraction pass after replies that did not report state themselves.
+    pub(crate) state_extraction: bool,
+    /// Model for state extraction; defaults like `scene_model`.
+    pub(crate) extraction_model: Option<String>,
+    /// Roll a local skill check when the player attempts something risky.
+    pub(crate) skill_checks: bool,
+    pub(crate) difficulty: Difficulty,
+    /// In-game minutes that pass with each completed turn.
+    pub(crate) minutes_per_turn: u32,
+    /// Fixed `/craft` combinations; anything else is left to the extraction model.
+    pub(crate) recipes: Vec<Recipe>,
+    pub(crate) encounters: EncounterTable,
+    pub(crate) memory: MemorySettings,
+    /// Ask structured-output models for three numbered choices each turn.
+    pub(crate) choice_mode: bool,
+    /// Canned actions submitted by F1-F4, in order.
+    pub(crate) quick_actions: Vec<String>,
+    pub(crate) keys: Keymap,
+    /// Name of a built-in theme or one from `themes`.
+    pub(crate) theme: String,
+    pub(crate) themes: BTreeMap<String, Theme>,
+    /// Set by `--no-color` or `NO_COLOR`; overrides `theme` for this run only.
+    #[serde(skip)]
+    pub(crate) no_color: bool,
+    /// Characters of each reply revealed per 200ms tick; 0 shows replies at once.
+    pub(crate) typewriter_chars: u32,
+    pub(crate) layout: PaneLayout,
+    /// Show the Scene pane and request scene art; off gives the Story pane the full height.
+    pub(crate) show_scene: bool,
+    pub(crate) scene_style: SceneStyle,
+    pub(crate) notifications: Notifications,
+    /// Draw scene art as a generated picture in terminals with kitty or sixel graphics.
+    pub(crate) graphics: GraphicsMode,
+    /// Prices by model or Azure deployment name; turns on unlisted models are left out of
+    /// the session cost.
+    pub(crate) prices: BTreeMap<String, ModelPrice>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            provider: Provider::OpenAi,
+            api_url: None,
+            model: MODEL.to_string(),
+            headers: BTreeMap::new(),
+            openrouter: OpenRouterRouting::default(),
+            azure: AzureSettings::default(),
+            hooks: Vec::new(),
+            word_target: None,
+            capabilities: CapabilityOverrides::default(),
+            retry: RetryPolicy::default(),
+            timeout_secs: 60,
+            connect_timeout_secs: 10,
+            proxy: None,
+            history_token_budget: None,
+            prompt_cache: true,
+            generation: GenerationParams::default(),
+            scene_model: None,
+            state_extraction: true,
+            extraction_model: None,
+            skill_checks: true,
+            difficulty: Difficulty::default(),
+            minutes_per_turn: 15,
+            recipes: Vec::new(),
+            encounters: EncounterTable::default(),
+            memory: MemorySettings::default(),
+            choice_mode: false,
+            quick_actions: ["look around", "check inventory", "continue", "wait"]
+                .map(String::from)
+                .to_vec(),
+            keys: Keymap::default(),
+            theme: theme::DEFAULT_THEME.to_string(),
+            themes: BTreeMap::new(),
+            no_color: false,
+            typewriter_chars: 40,
+            layout: PaneLayout::default(),
+            show_scene: true,
+            scene_style: SceneStyle::default(),
+            notifications: Notifications::default(),
+            graphics: GraphicsMode::default(),
+            prices: BTreeMap::from([
+                (MODEL.to_string(), ModelPrice { input: 0.25, output: 2.0 }),
+                (UTILITY_MODEL.to_string(), ModelPrice { input: 0.05, output: 0.4 }),
+            ]),
+        }
+    }
+}
+
+impl Settings {
+    pub(crate) fn path() -> PathBuf {
+        Path::new(DATA_DIR).join(CONFIG_FILE)
+    }
+
+    pub(crate) fn load() -> Result<Self> {
+        let path = Self::path();
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(Error::Io(err)),
+        };
+        let settings: Self =
+            serde_json::from_str(&contents).map_err(|err| Error::SaveCorrupt {
+                path: path.clone(),
+                message: err.to_string(),
+            })?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        let path = Self::path();
+        fs::create_dir_all(DATA_DIR)?;
+        let mut contents = serde_json::to_string_pretty(self)?;
+        contents.push('\n');
+        fs::write(&path, contents)?;
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(target) = self.word_target
+            && (target.min == 0 || target.min > target.max)
+        {
+            return Err(Error::Config(format!(
+                "word_target needs 0 < min <= max in {}",
+                Self::path().display()
+            )));
+        }
+        if let Some(Err(err)) = self.proxy() {
+            return Err(err);
+        }
+        if self.generation.max_output_tokens == 0
+            || self
+                .generation
+                .temperature
+                .is_some_and(|t| !(0.0..=2.0).contains(&t))
+        {
+            return Err(Error::Config(format!(
+                "generation needs max_output_tokens > 0 and temperature within 0-2 in {}",
+                Self::path().display()
+            )));
+        }
+        if self.provider == Provider::Azure
+            && (self.azure.endpoint.trim().is_empty() || self.azure.deployment.trim().is_empty())
+        {
+            return Err(Error::Config(format!(
+                "Azure provider requires azure.endpoint and azure.deployment in {}",
+                Self::path().display()
+            )));
+        }
+        if !PaneLayout::SCENE_PERCENT.contains(&self.layout.scene_percent)
+            || !PaneLayout::SIDE_WIDTH.contains(&self.layout.side_width)
+        {
+            return Err(Error::Config(format!(
+                "layout needs scene_percent within 10-90 and side_width within 20-80 in {}",
+                Self::path().display()
+            )));
+        }
+        if theme::find(&self.theme, &self.themes).is_none() {
+            return Err(Error::Config(format!(
+                "unknown theme {:?} in {}; choose one of: {}",
+                self.theme,
+                Self::path().display(),
+                theme::names(&self.themes).join(", ")
+            )));
+        }
+        if let Err(message) = self.keys.validate() {
+            return Err(Error::Config(format!("{message} in {}", Self::path().display())));
+        }
+        Ok(())
+    }
+
+    /// The selected theme's colors.
+    pub(crate) fn theme(&self) -> Theme {
+        if self.no_color {
+            return Theme::plain();
+        }
+        theme::find(&self.theme, &self.themes).unwrap_or_default()
+    }
+
+    pub(crate) fn responses_url(&self) -> String {
+        if let Some(url) = self.api_url.as_deref() {
+            return url.to_string();
+        }
+        match self.provider {
+            Provider::OpenAi => API_URL.to_string(),
+            Provider::OpenRouter => OPENROUTER_API_URL.to_string(),
+            Provider::Azure => self.azure_url("responses"),
+        }
+    }
+
+    pub(crate) fn models_url(&self) -> String {
+        if let Some(url) = self.api_url.as_deref() {
+            let base = url.trim_end_matches('/').trim_end_matches("/responses");
+            return format!("{base}/models");
+        }
+        match self.provider {
+            Provider::OpenAi => API_MODELS_URL.to_string(),
+            Provider::OpenRouter => OPENROUTER_MODELS_URL.to_string(),
+            Provider::Azure => self.azure_url("models"),
+        }
+    }
+
+    pub(crate) fn embeddings_url(&self) -> String {
+        if let Some(url) = self.api_url.as_deref() {
+            let base = url.trim_end_matches('/').trim_end_matches("/responses");
+            return format!("{base}/embeddings");
+        }
+        match self.provider {
+            Provider::OpenAi => API_EMBEDDINGS_URL.to_string(),
+            Provider::OpenRouter => OPENROUTER_EMBEDDINGS_URL.to_string(),
+            Provider::Azure => self.azure_url("embeddings"),
+        }
+    }
+
+    fn azure_url(&self, path: &str) -> String {
+        format!(
+            "{}/openai/{path}?api-version={}",
+            self.azure.endpoint.trim_end_matches('/'),
+            self.azure.api_version
+        )
+    }
+
+    /// The `model` field sent in request bodies; Azure expects the deployment name.
+    pub(crate) fn request_model(&self) -> &str {
+        match self.provider {
+            Provider::Azure => &self.azure.deployment,
+            _ => &self.model,
+        }
+    }
+
+    /// Price of the narration model, looked up by the name sent in requests (the
+    /// deployment on Azure), then by the configured model name.
+    pub(crate) fn price(&self) -> Option<ModelPrice> {
+        self.prices
+            .get(self.request_model())
+            .or_else(|| self.prices.get(&self.model))
+            .copied()
+    }
+
+    /// The `model` field sent in scene art requests.
+    pub(crate) fn scene_request_model(&self) -> String {
+        self.utility_model(self.scene_model.as_deref())
+    }
+
+    /// The `model` field sent in state extraction requests.
+    pub(crate) fn extraction_request_model(&self) -> String {
+        self.utility_model(self.extraction_model.as_deref())
+    }
+
+    /// The `model` field sent in embedding requests.
+    pub(crate) fn embedding_request_model(&self) -> String {
+        let configured = self.memory.model.as_deref().map(str::trim);
+        if let Some(model) = configured.filter(|m| !m.is_empty()) {
+            return model.to_string();
+        }
+        match self.provider {
+            Provider::OpenRouter => format!("openai/{EMBEDDING_MODEL}"),
+            Provider::OpenAi | Provider::Azure => EMBEDDING_MODEL.to_string(),
+        }
+    }
+
+    fn utility_model(&self, configured: Option<&str>) -> String {
+        if let Some(model) = configured.map(str::trim).filter(|m| !m.is_empty()) {
+            return model.to_string();
+        }
+        match self.provider {
+            Provider::OpenAi => UTILITY_MODEL.to_string(),
+            Provider::OpenRouter => format!("openai/{UTILITY_MODEL}"),
+            Provider::Azure => self.azure.deployment.clone(),
+        }
+    }
+
+    pub(crate) fn capabilities(&self) -> Capabilities {
+        Capabilities::detect(self.provider, self.request_model()).with_overrides(self.capabilities)
+    }
+
+    /// Tokens of history kept in context, leaving the rest of the window for the
+    /// system prompt, memories and the reply.
+    pub(crate) fn history_token_budget(&self) -> u64 {
+        self.history_token_budget
+            .unwrap_or(self.capabilities().context_window / 4)
+    }
+
+    /// The endpoint counting a request's input tokens, where the provider has one.
+    pub(crate) fn input_tokens_url(&self) -> Option<String> {
+        match self.provider {
+            Provider::OpenAi => Some(API_INPUT_TOKENS_URL.to_string()),
+            Provider::Azure => Some(self.azure_url("responses/input_tokens")),
+            Provider::OpenRouter => None,
+        }
+    }
+
+    /// Client builder with the shared connection settings; callers choose the request timeout.
+    pub(crate) fn client_builder(&self) -> Result<ClientBuilder> {
+        let mut builder = Client::builder()
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs.max(1)));
+        if let Some(proxy) = self.proxy() {
+            builder = builder.proxy(proxy?);
+        }
+        Ok(builder)
+    }
+
+    fn proxy(&self) -> Option<Result<Proxy>> {
+        let url = self.proxy.as_deref()?.trim();
+        if url.is_empty() {
+            return None;
+        }
+        Some(Proxy::all(url).map_err(|err| Error::Config(format!("invalid proxy {url}: {err}"))))
+    }
+
+    pub(crate) fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.max(1))
+    }
+
+    /// Applies a runtime change and returns a description for the log.
+    pub(crate) fn apply(&mut self, change: &SettingChange) -> String {
+        match change {
+            SettingChange::Model(model) => {
+                self.set_model(model);
+                format!("Model set to: {model}")
+            }
+            SettingChange::TimeoutSecs(secs) => {
+                self.timeout_secs = *secs;
+                format!("Request timeout set to {secs}s")
+            }
+            SettingChange::ConnectTimeoutSecs(secs) => {
+                self.connect_timeout_secs = *secs;
+                format!("Connect timeout set to {secs}s")
+            }
+            SettingChange::MaxOutputTokens(tokens) => {
+                self.generation.max_output_tokens = *tokens;
+                format!("Max output tokens set to {tokens}")
+            }
+            SettingChange::ReasoningEffort(effort) => {
+                self.generation.reasoning_effort = *effort;
+                format!("Reasoning effort set to {}", effort.label())
+            }
+            SettingChange::Temperature(temperature) => {
+                self.generation.temperature = *temperature;
+                match temperature {
+                    Some(value) => format!("Temperature set to {value}"),
+                    None => "Temperature reset to the model default".to_string(),
+                }
+            }
+            SettingChange::MinutesPerTurn(minutes) => {
+                self.minutes_per_turn = *minutes;
+                format!("Each turn now advances the clock {minutes} minutes")
+            }
+            SettingChange::ChoiceMode(enabled) => {
+                self.choice_mode = *enabled;
+                if *enabled {
+                    "Choice mode on: type 1-3 and Enter to pick a suggested action".to_string()
+                } else {
+                    "Choice mode off".to_string()
+                }
+            }
+            SettingChange::Difficulty(difficulty) => {
+                self.difficulty = *difficulty;
+                format!("Difficulty set to {}", difficulty.label())
+            }
+            SettingChange::Theme(name) => {
+                if theme::find(name, &self.themes).is_none() {
+                    return format!(
+                        "Unknown theme {name}; choose one of: {}",
+                        theme::names(&self.themes).join(", ")
+                    );
+                }
+                self.theme = name.clone();
+                format!("Theme set to {name}")
+            }
+            SettingChange::Typewriter(chars) => {
+                self.typewriter_chars = *chars;
+                if *chars == 0 {
+                    "Typewriter off: replies appear at once".to_string()
+                } else {
+                    format!("Typewriter reveals {chars} characters per tick")
+                }
+            }
+            SettingChange::ShowScene(enabled) => {
+                self.show_scene = *enabled;
+                if *enabled {
+                    "Scene pane on".to_string()
+                } else {
+                    "Scene pane off: no scene art will be requested".to_string()
+                }
+            }
+            SettingChange::SceneStyle(style) => {
+                self.scene_style = *style;
+                format!("Scene art style set to {}", style.label())
+            }
+            SettingChange::Verbosity(verbosity) => {
+                self.generation.verbosity = *verbosity;
+                match verbosity {
+                    Some(value) => format!("Verbosity set to {}", value.label()),
+                    None => "Verbosity reset to the model default".to_string(),
+                }
+            }
+        }
+    }
+
+    /// Changes the model used for narration; on Azure this selects a deployment.
+    pub(crate) fn set_model(&mut self, model: &str) {
+        match self.provider {
+            Provider::Azure => self.azure.deployment = model.to_string(),
+            _ => self.model = model.to_string(),
+        }
+    }
+
+    /// Adds auth and any configured extra headers (e.g. OpenRouter's `HTTP-Referer`).
+    pub(crate) fn authorize(&self, request: RequestBuilder, api_key: &str) -> RequestBuilder {
+        let mut request = match self.provider {
+            Provider::Azure => request.header("api-key", api_key),
+            _ => request.bearer_auth(api_key),
+        };
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        request
+    }
+
+    /// Provider-specific fields merged into every request body.
+    pub(crate) fn extend_request_body(&self, body: &mut Value) {
+        if self.prompt_cache && self.provider != Provider::OpenRouter {
+            body["prompt_cache_key"] = json!(PROMPT_CACHE_KEY);
+        }
+        if self.provider != Provider::OpenRouter {
+            return;
+        }
+        let routing = &self.openrouter;
+        let mut provider = serde_json::Map::new();
+        if !routing.order.is_empty() {
+            provider.insert("order".to_string(), json!(routing.order));
+        }
+        if let Some(allow) = routing.allow_fallbacks {
+            provider.insert("allow_fallbacks".to_string(), json!(allow));
+        }
+        if let Some(max_price) = &routing.max_price {
+            let mut price = serde_json::Map::new();
+            if let Some(prompt) = max_price.prompt {
+                price.insert("prompt".to_string(), json!(prompt));
+            }
+            if let Some(completion) = max_price.completion {
+                price.insert("completion".to_string(), json!(completion));
+            }
+            if !price.is_empty() {
+                provider.insert("max_price".to_string(), Value::Object(price));
+            }
+        }
+        if !provider.is_empty() {
+            body["provider"] = Value::Object(provider);
+        }
+    }
+}
+
+/// API keys tried in order; the active key advances when one is rate limited or out of quota.
+pub(crate) struct KeyRing {
+    keys: Vec<String>,
+    current: AtomicUsize,
+}
+
+impl KeyRing {
+    pub(crate) fn new(keys: Vec<String>) -> Self {
+        Self {
+            keys,
+            current: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn current(&self) -> &str {
+        let index = self.current.load(Ordering::Relaxed) % self.keys.len();
+        &self.keys[index]
+    }
+
+    /// Switches to the next key; returns `false` when there is nothing to rotate to.
+    pub(crate) fn rotate(&self) -> bool {
+        if self.keys.len() < 2 {
+            return false;
+        }
+        self.current.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &str> {
+        self.keys.iter().map(String::as_str)
+    }
+}
+
+/// Loads the primary key (prompting if needed) plus any `<KEY_VAR>_1..n` rotation keys.
+pub(crate) fn load_key_ring(settings: &Settings) -> Result<KeyRing> {
+    let primary = load_or_prompt_api_key(settings)?;
+    let mut keys = vec![primary];
+    for key in read_rotation_keys(Path::new(ENV_FILE), settings.provider.key_var()) {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    if keys.len() > 1 {
+        println!("Loaded {} API keys for rotation.", keys.len());
+    }
+    Ok(KeyRing::new(keys))
+}
+
+fn read_rotation_keys(path: &Path, key_var: &str) -> Vec<String> {
+    (1..=MAX_ROTATION_KEYS)
+        .filter_map(|n| {
+            let var = format!("{key_var}_{n}");
+            read_env_key(&var).or_else(|| read_key_from_env_file(path, &var))
+        })
+        .collect()
+}
+
+fn load_or_prompt_api_key(settings: &Settings) -> Result<String> {
+    let env_path = Path::new(ENV_FILE);
+    let key_var = settings.provider.key_var();
+
+    if let Some(key) = read_env_key(key_var) {
+        match validate_api_key(settings, &key) {
             Ok(()) => return Ok(key),
             Err(err) => {
-                println!("OPENAI_API_KEY from environment is invalid: {err}");
+                println!("{key_var} from environment is invalid: {err}");
             }
         }
     }
 
-    if let Some(key) = read_key_from_env_file(env_path) {
-        match validate_api_key(&key) {
+    if let Some(key) = read_key_from_env_file(env_path, key_var) {
+        match validate_api_key(settings, &key) {
             Ok(()) => return Ok(key),
             Err(err) => {
-                println!("OPENAI_API_KEY from .env is invalid: {err}");
+                println!("{key_var} from .env is invalid: {err}");
             }
         }
     }
 
     loop {
-        println!("OPENAI_API_KEY not found. Paste your API key and press Enter:");
+        println!("{key_var} not found. Paste your API key and press Enter:");
         let mut input = String::new();
         io::stdin().read_line(&mut input)?;
         let key = input.trim();
@@ -64,9 +1044,9 @@ pub(crate) fn load_or_prompt_api_key() -> Result<String> {
             continue;
         }
 
-        match validate_api_key(key) {
+        match validate_api_key(settings, key) {
             Ok(()) => {
-                upsert_env_key(env_path, key)?;
+                upsert_env_key(env_path, key_var, key)?;
                 return Ok(key.to_string());
             }
             Err(err) => {
@@ -76,63 +1056,48 @@ pub(crate) fn load_or_prompt_api_key() -> Result<String> {
     }
 }
 
-fn validate_api_key(api_key: &str) -> Result<()> {
-    println!("Validating OpenAI API key...");
+fn validate_api_key(settings: &Settings, api_key: &str) -> Result<()> {
+    let label = settings.provider.label();
+    println!("Validating {label} API key...");
     let _ = io::stdout().flush();
-    let client = Client::builder()
+    let client = settings
+        .client_builder()?
         .timeout(Duration::from_secs(15))
         .build()?;
-    let body = json!({
-        "model": MODEL,
-        "input": "Test request to validate API key."
-    });
-    let response = client
-        .post(API_INPUT_TOKENS_URL)
-        .bearer_auth(api_key)
-        .json(&body)
-        .send()?;
+    let request = match settings.provider {
+        Provider::OpenAi => client.post(API_INPUT_TOKENS_URL).json(&json!({
+            "model": settings.model,
+            "input": "Test request to validate API key."
+        })),
+        Provider::OpenRouter => client.get(OPENROUTER_KEY_URL),
+        Provider::Azure => client.get(settings.azure_url("models")),
+    };
+    let response = settings.authorize(request, api_key).send()?;
 
     if response.status().is_success() {
         return Ok(());
     }
-
-    let status = response.status();
-    let text = response.text().unwrap_or_default();
-    let message = extract_api_error_message(&text).unwrap_or(text);
-    Err(anyhow!("OpenAI API error ({status}): {message}"))
+    Err(Error::from_response(label, response))
 }
 
-fn extract_api_error_message(body: &str) -> Option<String> {
-    let value: serde_json::Value = serde_json::from_str(body).ok()?;
-    let message = value
-        .get("error")?
-        .get("message")?
-        .as_str()?
-        .trim();
-    if message.is_empty() {
-        None
-    } else {
-        Some(message.to_string())
-    }
-}
-
-fn read_env_key() -> Option<String> {
-    env::var("OPENAI_API_KEY")
+fn read_env_key(key_var: &str) -> Option<String> {
+    env::var(key_var)
         .ok()
         .and_then(|key| normalize_key(&key))
 }
 
-fn read_key_from_env_file(path: &Path) -> Option<String> {
+fn read_key_from_env_file(path: &Path, key_var: &str) -> Option<String> {
+    let prefix = format!("{key_var}=");
     let contents = fs::read_to_string(path).ok()?;
     for line in contents.lines() {
         let trimmed = line.trim();
         if trimmed.is_empty() || trimmed.starts_with('#') {
             continue;
         }
-        if let Some(value) = trimmed.strip_prefix("OPENAI_API_KEY=") {
-            if let Some(key) = normalize_key(value) {
-                return Some(key);
-            }
+        if let Some(value) = trimmed.strip_prefix(prefix.as_str())
+            && let Some(key) = normalize_key(value)
+        {
+            return Some(key);
         }
     }
     None
@@ -162,21 +1127,22 @@ fn normalize_key(raw: &str) -> Option<String> {
     }
 }
 
-fn upsert_env_key(path: &Path, key: &str) -> Result<()> {
+fn upsert_env_key(path: &Path, key_var: &str, key: &str) -> Result<()> {
+    let prefix = format!("{key_var}=");
     let contents = fs::read_to_string(path).unwrap_or_default();
     let mut lines: Vec<String> = contents.lines().map(|line| line.to_string()).collect();
     let mut found = false;
 
     for line in &mut lines {
-        if line.trim_start().starts_with("OPENAI_API_KEY=") {
-            *line = format!("OPENAI_API_KEY={key}");
+        if line.trim_start().starts_with(&prefix) {
+            *line = format!("{prefix}{key}");
             found = true;
             break;
         }
     }
 
     if !found {
-        lines.push(format!("OPENAI_API_KEY={key}"));
+        lines.push(format!("{prefix}{key}"));
     }
 
     let mut output = lines.join("\n");
@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::flags::{parse_assignment, FlagValue};
+use crate::items::Item;
+
+/// A model-driven state update that may contradict something the player set by hand.
+#[derive(Clone)]
+pub(crate) enum Change {
+    AddItem(Item),
+    RemoveItem(String),
+    SetLocation(String),
+    SetFlag(String),
+    ClearFlag(String),
+}
+
+impl Change {
+    /// The slash command making this change, put in the input by "edit" to adjust.
+    pub(crate) fn command(&self) -> String {
+        match self {
+            Self::AddItem(item) => format!("/add item {}", item.name),
+            Self::RemoveItem(name) => format!("/remove item {name}"),
+            Self::SetLocation(place) => format!("/set location {place}"),
+            Self::SetFlag(flag) => format!("/flag {flag}"),
+            Self::ClearFlag(name) => format!("/unflag {name}"),
+        }
+    }
+}
+
+/// A change held back for the player to settle.
+pub(crate) struct Conflict {
+    pub(crate) change: Change,
+    /// What the player set, e.g. `You added rope.`
+    pub(crate) mine: String,
+    /// What the narrator wants instead.
+    pub(crate) theirs: String,
+}
+
+/// State the player set with slash commands, held until a conflict over it is settled.
+#[derive(Default)]
+pub(crate) struct Claims {
+    /// Items added with `/add item`, by lowercase name.
+    kept_items: BTreeSet<String>,
+    /// Items removed with `/remove item`.
+    dropped_items: BTreeSet<String>,
+    /// `/set location`, with the turn it was set on; it only holds for the next reply.
+    location: Option<(String, u32)>,
+    /// Values set with `/flag`, or `None` for flags cleared with `/unflag`.
+    flags: BTreeMap<String, Option<FlagValue>>,
+}
+
+impl Claims {
+    pub(crate) fn keep_item(&mut self, name: &str) {
+        let name = name.trim().to_lowercase();
+        self.dropped_items.remove(&name);
+        self.kept_items.insert(name);
+    }
+
+    pub(crate) fn drop_item(&mut self, name: &str) {
+        let name = name.trim().to_lowercase();
+        self.kept_items.remove(&name);
+        self.dropped_items.insert(name);
+    }
+
+    pub(crate) fn set_location(&mut self, place: &str, turn: u32) {
+        self.location = Some((place.trim().to_string(), turn));
+    }
+
+    /// Lets the location go once a reply to a later action has been applied.
+    pub(crate) fn expire_location(&mut self, turn: u32) {
+        if self.location.as_ref().is_some_and(|(_, set_on)| turn > *set_on) {
+            self.location = None;
+        }
+    }
+
+    pub(crate) fn set_flag(&mut self, flag: &str) {
+        if let Some((name, value)) = parse_assignment(flag) {
+            self.flags.insert(name, Some(value));
+        }
+    }
+
+    pub(crate) fn clear_flag(&mut self, name: &str) {
+        let name = name.split_once('=').map_or(name, |(name, _)| name);
+        self.flags.insert(name.trim().to_string(), None);
+    }
+
+    /// The conflict `change` raises, or `None` when nothing the player set stands in its way.
+    pub(crate) fn dispute(&self, change: &Change) -> Option<Conflict> {
+        let (mine, theirs) = match change {
+            Change::RemoveItem(name) if self.kept_items.contains(&name.trim().to_lowercase()) => {
+                (format!("You added {name}."), format!("The narrator removed {name}."))
+            }
+            Change::AddItem(item) if self.dropped_items.contains(&item.name.to_lowercase()) => (
+                format!("You removed {}.", item.name),
+                format!("The narrator gave you {}.", item.label()),
+            ),
+            Change::SetLocation(place) => match &self.location {
+                Some((mine, _)) if !mine.eq_ignore_ascii_case(place.trim()) => {
+                    (format!("You are at {mine}."), format!("The narrator moved you to {place}."))
+                }
+                _ => return None,
+            },
+            Change::SetFlag(flag) => {
+                let (name, value) = parse_assignment(flag)?;
+                match self.flags.get(&name) {
+                    Some(Some(mine)) if *mine != value => (
+                        format!("You set {name}={mine}."),
+                        format!("The narrator set {name}={value}."),
+                    ),
+                    Some(None) => (
+                        format!("You cleared {name}."),
+                        format!("The narrator set {name}={value}."),
+                    ),
+                    _ => return None,
+                }
+            }
+            Change::ClearFlag(name) => match self.flags.get(name.trim()) {
+                Some(Some(mine)) => (
+                    format!("You set {}={mine}.", name.trim()),
+                    format!("The narrator cleared {}.", name.trim()),
+                ),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(Conflict {
+            change: change.clone(),
+            mine,
+            theirs,
+        })
+    }
+
+    /// Forgets the claim `change` contradicted, once the player accepted it.
+    pub(crate) fn release(&mut self, change: &Change) {
+        match change {
+            Change::AddItem(item) => {
+                self.dropped_items.remove(&item.name.to_lowercase());
+            }
+            Change::RemoveItem(name) => {
+                self.kept_items.remove(&name.trim().to_lowercase());
+            }
+            Change::SetLocation(_) => self.location = None,
+            Change::SetFlag(flag) => {
+                if let Some((name, _)) = parse_assignment(flag) {
+                    self.flags.remove(&name);
+                }
+            }
+            Change::ClearFlag(name) => {
+                self.flags.remove(name.trim());
+            }
+        }
+    }
+}
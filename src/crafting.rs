@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+pub(crate) const CRAFTING_PROMPT: &str = r#"You resolve crafting in a text adventure.
+The player combines two inventory items. Decide whether they plausibly make something in this setting.
+- "success": false if the items cannot reasonably be combined.
+- "result": a short item name for what was made; empty when unsuccessful.
+- "description": one sentence describing the attempt and its outcome.
+Reply with JSON only."#;
+
+/// A fixed combination from the config's `recipes` table, checked before asking the model.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Recipe {
+    pub(crate) ingredients: [String; 2],
+    pub(crate) result: String,
+}
+
+impl Recipe {
+    /// Whether `a` and `b` are this recipe's ingredients, in either order.
+    pub(crate) fn matches(&self, a: &str, b: &str) -> bool {
+        let [first, second] = &self.ingredients;
+        (first.eq_ignore_ascii_case(a) && second.eq_ignore_ascii_case(b))
+            || (first.eq_ignore_ascii_case(b) && second.eq_ignore_ascii_case(a))
+    }
+}
+
+/// The crafting model's verdict on combining two items.
+#[derive(Clone, Deserialize)]
+pub(crate) struct Crafted {
+    pub(crate) success: bool,
+    #[serde(default)]
+    pub(crate) result: String,
+    #[serde(default)]
+    pub(crate) description: String,
+}
+
+impl Crafted {
+    pub(crate) fn parse_lenient(text: &str) -> Option<Self> {
+        let start = text.find('{')?;
+        let end = text.rfind('}')?;
+        serde_json::from_str(text.get(start..=end)?).ok()
+    }
+}
+
+/// `text.format` value requesting a strict `crafted` schema.
+pub(crate) fn crafted_format() -> Value {
+    json!({
+        "type": "json_schema",
+        "name": "crafted",
+        "strict": true,
+        "schema": {
+            "type": "object",
+            "additionalProperties": false,
+            "required": ["success", "result", "description"],
+            "properties": {
+                "success": { "type": "boolean" },
+                "result": { "type": "string" },
+                "description": { "type": "string" }
+            }
+        }
+    })
+}
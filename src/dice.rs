@@ -0,0 +1,117 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+
+const MAX_DICE: u32 = 100;
+const MAX_SIDES: u32 = 1000;
+const MAX_MODIFIER: i32 = 1000;
+
+/// A parsed dice expression such as `2d6+3`, `d20` or `4d8-1`.
+#[derive(Clone, Copy)]
+pub(crate) struct DiceExpr {
+    pub(crate) count: u32,
+    pub(crate) sides: u32,
+    pub(crate) modifier: i32,
+}
+
+pub(crate) struct RollResult {
+    pub(crate) expr: DiceExpr,
+    pub(crate) rolls: Vec<u32>,
+    pub(crate) total: i32,
+}
+
+impl DiceExpr {
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let text: String = text.split_whitespace().collect::<String>().to_lowercase();
+        let (count, rest) = text.split_once('d')?;
+        let count = if count.is_empty() { 1 } else { count.parse().ok()? };
+        let (sides, modifier) = match rest.find(['+', '-']) {
+            Some(pos) => (&rest[..pos], rest[pos..].parse::<i32>().ok()?),
+            None => (rest, 0),
+        };
+        let sides: u32 = sides.parse().ok()?;
+        if !(1..=MAX_DICE).contains(&count)
+            || !(2..=MAX_SIDES).contains(&sides)
+            || !(-MAX_MODIFIER..=MAX_MODIFIER).contains(&modifier)
+        {
+            return None;
+        }
+        Some(Self {
+            count,
+            sides,
+            modifier,
+        })
+    }
+
+    pub(crate) fn roll(self, rng: &mut StdRng) -> RollResult {
+        let rolls: Vec<u32> = (0..self.count)
+            .map(|_| rng.random_range(1..=self.sides))
+            .collect();
+        let total = rolls.iter().sum::<u32>() as i32 + self.modifier;
+        RollResult {
+            expr: self,
+            rolls,
+            total,
+        }
+    }
+}
+
+impl std::fmt::Display for DiceExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+        match self.modifier {
+            0 => Ok(()),
+            m if m > 0 => write!(f, "+{m}"),
+            m => write!(f, "{m}"),
+        }
+    }
+}
+
+impl RollResult {
+    /// e.g. `2d6+3: [4, 2] +3 = 9`
+    pub(crate) fn describe(&self) -> String {
+        let rolls: Vec<String> = self.rolls.iter().map(u32::to_string).collect();
+        let modifier = match self.expr.modifier {
+            0 => String::new(),
+            m if m > 0 => format!(" +{m}"),
+            m => format!(" {m}"),
+        };
+        format!(
+            "{}: [{}]{modifier} = {}",
+            self.expr,
+            rolls.join(", "),
+            self.total
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiceExpr;
+
+    fn parse(text: &str) -> Option<(u32, u32, i32)> {
+        DiceExpr::parse(text).map(|expr| (expr.count, expr.sides, expr.modifier))
+    }
+
+    #[test]
+    fn parses_dice_expressions() {
+        assert_eq!(parse("d20"), Some((1, 20, 0)));
+        assert_eq!(parse("2d6+3"), Some((2, 6, 3)));
+        assert_eq!(parse("4d8-1"), Some((4, 8, -1)));
+        assert_eq!(parse(" 2D6 + 3 "), Some((2, 6, 3)));
+    }
+
+    #[test]
+    fn rejects_out_of_range_dice() {
+        assert_eq!(parse("0d6"), None);
+        assert_eq!(parse("d1"), None);
+        assert_eq!(parse("101d6"), None);
+        assert_eq!(parse("d1001"), None);
+    }
+
+    #[test]
+    fn rejects_modifiers_that_could_overflow() {
+        assert_eq!(parse("d6+2147483647"), None);
+        assert_eq!(parse("d6-1001"), None);
+        assert_eq!(parse("d6+1000"), Some((1, 6, 1000)));
+    }
+}
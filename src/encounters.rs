@@ -0,0 +1,64 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// One row of the encounter table; `weight` is relative to the other rows.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Encounter {
+    pub(crate) description: String,
+    #[serde(default = "default_weight")]
+    pub(crate) weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// Encounters rolled locally whenever the player travels.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct EncounterTable {
+    /// Percent chance that a journey triggers an encounter; 0 disables them.
+    pub(crate) chance: u32,
+    pub(crate) entries: Vec<Encounter>,
+}
+
+impl Default for EncounterTable {
+    fn default() -> Self {
+        let entries = [
+            "a wary traveler who asks for news from the road",
+            "tracks of something large crossing the path",
+            "a merchant's cart stuck with a broken wheel",
+            "a pair of bandits demanding a toll",
+            "a lost child looking for home",
+        ];
+        Self {
+            chance: 15,
+            entries: entries
+                .into_iter()
+                .map(|description| Encounter {
+                    description: description.to_string(),
+                    weight: 1,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl EncounterTable {
+    /// Rolls against `chance`, then picks a weighted entry.
+    pub(crate) fn roll(&self, rng: &mut StdRng) -> Option<&Encounter> {
+        let total: u32 = self.entries.iter().map(|entry| entry.weight).sum();
+        if total == 0 || rng.random_range(0..100) >= self.chance {
+            return None;
+        }
+        let mut pick = rng.random_range(0..total);
+        self.entries.iter().find(|entry| {
+            if pick < entry.weight {
+                return true;
+            }
+            pick -= entry.weight;
+            false
+        })
+    }
+}
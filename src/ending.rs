@@ -0,0 +1,60 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::app::{GameState, LogEntry};
+use crate::chapters::render_story;
+use crate::clock;
+use crate::config::DATA_DIR;
+use crate::error::Result;
+use crate::quests::QuestStatus;
+
+const STORIES_DIR: &str = "stories";
+
+/// Sent as a system note when the story ends, asking for the epilogue in place of a turn.
+pub(crate) const EPILOGUE_NOTE: &str = "The story has reached its end. Write a closing epilogue of 2-3 short paragraphs that resolves the main threads and says what became of the player character and the people they met. Do not ask what the player does next.";
+
+/// Lines for the final stats screen and the exported story.
+pub(crate) fn final_stats(state: &GameState) -> Vec<String> {
+    let completed = state
+        .quests
+        .iter()
+        .filter(|quest| quest.status == QuestStatus::Completed)
+        .count();
+    let items: u32 = state.inventory.iter().map(|item| item.qty).sum();
+    vec![
+        format!("Turns played: {}", state.turn),
+        format!("Ended on: {}", clock::describe(state.minutes)),
+        format!("Health: {}/{}", state.hp, state.max_hp),
+        format!("Gold: {}", state.gold),
+        format!("Quests completed: {completed}/{}", state.quests.len()),
+        format!("Places visited: {}", state.visited.len()),
+        format!("Items carried: {items}"),
+        format!("Chapters: {}", state.chapters.len() + 1),
+    ]
+}
+
+/// Writes the whole story and final stats to `.story-gen/stories/story-<unix time>.md`.
+pub(crate) fn export_story(state: &GameState, log: &[LogEntry]) -> Result<PathBuf> {
+    let dir = Path::new(DATA_DIR).join(STORIES_DIR);
+    fs::create_dir_all(&dir)?;
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!("story-{stamp}.md"));
+    let mut contents = format!("# {}\n\n", state.character.name);
+    for chapter in &state.chapters {
+        contents.push_str(&format!("## Chapter {}\n\n{}\n\n", chapter.number, chapter.summary));
+    }
+    if !state.chapters.is_empty() {
+        contents.push_str(&format!("## Chapter {}\n\n", state.chapter));
+    }
+    contents.push_str(&render_story(log));
+    contents.push_str("## Final stats\n\n");
+    for line in final_stats(state) {
+        contents.push_str(&format!("- {line}\n"));
+    }
+    fs::write(&path, contents)?;
+    Ok(path)
+}
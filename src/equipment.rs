@@ -0,0 +1,78 @@
+use serde::Serialize;
+
+use crate::items::Item;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Slot {
+    Weapon,
+    Armor,
+    Trinket,
+}
+
+impl Slot {
+    pub(crate) const ALL: [Slot; 3] = [Slot::Weapon, Slot::Armor, Slot::Trinket];
+
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|slot| slot.label().eq_ignore_ascii_case(value.trim()))
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Slot::Weapon => "weapon",
+            Slot::Armor => "armor",
+            Slot::Trinket => "trinket",
+        }
+    }
+
+    /// The slot an item's tags point to, if any.
+    pub(crate) fn for_item(item: &Item) -> Option<Self> {
+        item.tags.iter().find_map(|tag| Self::parse(tag))
+    }
+}
+
+/// What the player wields and wears, held apart from carried inventory.
+#[derive(Clone, Default, Serialize)]
+pub(crate) struct Equipment {
+    pub(crate) weapon: Option<Item>,
+    pub(crate) armor: Option<Item>,
+    pub(crate) trinket: Option<Item>,
+}
+
+impl Equipment {
+    pub(crate) fn slot(&self, slot: Slot) -> &Option<Item> {
+        match slot {
+            Slot::Weapon => &self.weapon,
+            Slot::Armor => &self.armor,
+            Slot::Trinket => &self.trinket,
+        }
+    }
+
+    pub(crate) fn slot_mut(&mut self, slot: Slot) -> &mut Option<Item> {
+        match slot {
+            Slot::Weapon => &mut self.weapon,
+            Slot::Armor => &mut self.armor,
+            Slot::Trinket => &mut self.trinket,
+        }
+    }
+
+    /// The slot holding an item called `name`.
+    pub(crate) fn find(&self, name: &str) -> Option<Slot> {
+        Slot::ALL
+            .into_iter()
+            .find(|slot| self.slot(*slot).as_ref().is_some_and(|item| item.is(name)))
+    }
+
+    /// `weapon: rusty sword, armor: none, trinket: none`.
+    pub(crate) fn summary(&self) -> String {
+        Slot::ALL
+            .into_iter()
+            .map(|slot| {
+                let item = self.slot(slot).as_ref().map_or("none", |item| item.name.as_str());
+                format!("{}: {item}", slot.label())
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
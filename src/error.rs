@@ -0,0 +1,163 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use reqwest::blocking::Response;
+use reqwest::StatusCode;
+
+pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
+
+pub(crate) enum Error {
+    /// The provider rejected the request with a non-success status.
+    Api {
+        provider: &'static str,
+        status: StatusCode,
+        message: String,
+    },
+    /// HTTP 429; `retry_after` comes from the `Retry-After` header when present.
+    RateLimited {
+        provider: &'static str,
+        retry_after: Option<Duration>,
+        message: String,
+    },
+    Timeout,
+    /// Connection-level failure (DNS, TLS, reset) before a status was received.
+    Network(String),
+    /// The response arrived but did not contain what we expected.
+    Parse(String),
+    /// A file we persisted earlier could not be read back.
+    SaveCorrupt { path: PathBuf, message: String },
+    Config(String),
+    Hook(String),
+    Io(io::Error),
+    Terminal(io::Error),
+}
+
+impl Error {
+    pub(crate) fn from_response(provider: &'static str, response: Response) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let text = response.text().unwrap_or_default();
+        let message = extract_api_error_message(&text).unwrap_or(text);
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            Error::RateLimited {
+                provider,
+                retry_after,
+                message,
+            }
+        } else {
+            Error::Api {
+                provider,
+                status,
+                message,
+            }
+        }
+    }
+
+    /// Whether resending the same request might succeed.
+    pub(crate) fn is_retryable(&self) -> bool {
+        match self {
+            Error::Api { status, .. } => status.is_server_error(),
+            Error::RateLimited { .. } | Error::Timeout | Error::Network(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the session cannot continue after this error.
+    pub(crate) fn is_fatal(&self) -> bool {
+        matches!(self, Error::Terminal(_))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Api {
+                provider,
+                status,
+                message,
+            } => write!(f, "{provider} API error ({status}): {message}"),
+            Error::RateLimited {
+                provider,
+                retry_after,
+                message,
+            } => match retry_after {
+                Some(wait) => write!(
+                    f,
+                    "{provider} rate limit reached, retry in {}s: {message}",
+                    wait.as_secs()
+                ),
+                None => write!(f, "{provider} rate limit reached: {message}"),
+            },
+            Error::Timeout => write!(f, "Request timed out."),
+            Error::Network(message) => write!(f, "Network error: {message}"),
+            Error::Parse(message) => write!(f, "{message}"),
+            Error::SaveCorrupt { path, message } => {
+                write!(f, "{} is corrupt: {message}", path.display())
+            }
+            Error::Config(message) => write!(f, "Config error: {message}"),
+            Error::Hook(message) => write!(f, "{message}"),
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::Terminal(err) => write!(f, "Terminal error: {err}"),
+        }
+    }
+}
+
+// `main` returns `Result`, which prints errors with `Debug`; keep that readable.
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Error::Timeout
+        } else if err.is_decode() {
+            Error::Parse(format!("Could not decode response: {err}"))
+        } else {
+            let mut message = err.to_string();
+            let mut source = std::error::Error::source(&err);
+            while let Some(cause) = source {
+                message.push_str(&format!(": {cause}"));
+                source = cause.source();
+            }
+            Error::Network(message)
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Parse(err.to_string())
+    }
+}
+
+fn extract_api_error_message(body: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    let message = value
+        .get("error")?
+        .get("message")?
+        .as_str()?
+        .trim();
+    if message.is_empty() {
+        None
+    } else {
+        Some(message.to_string())
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+
+/// A story flag's value; bare flags like `met_king` are `true`.
+#[derive(Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub(crate) enum FlagValue {
+    Bool(bool),
+    Int(i64),
+    Text(String),
+}
+
+impl FlagValue {
+    pub(crate) fn parse(value: &str) -> Self {
+        let value = value.trim();
+        if let Ok(flag) = value.parse::<bool>() {
+            return Self::Bool(flag);
+        }
+        if let Ok(number) = value.parse::<i64>() {
+            return Self::Int(number);
+        }
+        Self::Text(value.to_string())
+    }
+}
+
+impl fmt::Display for FlagValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlagValue::Bool(flag) => write!(f, "{flag}"),
+            FlagValue::Int(number) => write!(f, "{number}"),
+            FlagValue::Text(text) => write!(f, "{text}"),
+        }
+    }
+}
+
+/// Splits `name=value` (or a bare `name`, meaning `true`) into a flag assignment.
+pub(crate) fn parse_assignment(text: &str) -> Option<(String, FlagValue)> {
+    let (name, value) = match text.split_once('=') {
+        Some((name, value)) => (name.trim(), FlagValue::parse(value)),
+        None => (text.trim(), FlagValue::Bool(true)),
+    };
+    (!name.is_empty()).then(|| (name.to_string(), value))
+}
+
+/// `met_king, reputation=5, mood=wary`: true flags by name, the rest as assignments.
+pub(crate) fn describe_flags(flags: &BTreeMap<String, FlagValue>) -> String {
+    if flags.is_empty() {
+        return "None".to_string();
+    }
+    flags
+        .iter()
+        .map(|(name, value)| match value {
+            FlagValue::Bool(true) => name.clone(),
+            _ => format!("{name}={value}"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::DATA_DIR;
+use crate::error::Result;
+
+const GALLERIES_DIR: &str = "galleries";
+
+/// Scene art as it was shown, kept for `/gallery`.
+pub(crate) struct GalleryScene {
+    /// The turn and place the art was asked for.
+    pub(crate) turn: u32,
+    pub(crate) location: String,
+    pub(crate) art: SceneArt,
+}
+
+pub(crate) enum SceneArt {
+    Text(String),
+    /// A generated picture's PNG, written out next to the Markdown on export.
+    Picture(Vec<u8>),
+}
+
+/// The `/gallery` overlay, showing one scene at a time.
+#[derive(Default)]
+pub(crate) struct GalleryView {
+    pub(crate) selected: usize,
+}
+
+/// Saves a `/scene image` picture as `.story-gen/galleries/turn-<n>-<unix time>.png`.
+pub(crate) fn save_illustration(turn: u32, png: &[u8]) -> Result<PathBuf> {
+    let dir = Path::new(DATA_DIR).join(GALLERIES_DIR);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("turn-{turn}-{}.png", unix_time()));
+    fs::write(&path, png)?;
+    Ok(path)
+}
+
+/// Writes every scene to `.story-gen/galleries/gallery-<unix time>.md`, with pictures
+/// saved beside it as `gallery-<unix time>-<n>.png`.
+pub(crate) fn export(title: &str, scenes: &[GalleryScene]) -> Result<PathBuf> {
+    let dir = Path::new(DATA_DIR).join(GALLERIES_DIR);
+    fs::create_dir_all(&dir)?;
+    let stem = format!("gallery-{}", unix_time());
+    let path = dir.join(format!("{stem}.md"));
+    let mut contents = format!("# {title}\n\n");
+    for (i, scene) in scenes.iter().enumerate() {
+        contents.push_str(&format!("## Turn {}: {}\n\n", scene.turn, scene.location));
+        match &scene.art {
+            SceneArt::Text(art) => contents.push_str(&format!("```text\n{art}\n```\n\n")),
+            SceneArt::Picture(png) => {
+                let file = format!("{stem}-{}.png", i + 1);
+                fs::write(dir.join(&file), png)?;
+                contents.push_str(&format!("![{}]({file})\n\n", scene.location));
+            }
+        }
+    }
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+pub(crate) fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
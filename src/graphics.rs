@@ -0,0 +1,280 @@
+use std::env;
+use std::io::{self, Cursor, Write};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crossterm::{cursor::MoveTo, queue, terminal};
+use ratatui::layout::Rect;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Which inline image protocol to draw scene art with, set as `graphics` in the config.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum GraphicsMode {
+    /// Pick from the terminal's environment, falling back to text art.
+    #[default]
+    Auto,
+    Kitty,
+    Sixel,
+    Off,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Protocol {
+    Kitty,
+    Sixel,
+}
+
+impl GraphicsMode {
+    /// The protocol to use, if any. Auto stays off inside tmux and screen, which
+    /// swallow graphics escapes unless passthrough is configured.
+    pub(crate) fn protocol(self) -> Option<Protocol> {
+        match self {
+            Self::Kitty => Some(Protocol::Kitty),
+            Self::Sixel => Some(Protocol::Sixel),
+            Self::Off => None,
+            Self::Auto => detect(),
+        }
+    }
+}
+
+fn detect() -> Option<Protocol> {
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    let term = var("TERM");
+    if env::var_os("TMUX").is_some() || term.starts_with("screen") || term.starts_with("tmux") {
+        return None;
+    }
+    let program = var("TERM_PROGRAM");
+    if env::var_os("KITTY_WINDOW_ID").is_some()
+        || term == "xterm-kitty"
+        || term == "xterm-ghostty"
+        || matches!(program.as_str(), "WezTerm" | "ghostty")
+    {
+        return Some(Protocol::Kitty);
+    }
+    if term.starts_with("foot") || term.starts_with("mlterm") || term.contains("sixel") {
+        return Some(Protocol::Sixel);
+    }
+    None
+}
+
+/// A generated scene picture: the PNG as received, plus its pixels for sixel.
+pub(crate) struct Image {
+    png: Vec<u8>,
+    width: u32,
+    height: u32,
+    /// 8-bit RGB, row by row.
+    rgb: Vec<u8>,
+}
+
+impl Image {
+    pub(crate) fn decode(png: Vec<u8>) -> Result<Self> {
+        let invalid = |err: png::DecodingError| Error::Parse(format!("Scene image: {err}"));
+        let mut decoder = png::Decoder::new(Cursor::new(png.as_slice()));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(invalid)?;
+        let size = reader
+            .output_buffer_size()
+            .ok_or_else(|| Error::Parse("Scene image is too large.".to_string()))?;
+        let mut pixels = vec![0; size];
+        let info = reader.next_frame(&mut pixels).map_err(invalid)?;
+        let channels = info.color_type.samples();
+        let rgb = pixels[..info.buffer_size()]
+            .chunks_exact(channels)
+            .flat_map(|pixel| match channels {
+                1 | 2 => [pixel[0]; 3],
+                _ => [pixel[0], pixel[1], pixel[2]],
+            })
+            .collect();
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            rgb,
+            png,
+        })
+    }
+
+    /// The PNG as received, for saving.
+    pub(crate) fn png(&self) -> &[u8] {
+        &self.png
+    }
+}
+
+/// Tracks what is drawn over the Scene pane so images are only sent when something changed.
+pub(crate) struct InlineImages {
+    protocol: Protocol,
+    /// The tab, pane and image shown now.
+    shown: Option<(usize, Rect, u64)>,
+}
+
+impl InlineImages {
+    pub(crate) fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            shown: None,
+        }
+    }
+
+    /// Shows `want` (its key and image) after a frame is drawn, replacing what was shown.
+    /// Returns true when the screen must be fully redrawn first to wipe an old sixel image,
+    /// in which case the new image goes up on the next call.
+    pub(crate) fn sync(
+        &mut self,
+        out: &mut impl Write,
+        want: Option<((usize, Rect, u64), &Image)>,
+    ) -> io::Result<bool> {
+        let key = want.as_ref().map(|(key, _)| *key);
+        if key == self.shown {
+            return Ok(false);
+        }
+        if self.shown.take().is_some() {
+            match self.protocol {
+                Protocol::Kitty => write!(out, "\x1b_Ga=d,d=A,q=2\x1b\\")?,
+                Protocol::Sixel => return Ok(true),
+            }
+        }
+        if let Some((key, image)) = want {
+            let (_, area, _) = key;
+            queue!(out, MoveTo(area.x, area.y))?;
+            match self.protocol {
+                Protocol::Kitty => write_kitty(out, image, area)?,
+                Protocol::Sixel => write_sixel(out, image, area)?,
+            }
+            self.shown = Some(key);
+        }
+        out.flush()?;
+        Ok(false)
+    }
+
+    /// Removes any image before the terminal is restored.
+    pub(crate) fn clear(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.shown.take().is_some() && self.protocol == Protocol::Kitty {
+            write!(out, "\x1b_Ga=d,d=A,q=2\x1b\\")?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Pixel size of one cell, guessed when the terminal does not report it.
+fn cell_pixels() -> (u32, u32) {
+    match terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            u32::from(size.width / size.columns).max(1),
+            u32::from(size.height / size.rows).max(1),
+        ),
+        _ => (10, 20),
+    }
+}
+
+/// The image's size in pixels once scaled to fit `area` with its aspect ratio kept.
+fn fit(image: &Image, area: Rect) -> (u32, u32) {
+    let (cell_width, cell_height) = cell_pixels();
+    let max_width = u32::from(area.width) * cell_width;
+    let max_height = u32::from(area.height) * cell_height;
+    let scale = f64::min(
+        f64::from(max_width) / f64::from(image.width.max(1)),
+        f64::from(max_height) / f64::from(image.height.max(1)),
+    );
+    let width = (f64::from(image.width) * scale) as u32;
+    let height = (f64::from(image.height) * scale) as u32;
+    (width.clamp(1, max_width.max(1)), height.clamp(1, max_height.max(1)))
+}
+
+/// Kitty takes the PNG as is and scales it into the given cells.
+fn write_kitty(out: &mut impl Write, image: &Image, area: Rect) -> io::Result<()> {
+    let (cell_width, cell_height) = cell_pixels();
+    let (width, height) = fit(image, area);
+    let columns = width.div_ceil(cell_width);
+    let rows = height.div_ceil(cell_height);
+    let data = STANDARD.encode(&image.png);
+    // Escapes are limited to 4096 bytes of payload; `m=1` marks more to come.
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        if i == 0 {
+            write!(out, "\x1b_Ga=T,f=100,q=2,C=1,c={columns},r={rows},m={more};")?;
+        } else {
+            write!(out, "\x1b_Gm={more};")?;
+        }
+        out.write_all(chunk)?;
+        write!(out, "\x1b\\")?;
+    }
+    Ok(())
+}
+
+/// Sixel needs the pixels themselves: scaled by nearest neighbour and mapped onto
+/// a 6x6x6 color cube.
+fn write_sixel(out: &mut impl Write, image: &Image, area: Rect) -> io::Result<()> {
+    let (width, height) = fit(image, area);
+    let level = |value: u8| (u32::from(value) * 5 + 127) / 255;
+    let indices: Vec<u8> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let source_x = (x * image.width / width).min(image.width - 1);
+            let source_y = (y * image.height / height).min(image.height - 1);
+            let offset = ((source_y * image.width + source_x) * 3) as usize;
+            let pixel = &image.rgb[offset..offset + 3];
+            (level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2])) as u8
+        })
+        .collect();
+
+    write!(out, "\x1bP0;1;0q\"1;1;{width};{height}")?;
+    for index in 0..216u32 {
+        let percent = |step: u32| step * 100 / 5;
+        write!(
+            out,
+            "#{index};2;{};{};{}",
+            percent(index / 36),
+            percent(index / 6 % 6),
+            percent(index % 6)
+        )?;
+    }
+    let (width, height) = (width as usize, height as usize);
+    for band in (0..height).step_by(6) {
+        let rows = band..(band + 6).min(height);
+        let mut used = [false; 216];
+        for y in rows.clone() {
+            for &color in &indices[y * width..(y + 1) * width] {
+                used[usize::from(color)] = true;
+            }
+        }
+        for color in (0..216).filter(|&color| used[color]) {
+            write!(out, "#{color}")?;
+            let mut run: Option<(u8, usize)> = None;
+            for x in 0..width {
+                let bits = rows
+                    .clone()
+                    .filter(|&y| usize::from(indices[y * width + x]) == color)
+                    .fold(0u8, |bits, y| bits | 1 << (y - band));
+                let ch = 63 + bits;
+                run = match run {
+                    Some((last, count)) if last == ch => Some((last, count + 1)),
+                    Some((last, count)) => {
+                        write_run(out, last, count)?;
+                        Some((ch, 1))
+                    }
+                    None => Some((ch, 1)),
+                };
+            }
+            if let Some((last, count)) = run {
+                write_run(out, last, count)?;
+            }
+            // `$` returns to the start of the band for the next color.
+            write!(out, "$")?;
+        }
+        write!(out, "-")?;
+    }
+    write!(out, "\x1b\\")
+}
+
+fn write_run(out: &mut impl Write, ch: u8, count: usize) -> io::Result<()> {
+    let ch = char::from(ch);
+    if count > 3 {
+        write!(out, "!{count}{ch}")
+    } else {
+        write!(out, "{}", ch.to_string().repeat(count))
+    }
+}
@@ -0,0 +1,10 @@
+/// FNV-1a of `text` trimmed and lowercased, which unlike std's hasher is fixed across
+/// Rust versions, so colors and sketches stay put between runs.
+pub(crate) fn stable_hash(text: &str) -> u64 {
+    text.trim()
+        .to_lowercase()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
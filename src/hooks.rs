@@ -0,0 +1,124 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use serde_json::{json, Value};
+
+use crate::app::GameState;
+use crate::config::{Notifications, TurnHook};
+use crate::error::{Error, Result};
+
+pub(crate) fn turn_payload(input: Option<&str>, reply: &str, state: &GameState) -> Value {
+    json!({
+        "turn": state.turn,
+        "input": input,
+        "reply": reply,
+        "state": state,
+    })
+}
+
+/// Rings the bell and, if enabled, raises a desktop notification for a finished turn.
+pub(crate) fn notify_turn(notifications: Notifications, out: &mut impl Write, title: &str) {
+    if notifications.bell {
+        let _ = out.write_all(b"\x07").and_then(|()| out.flush());
+    }
+    if !notifications.desktop {
+        return;
+    }
+    let body = "The narrator has replied.";
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {body:?} with title {:?}",
+            format!("story-gen: {title}")
+        ));
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.arg(format!("story-gen: {title}")).arg(body);
+        command
+    };
+    // Best effort: a missing notifier should not interrupt play. Waiting on a thread
+    // reaps the notifier once it exits.
+    thread::spawn(move || {
+        let _ = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    });
+}
+
+/// Runs every configured hook on its own thread; failures are reported on `errors`.
+pub(crate) fn run_turn_hooks(hooks: &[TurnHook], payload: &Value, errors: &Sender<String>) {
+    for hook in hooks {
+        let hook = hook.clone();
+        let body = payload.to_string();
+        let errors = errors.clone();
+        thread::spawn(move || {
+            if let Some(command) = hook.command.as_deref()
+                && let Err(err) = run_command(command, &body)
+            {
+                let _ = errors.send(format!("Hook `{command}` failed: {err}"));
+            }
+            if let Some(url) = hook.webhook.as_deref()
+                && let Err(err) = post_webhook(url, &body)
+            {
+                let _ = errors.send(format!("Webhook {url} failed: {err}"));
+            }
+        });
+    }
+}
+
+fn run_command(command: &str, body: &str) -> Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    // Hook output must never reach the alternate screen.
+    let mut child = shell
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(body.as_bytes());
+    }
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match stderr.lines().find(|line| !line.trim().is_empty()) {
+        Some(line) => Err(Error::Hook(format!(
+            "exited with {}: {}",
+            output.status,
+            line.trim()
+        ))),
+        None => Err(Error::Hook(format!("exited with {}", output.status))),
+    }
+}
+
+fn post_webhook(url: &str, body: &str) -> Result<()> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?;
+    let response = client
+        .post(url)
+        .header("content-type", "application/json")
+        .body(body.to_string())
+        .send()?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(Error::from_response("Webhook", response))
+    }
+}
@@ -0,0 +1,93 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::{DATA_DIR, ENV_FILE};
+use crate::error::Result;
+
+pub(crate) fn ensure_secret_hygiene() -> Result<()> {
+    let cwd = env::current_dir()?;
+    let Some(repo_root) = find_git_root(&cwd) else {
+        return Ok(());
+    };
+
+    let gitignore = cwd.join(".gitignore");
+    let entries = [ENV_FILE.to_string(), format!("{DATA_DIR}/")];
+    let added = append_missing_ignore_entries(&gitignore, &entries)?;
+    if !added.is_empty() {
+        println!(
+            "Added {} to {} to keep API keys and saves out of git.",
+            added.join(", "),
+            gitignore.display()
+        );
+    }
+
+    if !cwd.join(ENV_FILE).exists() {
+        return Ok(());
+    }
+    let env_path = cwd.join(ENV_FILE).to_string_lossy().into_owned();
+    if git_exit_code(&repo_root, &["ls-files", "--error-unmatch", "--", &env_path]) == Some(0) {
+        println!(
+            "Warning: {ENV_FILE} is tracked by git in {}. Rotate your API key and run `git rm --cached {ENV_FILE}`.",
+            repo_root.display()
+        );
+    } else if git_exit_code(&repo_root, &["check-ignore", "-q", "--", &env_path]) == Some(1) {
+        println!(
+            "Warning: {ENV_FILE} is inside the git repository at {} but is not ignored.",
+            repo_root.display()
+        );
+    }
+    Ok(())
+}
+
+fn find_git_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(|dir| dir.to_path_buf())
+}
+
+fn append_missing_ignore_entries(path: &Path, entries: &[String]) -> Result<Vec<String>> {
+    let contents = fs::read_to_string(path).unwrap_or_default();
+    let existing: Vec<&str> = contents
+        .lines()
+        .map(|line| line.trim().trim_start_matches('/'))
+        .collect();
+    let missing: Vec<String> = entries
+        .iter()
+        .filter(|entry| {
+            let bare = entry.trim_end_matches('/');
+            !existing
+                .iter()
+                .any(|line| *line == entry.as_str() || *line == bare)
+        })
+        .cloned()
+        .collect();
+    if missing.is_empty() {
+        return Ok(missing);
+    }
+
+    let mut output = contents;
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output.push_str("# story-gen secrets and local data\n");
+    for entry in &missing {
+        output.push_str(entry);
+        output.push('\n');
+    }
+    fs::write(path, output)?;
+    Ok(missing)
+}
+
+fn git_exit_code(repo_root: &Path, args: &[&str]) -> Option<i32> {
+    Command::new("git")
+        .arg("-C")
+        .arg(repo_root)
+        .args(args)
+        .output()
+        .ok()?
+        .status
+        .code()
+}
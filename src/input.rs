@@ -1,20 +1,240 @@
-use anyhow::Result;
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::path::Path;
 
-use crate::app::{App, LogKind};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+use crate::achievements::ACHIEVEMENTS;
+use crate::app::{
+    App, BranchPicker, Focus, Inspector, LogFilter, LogKind, Reader, ScenarioPicker,
+    SettingsMenu, SideTab, TabRequest,
+};
+use crate::config::{
+    Difficulty, ReasoningEffort, SceneStyle, SettingChange, Settings, Verbosity,
+};
+use crate::dice::DiceExpr;
+use crate::equipment::Slot;
+use crate::gallery::GalleryView;
+use crate::palette::{CommandPalette, COMMANDS};
+use crate::error::Result;
+use crate::quests::QuestStatus;
+use crate::scenarios::Scenario;
+use crate::tasks::TaskKind;
+use crate::theme;
+use crate::wiki::WikiView;
+
+/// Text that arrives in one piece: a bracketed paste, or a commit from an input method
+/// that the terminal delivers the same way. Goes to whichever editor is open.
+pub(crate) fn handle_paste(text: &str, app: &mut App) {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    if let Some(editor) = app.reply_editor.as_mut().or(app.prompt_editor.as_mut()) {
+        editor.push_str(&text);
+    } else if !app.has_overlay() {
+        app.input.insert_str(&text);
+    }
+}
 
 pub(crate) fn handle_key_event(key: KeyEvent, app: &mut App) -> Result<bool> {
+    // Terminals with key release reporting (and Windows) also send releases; acting on
+    // those too would type every character twice.
+    if key.kind == KeyEventKind::Release {
+        return Ok(false);
+    }
+    // Any key but quit skips the typewriter to the full reply.
+    if app.reveal.is_some() && !app.keys.quit.matches(key) {
+        app.reveal = None;
+        return Ok(false);
+    }
+    if app.confirm_quit {
+        return Ok(handle_confirm_quit_key(key, app));
+    }
+    if !app.conflicts.is_empty() {
+        if app.keys.quit.matches(key) {
+            return Ok(app.request_quit());
+        }
+        handle_conflict_key(key, app);
+        return Ok(false);
+    }
+    if app.keys.inspector.matches(key) {
+        app.inspector = match app.inspector {
+            Some(_) => None,
+            None => Some(Inspector::default()),
+        };
+        return Ok(false);
+    }
+    if app.inspector.is_some() {
+        handle_inspector_key(key, app);
+        return Ok(false);
+    }
+    if app.reader.is_some() {
+        handle_reader_key(key, app);
+        return Ok(false);
+    }
+    if app.reply_editor.is_some() {
+        handle_reply_editor_key(key, app);
+        return Ok(false);
+    }
+    if app.prompt_editor.is_some() {
+        handle_prompt_editor_key(key, app);
+        return Ok(false);
+    }
+    if app.show_stats {
+        if matches!(key.code, KeyCode::Esc | KeyCode::Enter) {
+            app.show_stats = false;
+        }
+        return Ok(false);
+    }
+    if app.scenario_picker.is_some() {
+        handle_scenario_picker_key(key, app);
+        return Ok(false);
+    }
+    if app.game_over {
+        return Ok(handle_game_over_key(key, app));
+    }
+    if app.story_over {
+        return Ok(handle_story_end_key(key, app));
+    }
+    if app.model_picker.is_some() {
+        handle_model_picker_key(key, app);
+        return Ok(false);
+    }
+    if app.settings_menu.is_some() {
+        handle_settings_menu_key(key, app);
+        return Ok(false);
+    }
+    if app.branch_picker.is_some() {
+        handle_branch_picker_key(key, app);
+        return Ok(false);
+    }
+    if app.wiki.is_some() {
+        handle_wiki_key(key, app);
+        return Ok(false);
+    }
+    if app.gallery.is_some() {
+        handle_gallery_key(key, app);
+        return Ok(false);
+    }
+    if app.palette.is_some() {
+        return handle_palette_key(key, app);
+    }
+
+    let keys = &app.keys;
+    if keys.quit.matches(key) {
+        return Ok(app.request_quit());
+    }
+    if keys.new_game.matches(key) {
+        app.scenario_picker = Some(ScenarioPicker { selected: 0 });
+        return Ok(false);
+    }
+    if keys.restore_input.matches(key) {
+        if let Some(last) = app.last_sent_input.clone() {
+            app.input.set(last);
+        }
+        return Ok(false);
+    }
+    if keys.palette.matches(key) {
+        app.palette = Some(CommandPalette::default());
+        return Ok(false);
+    }
+    if keys.line_input.matches(key) {
+        app.line_input_requested = true;
+        return Ok(false);
+    }
+    if keys.redraw_scene.matches(key) {
+        app.redraw_scene();
+        return Ok(false);
+    }
+    if keys.settings.matches(key) {
+        app.settings_requested = true;
+        return Ok(false);
+    }
+    if keys.filter.matches(key) {
+        app.cycle_log_filter();
+        return Ok(false);
+    }
+    if keys.new_tab.matches(key) {
+        app.tab_request = Some(TabRequest::New);
+        return Ok(false);
+    }
+    if keys.next_tab.matches(key) {
+        app.tab_request = Some(TabRequest::Next);
+        return Ok(false);
+    }
+    if keys.grow_scene.matches(key) {
+        app.resize_scene(5);
+        return Ok(false);
+    }
+    if keys.shrink_scene.matches(key) {
+        app.resize_scene(-5);
+        return Ok(false);
+    }
+    if keys.widen_side_panel.matches(key) {
+        app.resize_side_panel(2);
+        return Ok(false);
+    }
+    if keys.narrow_side_panel.matches(key) {
+        app.resize_side_panel(-2);
+        return Ok(false);
+    }
+    if keys.toggle_side_panel.matches(key) {
+        app.layout.side_panel = !app.layout.side_panel;
+        return Ok(false);
+    }
+    if keys.next_side_tab.matches(key) {
+        app.set_side_tab(app.side_tab.next());
+        return Ok(false);
+    }
+    if keys.cycle_focus.matches(key) {
+        app.cycle_focus();
+        return Ok(false);
+    }
+    // Keep a line of overlap so the reader does not lose their place.
+    let rows = match app.focus {
+        Focus::Scene => app.scene_rows,
+        Focus::Input | Focus::Story => app.story_rows,
+    };
+    let page = i32::from(rows.saturating_sub(1).max(1));
+    let scroll = [
+        (keys.scroll_up, -1),
+        (keys.scroll_down, 1),
+        (keys.page_up, -page),
+        (keys.page_down, page),
+        (keys.scroll_top, i32::MIN),
+        (keys.scroll_bottom, i32::MAX),
+    ]
+    .into_iter()
+    .find(|(chord, _)| chord.matches(key));
+    if let Some((_, delta)) = scroll {
+        app.scroll_focused(delta);
+        return Ok(false);
+    }
+    // Any other key goes to the Input pane; Esc only hands focus back.
+    if app.focus != Focus::Input {
+        app.focus = Focus::Input;
+        if key.code == KeyCode::Esc {
+            return Ok(false);
+        }
+    }
+
     if key.modifiers.contains(KeyModifiers::CONTROL) {
         match key.code {
-            KeyCode::Char('c') => return Ok(true),
-            KeyCode::Char('n') => {
-                app.reset();
+            KeyCode::Char('w') => {
+                app.input.delete_word();
                 return Ok(false);
             }
-            KeyCode::Char('r') => {
-                if let Some(last) = app.last_sent_input.clone() {
-                    app.input = last;
-                }
+            KeyCode::Char('u') => {
+                app.input.clear_line();
+                return Ok(false);
+            }
+            _ => {}
+        }
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        match key.code {
+            KeyCode::Char('b') => {
+                app.input.word_left();
+                return Ok(false);
+            }
+            KeyCode::Char('f') => {
+                app.input.word_right();
                 return Ok(false);
             }
             _ => {}
@@ -22,16 +242,46 @@ pub(crate) fn handle_key_event(key: KeyEvent, app: &mut App) -> Result<bool> {
     }
 
     match key.code {
+        KeyCode::F(n @ 1..=4) => {
+            let Some(action) = app.quick_actions.get(n as usize - 1).cloned() else {
+                app.push_toast(format!("No quick action is set for F{n}."));
+                return Ok(false);
+            };
+            if app.busy {
+                app.push_toast("Wait for the narrator before acting.");
+            } else {
+                submit_action(app, action);
+            }
+        }
         KeyCode::Char(ch) => {
-            app.input.push(ch);
+            app.input.insert(ch);
         }
         KeyCode::Backspace => {
-            app.input.pop();
+            app.input.backspace();
+        }
+        KeyCode::Delete => {
+            app.input.delete();
+        }
+        KeyCode::Left => {
+            app.input.left();
+        }
+        KeyCode::Right => {
+            app.input.right();
+        }
+        KeyCode::Home => {
+            app.input.line_start();
+        }
+        KeyCode::End => {
+            app.input.line_end();
+        }
+        KeyCode::Enter if key.modifiers.intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) => {
+            app.input.insert('\n');
         }
         KeyCode::Enter => {
-            let input = app.input.trim().to_string();
-            app.input.clear();
+            let input = app.input.take().trim().to_string();
             if input.is_empty() {
+                // A blank answer skips the current creation question.
+                app.answer_creation("");
                 return Ok(false);
             }
             if input.starts_with('/') {
@@ -40,45 +290,984 @@ pub(crate) fn handle_key_event(key: KeyEvent, app: &mut App) -> Result<bool> {
                 }
                 return Ok(false);
             }
-            app.push_user_log(&input);
-            app.push_user_message(&input);
-            app.last_sent_input = Some(input.clone());
-            app.pending_input = Some(input);
+            if app.creation.is_some() {
+                app.answer_creation(&input);
+                return Ok(false);
+            }
+            // A bare number picks that suggested action, so typing "2 goblins" is left alone.
+            let choice = input
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| app.choices.get(n.checked_sub(1)?).cloned());
+            submit_action(app, choice.unwrap_or(input));
+        }
+        KeyCode::Esc => {
+            app.skip_creation();
+        }
+        _ => {}
+    }
+
+    Ok(false)
+}
+
+/// Sends a player action to the narrator, running the local checks that precede a turn.
+fn submit_action(app: &mut App, input: String) {
+    if app.chapter_requested || app.tasks.is_running(TaskKind::Chapter) {
+        app.input.set(input);
+        app.push_toast("The chapter is closing; try again in a moment.");
+        return;
+    }
+    app.creation = None;
+    app.choices.clear();
+    app.claims.expire_location(app.state.turn);
+    app.save_checkpoint();
+    app.retry_point = None;
+    app.push_user_log(&input);
+    app.push_user_message(&input);
+    app.resolve_skill_check(&input);
+    app.validate_travel(&input);
+    app.roll_encounter(&input);
+    app.last_sent_input = Some(input.clone());
+    app.pending_input = Some(input);
+}
+
+fn handle_model_picker_key(key: KeyEvent, app: &mut App) {
+    let Some(picker) = app.model_picker.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => {
+            app.model_picker = None;
         }
         KeyCode::Up => {
-            app.scroll = app.scroll.saturating_sub(1);
+            picker.selected = picker.selected.saturating_sub(1);
         }
-        KeyCode::Down => {
-            app.scroll = app.scroll.saturating_add(1);
+        KeyCode::Down if picker.selected + 1 < picker.models.len() => {
+            picker.selected += 1;
+        }
+        KeyCode::Enter => {
+            let model = picker.models.get(picker.selected).cloned();
+            app.model_picker = None;
+            if let Some(model) = model {
+                if app.busy {
+                    app.push_log(
+                        LogKind::System,
+                        "Wait for the current turn before switching models.",
+                    );
+                } else {
+                    app.pending_settings.push(SettingChange::Model(model));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn handle_settings_menu_key(key: KeyEvent, app: &mut App) {
+    let Some(menu) = app.settings_menu.as_mut() else {
+        return;
+    };
+    let editing_model = menu.selected == 0;
+    match key.code {
+        KeyCode::Esc => {
+            app.settings_menu = None;
+        }
+        KeyCode::Up => {
+            menu.selected = menu.selected.saturating_sub(1);
+        }
+        KeyCode::Down if menu.selected + 1 < SettingsMenu::ROWS.len() => {
+            menu.selected += 1;
+        }
+        KeyCode::Left => menu.adjust(false),
+        KeyCode::Right | KeyCode::Char(' ') if !editing_model => menu.adjust(true),
+        KeyCode::Backspace if editing_model => {
+            menu.draft.model.pop();
+        }
+        KeyCode::Char(ch) if editing_model => {
+            menu.draft.model.push(ch);
+        }
+        KeyCode::Enter => {
+            if app.busy && menu.model_changed() {
+                app.push_toast("Wait for the current turn before switching models.");
+                return;
+            }
+            let changes = menu.changes();
+            app.settings_menu = None;
+            app.pending_settings.extend(changes);
+        }
+        _ => {}
+    }
+}
+
+fn handle_gallery_key(key: KeyEvent, app: &mut App) {
+    let Some(gallery) = app.gallery.as_mut() else {
+        return;
+    };
+    let last = app.scenes.len().saturating_sub(1);
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.gallery = None,
+        KeyCode::Left | KeyCode::Up | KeyCode::Char('h') | KeyCode::Char('k') => {
+            gallery.selected = gallery.selected.saturating_sub(1);
+        }
+        KeyCode::Right | KeyCode::Down | KeyCode::Char('l') | KeyCode::Char('j') => {
+            gallery.selected = (gallery.selected + 1).min(last);
+        }
+        KeyCode::Home => gallery.selected = 0,
+        KeyCode::End => gallery.selected = last,
+        KeyCode::Char('e') => app.export_gallery(),
+        _ => {}
+    }
+}
+
+fn handle_wiki_key(key: KeyEvent, app: &mut App) {
+    let Some(wiki) = app.wiki.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => {
+            app.wiki = None;
+        }
+        KeyCode::Up => {
+            wiki.selected = wiki.selected.saturating_sub(1);
+        }
+        KeyCode::Down if wiki.selected + 1 < wiki.filtered().len() => {
+            wiki.selected += 1;
+        }
+        KeyCode::Enter => {
+            wiki.follow_link();
+        }
+        KeyCode::Backspace => {
+            wiki.query.pop();
+            wiki.selected = 0;
+        }
+        KeyCode::Char(ch) => {
+            wiki.query.push(ch);
+            wiki.selected = 0;
         }
         _ => {}
     }
+}
 
+/// Runs or fills in the chosen command; returns `true` when it was `/quit`.
+fn handle_palette_key(key: KeyEvent, app: &mut App) -> Result<bool> {
+    let Some(palette) = app.palette.as_mut() else {
+        return Ok(false);
+    };
+    match key.code {
+        KeyCode::Esc => {
+            app.palette = None;
+        }
+        KeyCode::Up => {
+            palette.selected = palette.selected.saturating_sub(1);
+        }
+        KeyCode::Down if palette.selected + 1 < palette.filtered().len() => {
+            palette.selected += 1;
+        }
+        KeyCode::Enter => {
+            let command = palette.selected_command();
+            app.palette = None;
+            match command {
+                Some(command) if command.is_complete() => {
+                    return handle_command(command.usage, app);
+                }
+                Some(command) => app.input.set(command.stem().to_string()),
+                None => {}
+            }
+        }
+        KeyCode::Backspace => {
+            palette.query.pop();
+            palette.selected = 0;
+        }
+        KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+            palette.query.push(ch);
+            palette.selected = 0;
+        }
+        _ => {}
+    }
     Ok(false)
 }
 
+/// Returns `true` when the player chose to quit.
+fn handle_game_over_key(key: KeyEvent, app: &mut App) -> bool {
+    if app.keys.quit.matches(key) {
+        return true;
+    }
+    match key.code {
+        KeyCode::Char('q') => true,
+        KeyCode::Char('r') => {
+            app.restore_checkpoint();
+            false
+        }
+        KeyCode::Char('n') => {
+            app.scenario_picker = Some(ScenarioPicker { selected: 0 });
+            false
+        }
+        _ => false,
+    }
+}
+
+fn handle_branch_picker_key(key: KeyEvent, app: &mut App) {
+    let Some(picker) = app.branch_picker.as_mut() else {
+        return;
+    };
+    // Row 0 is the active branch; the rest follow `app.branches`.
+    match key.code {
+        KeyCode::Esc => {
+            app.branch_picker = None;
+        }
+        KeyCode::Up => {
+            picker.selected = picker.selected.saturating_sub(1);
+        }
+        KeyCode::Down if picker.selected < app.branches.len() => {
+            picker.selected += 1;
+        }
+        KeyCode::Enter => {
+            let selected = picker.selected;
+            app.branch_picker = None;
+            if let Some(branch) = selected.checked_sub(1).and_then(|i| app.branches.get(i)) {
+                let name = branch.name.clone();
+                app.switch_branch(&name);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn handle_scenario_picker_key(key: KeyEvent, app: &mut App) {
+    let Some(picker) = app.scenario_picker.as_mut() else {
+        return;
+    };
+    // The row after the scenarios is "Custom".
+    match key.code {
+        KeyCode::Esc => {
+            app.scenario_picker = None;
+        }
+        KeyCode::Up => {
+            picker.selected = picker.selected.saturating_sub(1);
+        }
+        KeyCode::Down if picker.selected < app.scenarios.len() => {
+            picker.selected += 1;
+        }
+        KeyCode::Enter => {
+            let scenario = app.scenarios.get(picker.selected).cloned();
+            app.scenario_picker = None;
+            app.start_scenario(scenario);
+        }
+        _ => {}
+    }
+}
+
+fn handle_reply_editor_key(key: KeyEvent, app: &mut App) {
+    let Some(text) = app.reply_editor.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => {
+            app.reply_editor = None;
+        }
+        KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            if let Some(text) = app.reply_editor.take() {
+                app.apply_reply_edit(&text);
+            }
+        }
+        KeyCode::Enter => text.push('\n'),
+        KeyCode::Backspace => {
+            text.pop();
+        }
+        KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => text.push(ch),
+        _ => {}
+    }
+}
+
+fn handle_prompt_editor_key(key: KeyEvent, app: &mut App) {
+    let Some(text) = app.prompt_editor.as_mut() else {
+        return;
+    };
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Esc => {
+            app.prompt_editor = None;
+        }
+        KeyCode::Char('s') if ctrl => {
+            if let Some(text) = app.prompt_editor.take() {
+                app.apply_prompt_edit(&text);
+            }
+        }
+        KeyCode::Char('d') if ctrl => {
+            *text = app.default_prompt.clone();
+        }
+        KeyCode::Enter => text.push('\n'),
+        KeyCode::Backspace => {
+            text.pop();
+        }
+        KeyCode::Char(ch) if !ctrl => text.push(ch),
+        _ => {}
+    }
+}
+
+/// Pressing quit again leaves without saving, as does Q; S exports first.
+fn handle_confirm_quit_key(key: KeyEvent, app: &mut App) -> bool {
+    if app.keys.quit.matches(key) {
+        return true;
+    }
+    match key.code {
+        KeyCode::Char('s') | KeyCode::Enter => {
+            // Stay if the export failed, so the story is not lost.
+            let exported = app.export_story();
+            app.confirm_quit = !exported;
+            exported
+        }
+        KeyCode::Char('q') => true,
+        KeyCode::Esc | KeyCode::Char('n') => {
+            app.confirm_quit = false;
+            false
+        }
+        _ => false,
+    }
+}
+
+fn handle_conflict_key(key: KeyEvent, app: &mut App) {
+    match key.code {
+        KeyCode::Char('a') => app.settle_conflict(Some(true)),
+        KeyCode::Char('k') | KeyCode::Esc => app.settle_conflict(Some(false)),
+        KeyCode::Char('e') => app.settle_conflict(None),
+        _ => {}
+    }
+}
+
+fn handle_story_end_key(key: KeyEvent, app: &mut App) -> bool {
+    if app.keys.quit.matches(key) {
+        return true;
+    }
+    match key.code {
+        KeyCode::Char('q') => true,
+        KeyCode::Char('e') => {
+            app.export_story();
+            false
+        }
+        KeyCode::Char('n') => {
+            app.scenario_picker = Some(ScenarioPicker { selected: 0 });
+            false
+        }
+        KeyCode::Esc => {
+            app.story_over = false;
+            false
+        }
+        _ => false,
+    }
+}
+
+fn handle_inspector_key(key: KeyEvent, app: &mut App) {
+    let Some(inspector) = app.inspector.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => {
+            app.inspector = None;
+        }
+        KeyCode::Up => {
+            inspector.scroll = inspector.scroll.saturating_sub(1);
+        }
+        KeyCode::Down => {
+            inspector.scroll = inspector.scroll.saturating_add(1);
+        }
+        KeyCode::PageUp => {
+            inspector.scroll = inspector.scroll.saturating_sub(10);
+        }
+        KeyCode::PageDown => {
+            inspector.scroll = inspector.scroll.saturating_add(10);
+        }
+        KeyCode::Home => {
+            inspector.scroll = 0;
+        }
+        _ => {}
+    }
+}
+
+fn handle_reader_key(key: KeyEvent, app: &mut App) {
+    let Some(reader) = app.reader.as_mut() else {
+        return;
+    };
+    let page = reader.rows.saturating_sub(1).max(1);
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.reader = None;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            reader.scroll = reader.scroll.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            reader.scroll = reader.scroll.saturating_add(1);
+        }
+        KeyCode::PageUp | KeyCode::Char('b') => {
+            reader.scroll = reader.scroll.saturating_sub(page);
+        }
+        KeyCode::PageDown | KeyCode::Char(' ') => {
+            reader.scroll = reader.scroll.saturating_add(page);
+        }
+        KeyCode::Home | KeyCode::Char('g') => {
+            reader.scroll = 0;
+        }
+        KeyCode::End | KeyCode::Char('G') => {
+            reader.scroll = u16::MAX;
+        }
+        _ => {}
+    }
+}
+
 fn handle_command(input: &str, app: &mut App) -> Result<bool> {
     if !input.starts_with('/') {
         return Ok(false);
     }
 
     match input {
-        "/quit" | "/exit" => return Ok(true),
+        "/quit" | "/exit" => return Ok(app.request_quit()),
         "/new" => {
-            app.reset();
+            app.scenario_picker = Some(ScenarioPicker { selected: 0 });
+        }
+        "/keys" => {
+            let lines: Vec<String> = app
+                .keys
+                .describe()
+                .iter()
+                .map(|(name, chord, help)| format!("{chord:<8} {help} ({name})"))
+                .collect();
+            app.push_log(
+                LogKind::System,
+                format!(
+                    "Key bindings (change them under \"keys\" in {}):\n{}",
+                    Settings::path().display(),
+                    lines.join("\n")
+                ),
+            );
+        }
+        "/achievements" => {
+            let lines: Vec<String> = ACHIEVEMENTS
+                .iter()
+                .map(|achievement| {
+                    let mark = if app.achievements.unlocked.contains(achievement.id) {
+                        "x"
+                    } else {
+                        " "
+                    };
+                    format!("[{mark}] {}: {}", achievement.title, achievement.description)
+                })
+                .collect();
+            app.push_log(LogKind::System, format!("Achievements:\n{}", lines.join("\n")));
+        }
+        _ if input.starts_with("/fork ") => {
+            app.fork(input.trim_start_matches("/fork "));
+        }
+        "/branches" => {
+            app.branch_picker = Some(BranchPicker { selected: 0 });
+        }
+        _ if input.starts_with("/switch ") => {
+            app.switch_branch(input.trim_start_matches("/switch "));
+        }
+        _ if input.starts_with("/scenario load ") => {
+            let path = input.trim_start_matches("/scenario load ").trim();
+            if path.is_empty() {
+                app.push_log(LogKind::System, "Usage: /scenario load <path>");
+            } else {
+                match Scenario::load(Path::new(path)) {
+                    Ok(scenario) => {
+                        app.add_scenario(scenario.clone());
+                        app.start_scenario(Some(scenario));
+                    }
+                    Err(err) => app.push_log(LogKind::Error, err.to_string()),
+                }
+            }
+        }
+        "/prompt" => {
+            app.open_prompt_editor();
+        }
+        "/edit" => {
+            app.open_reply_editor();
+        }
+        "/retry" => {
+            app.retry_last();
+        }
+        "/end" => {
+            if app.busy || app.epilogue_pending {
+                app.push_log(LogKind::System, "Wait for the narrator before ending the story.");
+            } else if app.history.is_empty() {
+                app.push_log(LogKind::System, "The story has not started yet.");
+            } else {
+                app.begin_epilogue();
+            }
+        }
+        "/read" => {
+            app.reader = Some(Reader::default());
+        }
+        "/export" => {
+            app.export_story();
+        }
+        "/chapter" => {
+            let number = app.state.chapter;
+            let finished = app.state.chapters.len();
+            app.push_log(
+                LogKind::System,
+                format!("Chapter {number} ({finished} finished). /chapter end closes it."),
+            );
+        }
+        "/chapter end" => {
+            if app.busy {
+                app.push_log(LogKind::System, "Wait for the narrator before ending the chapter.");
+            } else if app.chapter_requested || app.tasks.is_running(TaskKind::Chapter) {
+                app.push_log(LogKind::System, "The chapter is already being summarized.");
+            } else if app.history.is_empty() {
+                app.push_log(LogKind::System, "Nothing has happened in this chapter yet.");
+            } else {
+                app.chapter_requested = true;
+                app.push_log(LogKind::System, "Summarizing the chapter...");
+            }
         }
         "/help" => {
+            let usages: Vec<&str> = COMMANDS.iter().map(|command| command.usage).collect();
+            app.push_log(
+                LogKind::System,
+                format!(
+                    "Commands: {}. {} searches them. F1-F4 send the quick actions from config.json. Left/Right, Home/End, Alt+B/F, Ctrl+W and Ctrl+U edit the input. {} shows the raw JSON of the last turn.",
+                    usages.join(", "),
+                    app.keys.palette,
+                    app.keys.inspector
+                ),
+            );
+        }
+        "/stats" => {
+            app.show_stats = true;
+        }
+        "/model" => {
+            let capabilities = app.capabilities.summary();
             app.push_log(
                 LogKind::System,
-                "Commands: /new, /quit, /set location <name>, /add item <name>, /remove item <name>, /flag <name>, /unflag <name>.",
+                format!("Model: {} ({capabilities})", app.model),
             );
         }
+        "/quest" | "/quest list" => {
+            if app.state.quests.is_empty() {
+                app.push_log(LogKind::System, "No quests yet.");
+            } else {
+                let lines: Vec<String> = app
+                    .state
+                    .quests
+                    .iter()
+                    .enumerate()
+                    .map(|(i, quest)| {
+                        let mark = match quest.status {
+                            QuestStatus::Active => " ",
+                            QuestStatus::Completed => "x",
+                        };
+                        format!("{}. [{mark}] {}: {}", i + 1, quest.title, quest.objective)
+                    })
+                    .collect();
+                app.push_log(LogKind::System, format!("Quests:\n{}", lines.join("\n")));
+            }
+        }
+        "/lore" | "/lore list" => {
+            if app.state.lore.is_empty() {
+                app.push_log(LogKind::System, "The lorebook is empty. Use /lore add <fact>.");
+            } else {
+                let lines: Vec<String> = app
+                    .state
+                    .lore
+                    .iter()
+                    .enumerate()
+                    .map(|(i, fact)| format!("{}. {fact}", i + 1))
+                    .collect();
+                app.push_log(LogKind::System, format!("Lore:\n{}", lines.join("\n")));
+            }
+        }
+        _ if input.starts_with("/lore add ") => {
+            let fact = input.trim_start_matches("/lore add ").trim();
+            if fact.is_empty() {
+                app.push_log(LogKind::System, "Usage: /lore add <fact>");
+            } else {
+                app.state.lore.push(fact.to_string());
+                app.push_log(LogKind::System, format!("Lore pinned: {fact}"));
+            }
+        }
+        _ if input.starts_with("/lore remove ") => {
+            let index = input.trim_start_matches("/lore remove ").trim().parse::<usize>();
+            match index {
+                Ok(n) if (1..=app.state.lore.len()).contains(&n) => {
+                    let fact = app.state.lore.remove(n - 1);
+                    app.push_log(LogKind::System, format!("Lore removed: {fact}"));
+                }
+                _ => app.push_log(LogKind::System, "Usage: /lore remove <#> (see /lore list)"),
+            }
+        }
+        _ if input.starts_with("/quest add ") => {
+            let args = input.trim_start_matches("/quest add ").trim();
+            let (title, objective) = args.split_once(':').unwrap_or((args, ""));
+            if app.state.add_quest(title, objective) {
+                app.push_log(LogKind::System, format!("Quest added: {}", title.trim()));
+            } else {
+                app.push_log(LogKind::System, "Usage: /quest add <title>: <objective>");
+            }
+        }
+        _ if input.starts_with("/quest complete ") => {
+            let key = input.trim_start_matches("/quest complete ");
+            match app.state.complete_quest(key) {
+                Some(title) => app.push_log(LogKind::System, format!("Quest completed: {title}")),
+                None => app.push_log(LogKind::System, format!("No open quest: {}", key.trim())),
+            }
+        }
+        "/shop" => {
+            if app.state.offers.is_empty() {
+                app.push_log(LogKind::System, "Nothing is on offer right now.");
+            } else {
+                let list: Vec<String> = app
+                    .state
+                    .offers
+                    .iter()
+                    .map(|offer| format!("{} - {}g", offer.item, offer.price))
+                    .collect();
+                let gold = app.state.gold;
+                app.push_log(
+                    LogKind::System,
+                    format!("For sale (you have {gold}g):\n{}", list.join("\n")),
+                );
+            }
+        }
+        _ if input.starts_with("/buy ") => {
+            let item = input.trim_start_matches("/buy ").to_string();
+            app.buy(&item);
+        }
+        _ if input.starts_with("/craft ") => {
+            match input.trim_start_matches("/craft ").split_once('+') {
+                Some((a, b)) if !a.trim().is_empty() && !b.trim().is_empty() => app.craft(a, b),
+                _ => app.push_log(LogKind::System, "Usage: /craft <item> + <item>"),
+            }
+        }
+        _ if input.starts_with("/equip ") => {
+            let args = input.trim_start_matches("/equip ").trim();
+            let (slot, name) = match args.split_once(' ') {
+                Some((first, rest)) if Slot::parse(first).is_some() => (Slot::parse(first), rest),
+                _ => (None, args),
+            };
+            app.equip(name, slot);
+        }
+        _ if input.starts_with("/unequip ") => {
+            app.unequip(input.trim_start_matches("/unequip "));
+        }
+        _ if input.starts_with("/inspect ") => {
+            let name = input.trim_start_matches("/inspect ").trim();
+            let equipped = app.state.equipment.find(name);
+            let equipped = equipped.and_then(|slot| app.state.equipment.slot(slot).as_ref());
+            let message = match app.state.item(name).or(equipped) {
+                Some(item) => item.details(),
+                None => format!("You are not carrying {name}."),
+            };
+            app.push_log(LogKind::System, message);
+        }
+        "/map" => {
+            app.show_map = !app.show_map;
+        }
+        "/tab new" => app.tab_request = Some(TabRequest::New),
+        "/tab close" => app.tab_request = Some(TabRequest::Close),
+        "/tab next" => app.tab_request = Some(TabRequest::Next),
+        _ if input.starts_with("/tab ") => {
+            match input.trim_start_matches("/tab ").trim().parse::<usize>() {
+                Ok(n) if n > 0 => app.tab_request = Some(TabRequest::Switch(n - 1)),
+                _ => app.push_log(LogKind::System, "Usage: /tab new|close|next|<n>"),
+            }
+        }
+        "/turns" => {
+            app.show_turns = !app.show_turns;
+        }
+        _ if input.starts_with("/goto turn ") => {
+            match input.trim_start_matches("/goto turn ").trim().parse::<u32>() {
+                Ok(turn) if turn > 0 && turn <= app.state.turn + 1 => {
+                    app.goto_turn = Some(turn);
+                    app.follow = false;
+                }
+                Ok(_) => app.push_toast(format!("Turns run from 1 to {}.", app.state.turn + 1)),
+                Err(_) => app.push_log(LogKind::System, "Usage: /goto turn <n>"),
+            }
+        }
+        "/copy" | "/copy last" => match app.narration_text(None) {
+            Some(text) => app.copy_to_clipboard(text),
+            None => app.push_toast("Nothing narrated yet."),
+        },
+        _ if input.starts_with("/copy turn ") => {
+            match input.trim_start_matches("/copy turn ").trim().parse::<u32>() {
+                Ok(turn) => match app.narration_text(Some(turn)) {
+                    Some(text) => app.copy_to_clipboard(text),
+                    None => app.push_toast(format!("No narration in turn {turn}.")),
+                },
+                Err(_) => app.push_log(LogKind::System, "Usage: /copy last | /copy turn <n>"),
+            }
+        }
+        "/filter" | "/filter off" => {
+            app.set_log_filter(None);
+        }
+        _ if input.starts_with("/filter ") => {
+            let filter = LogFilter::parse(input.trim_start_matches("/filter "));
+            app.set_log_filter(Some(filter));
+        }
+        "/sheet" => {
+            let summary = app.state.character.summary();
+            app.push_log(LogKind::System, format!("Character: {summary}"));
+        }
+        _ if input.starts_with("/sheet name ") => {
+            let name = input.trim_start_matches("/sheet name ").trim();
+            if name.is_empty() {
+                app.push_log(LogKind::System, "Usage: /sheet name <name>");
+            } else {
+                app.state.character.name = name.to_string();
+                app.push_log(LogKind::System, format!("Character renamed to {name}"));
+            }
+        }
+        _ if input.starts_with("/sheet set ") => {
+            let args = input.trim_start_matches("/sheet set ").trim();
+            match args.rsplit_once(' ') {
+                Some((stat, value)) if !stat.trim().is_empty() => match value.parse::<i32>() {
+                    Ok(value) => {
+                        let stat = stat.trim();
+                        let stored = app.state.character.set(stat, value);
+                        app.push_log(LogKind::System, format!("{stat} set to {stored}"));
+                    }
+                    Err(_) => app.push_log(LogKind::System, "Usage: /sheet set <stat> <value>"),
+                },
+                _ => app.push_log(LogKind::System, "Usage: /sheet set <stat> <value>"),
+            }
+        }
+        _ if input.starts_with("/sheet remove ") => {
+            let skill = input.trim_start_matches("/sheet remove ").trim();
+            if app.state.character.remove_skill(skill) {
+                app.push_log(LogKind::System, format!("Removed skill: {skill}"));
+            } else {
+                app.push_log(LogKind::System, format!("Skill not found: {skill}"));
+            }
+        }
+        _ if input.starts_with("/roll ") => {
+            match DiceExpr::parse(input.trim_start_matches("/roll ")) {
+                Some(expr) => app.roll_dice(expr),
+                None => app.push_log(LogKind::System, "Usage: /roll <dice>, e.g. /roll 2d6+3"),
+            }
+        }
+        "/gallery" => {
+            if app.scenes.is_empty() {
+                app.push_log(LogKind::System, "No scenes have been drawn yet.");
+            } else {
+                let selected = app.scenes.len() - 1;
+                app.gallery = Some(GalleryView { selected });
+            }
+        }
+        "/wiki" => {
+            let wiki = WikiView::new(app);
+            if wiki.entries.is_empty() {
+                app.push_log(LogKind::System, "The encyclopedia is empty. Keep playing!");
+            } else {
+                app.wiki = Some(wiki);
+            }
+        }
+        "/tasks" => {
+            if app.tasks.is_empty() {
+                app.push_log(LogKind::System, "No background tasks.");
+            } else {
+                let lines: Vec<String> = app
+                    .tasks
+                    .iter()
+                    .map(|task| {
+                        format!(
+                            "#{} {} ({}s)",
+                            task.id,
+                            task.kind.label(),
+                            task.elapsed().as_secs()
+                        )
+                    })
+                    .collect();
+                app.push_log(LogKind::System, lines.join("\n"));
+            }
+        }
+        _ if input.starts_with("/cancel") => {
+            let arg = input.trim_start_matches("/cancel").trim();
+            match arg.trim_start_matches('#').parse::<u64>() {
+                Ok(id) => match app.cancel_task(id) {
+                    Some(kind) => app.push_log(
+                        LogKind::System,
+                        format!("Cancelled {} task #{id}.", kind.label()),
+                    ),
+                    None => app.push_log(LogKind::System, format!("No task #{id}.")),
+                },
+                Err(_) => app.push_log(LogKind::System, "Usage: /cancel <id> (see /tasks)"),
+            }
+        }
+        "/compose" => {
+            app.line_input_requested = true;
+        }
+        "/settings" => {
+            app.settings_requested = true;
+        }
+        "/models" => {
+            if app.tasks.is_running(TaskKind::ModelList) {
+                app.push_log(LogKind::System, "Already fetching models...");
+            } else {
+                app.models_requested = true;
+                app.push_log(LogKind::System, "Fetching available models...");
+            }
+        }
+        _ if input.starts_with("/model ") => {
+            let model = input.trim_start_matches("/model ").trim();
+            if model.is_empty() {
+                app.push_log(LogKind::System, "Usage: /model <name>");
+            } else if app.busy {
+                app.push_log(
+                    LogKind::System,
+                    "Wait for the current turn before switching models.",
+                );
+            } else {
+                app.pending_settings
+                    .push(SettingChange::Model(model.to_string()));
+            }
+        }
+        _ if input.starts_with("/theme ") => {
+            let name = input.trim_start_matches("/theme ").trim();
+            app.pending_settings.push(SettingChange::Theme(name.to_string()));
+        }
+        "/theme" => {
+            let builtin: Vec<&str> = theme::builtin().into_iter().map(|(name, _)| name).collect();
+            app.push_log(
+                LogKind::System,
+                format!(
+                    "Usage: /theme <name>. Built-in themes: {}. Add your own under \"themes\" in {}.",
+                    builtin.join(", "),
+                    Settings::path().display()
+                ),
+            );
+        }
+        _ if input.starts_with("/set timeout ") => {
+            match parse_seconds(input.trim_start_matches("/set timeout ")) {
+                Some(secs) => app.pending_settings.push(SettingChange::TimeoutSecs(secs)),
+                None => app.push_log(LogKind::System, "Usage: /set timeout <seconds>"),
+            }
+        }
+        _ if input.starts_with("/set connect-timeout ") => {
+            match parse_seconds(input.trim_start_matches("/set connect-timeout ")) {
+                Some(secs) => app.pending_settings.push(SettingChange::ConnectTimeoutSecs(secs)),
+                None => app.push_log(LogKind::System, "Usage: /set connect-timeout <seconds>"),
+            }
+        }
+        _ if input.starts_with("/set max-tokens ") => {
+            match input.trim_start_matches("/set max-tokens ").trim().parse::<u32>() {
+                Ok(tokens) if tokens > 0 => {
+                    app.pending_settings.push(SettingChange::MaxOutputTokens(tokens));
+                }
+                _ => app.push_log(LogKind::System, "Usage: /set max-tokens <count>"),
+            }
+        }
+        _ if input.starts_with("/set effort ") => {
+            match ReasoningEffort::parse(input.trim_start_matches("/set effort ")) {
+                Some(effort) => app.pending_settings.push(SettingChange::ReasoningEffort(effort)),
+                None => app.push_log(
+                    LogKind::System,
+                    "Usage: /set effort <minimal|low|medium|high>",
+                ),
+            }
+        }
+        _ if input.starts_with("/set temperature ") => {
+            let value = input.trim_start_matches("/set temperature ").trim();
+            if value == "default" {
+                app.pending_settings.push(SettingChange::Temperature(None));
+            } else {
+                match value.parse::<f32>() {
+                    Ok(t) if (0.0..=2.0).contains(&t) => {
+                        app.pending_settings.push(SettingChange::Temperature(Some(t)));
+                    }
+                    _ => app.push_log(LogKind::System, "Usage: /set temperature <0-2|default>"),
+                }
+            }
+        }
+        _ if input.starts_with("/set verbosity ") => {
+            let value = input.trim_start_matches("/set verbosity ").trim();
+            if value == "default" {
+                app.pending_settings.push(SettingChange::Verbosity(None));
+            } else {
+                match Verbosity::parse(value) {
+                    Some(verbosity) => {
+                        app.pending_settings.push(SettingChange::Verbosity(Some(verbosity)));
+                    }
+                    None => app.push_log(
+                        LogKind::System,
+                        "Usage: /set verbosity <low|medium|high|default>",
+                    ),
+                }
+            }
+        }
+        _ if input.starts_with("/panel ") => {
+            match SideTab::parse(input.trim_start_matches("/panel ")) {
+                Some(tab) => app.set_side_tab(tab),
+                None => app.push_log(LogKind::System, "Usage: /panel inventory|quests|npcs|flags"),
+            }
+        }
+        "/scene" => app.redraw_scene(),
+        "/scene image" => {
+            if app.illustration_requested || app.tasks.is_running(TaskKind::Illustration) {
+                app.push_toast("An illustration is already being painted.");
+            } else {
+                app.illustration_requested = true;
+                app.push_toast("Painting the scene...");
+            }
+        }
+        _ if input.starts_with("/scene style") => {
+            match SceneStyle::parse(input.trim_start_matches("/scene style")) {
+                Some(style) => app.pending_settings.push(SettingChange::SceneStyle(style)),
+                None => {
+                    let names: Vec<&str> = SceneStyle::ALL.iter().map(|s| s.label()).collect();
+                    let usage = format!("Usage: /scene style {}", names.join("|"));
+                    app.push_log(LogKind::System, usage);
+                }
+            }
+        }
+        "/scene on" | "/scene off" => {
+            app.pending_settings.push(SettingChange::ShowScene(input == "/scene on"));
+        }
+        "/choices on" | "/choices off" => {
+            let enabled = input == "/choices on";
+            if enabled && !app.capabilities.structured_output {
+                app.push_log(
+                    LogKind::System,
+                    "Choice mode needs a model with structured output; it will apply once you switch.",
+                );
+            }
+            if !enabled {
+                app.choices.clear();
+            }
+            app.pending_settings.push(SettingChange::ChoiceMode(enabled));
+        }
+        _ if input.starts_with("/set difficulty ") => {
+            match Difficulty::parse(input.trim_start_matches("/set difficulty ")) {
+                Some(difficulty) => {
+                    app.pending_settings.push(SettingChange::Difficulty(difficulty));
+                }
+                None => app.push_log(LogKind::System, "Usage: /set difficulty easy|normal|brutal"),
+            }
+        }
+        _ if input.starts_with("/set pace ") => {
+            match input.trim_start_matches("/set pace ").trim().parse::<u32>() {
+                Ok(minutes) if minutes <= 24 * 60 => {
+                    app.pending_settings.push(SettingChange::MinutesPerTurn(minutes));
+                }
+                _ => app.push_log(LogKind::System, "Usage: /set pace <minutes per turn>"),
+            }
+        }
+        _ if input.starts_with("/set typewriter ") => {
+            let value = input.trim_start_matches("/set typewriter ").trim();
+            let chars = if value == "off" { Ok(0) } else { value.parse::<u32>() };
+            match chars {
+                Ok(chars) => app.pending_settings.push(SettingChange::Typewriter(chars)),
+                Err(_) => app.push_log(LogKind::System, "Usage: /set typewriter <chars per tick|off>"),
+            }
+        }
         _ if input.starts_with("/set location ") => {
             let loc = input.trim_start_matches("/set location ").trim();
             if loc.is_empty() {
                 app.push_log(LogKind::System, "Usage: /set location <name>");
             } else {
-                app.state.location = loc.to_string();
+                app.state.set_location(loc);
+                app.claims.set_location(loc, app.state.turn);
                 app.push_log(LogKind::System, format!("Location set to: {loc}"));
             }
         }
@@ -87,7 +1276,8 @@ fn handle_command(input: &str, app: &mut App) -> Result<bool> {
             if item.is_empty() {
                 app.push_log(LogKind::System, "Usage: /add item <name>");
             } else {
-                app.state.inventory.push(item.to_string());
+                app.state.gain_item(item);
+                app.claims.keep_item(item);
                 app.push_log(LogKind::System, format!("Added item: {item}"));
             }
         }
@@ -95,8 +1285,8 @@ fn handle_command(input: &str, app: &mut App) -> Result<bool> {
             let item = input.trim_start_matches("/remove item ").trim();
             if item.is_empty() {
                 app.push_log(LogKind::System, "Usage: /remove item <name>");
-            } else if let Some(pos) = app.state.inventory.iter().position(|i| i == item) {
-                app.state.inventory.remove(pos);
+            } else if app.state.lose_item(item) {
+                app.claims.drop_item(item);
                 app.push_log(LogKind::System, format!("Removed item: {item}"));
             } else {
                 app.push_log(LogKind::System, format!("Item not found: {item}"));
@@ -104,21 +1294,22 @@ fn handle_command(input: &str, app: &mut App) -> Result<bool> {
         }
         _ if input.starts_with("/flag ") => {
             let flag = input.trim_start_matches("/flag ").trim();
-            if flag.is_empty() {
-                app.push_log(LogKind::System, "Usage: /flag <name>");
-            } else if app.state.flags.iter().any(|f| f == flag) {
-                app.push_log(LogKind::System, format!("Flag already set: {flag}"));
-            } else {
-                app.state.flags.push(flag.to_string());
+            if flag.is_empty() || flag.starts_with('=') {
+                app.push_log(LogKind::System, "Usage: /flag <name>[=<value>]");
+            } else if app.state.raise_flag(flag) {
+                app.claims.set_flag(flag);
                 app.push_log(LogKind::System, format!("Flag set: {flag}"));
+            } else {
+                app.claims.set_flag(flag);
+                app.push_log(LogKind::System, format!("Flag already set: {flag}"));
             }
         }
         _ if input.starts_with("/unflag ") => {
             let flag = input.trim_start_matches("/unflag ").trim();
             if flag.is_empty() {
                 app.push_log(LogKind::System, "Usage: /unflag <name>");
-            } else if let Some(pos) = app.state.flags.iter().position(|f| f == flag) {
-                app.state.flags.remove(pos);
+            } else if app.state.clear_flag(flag) {
+                app.claims.clear_flag(flag);
                 app.push_log(LogKind::System, format!("Flag cleared: {flag}"));
             } else {
                 app.push_log(LogKind::System, format!("Flag not found: {flag}"));
@@ -131,3 +1322,11 @@ fn handle_command(input: &str, app: &mut App) -> Result<bool> {
 
     Ok(false)
 }
+
+fn parse_seconds(text: &str) -> Option<u64> {
+    text.trim()
+        .trim_end_matches('s')
+        .parse::<u64>()
+        .ok()
+        .filter(|secs| *secs > 0)
+}
@@ -0,0 +1,63 @@
+use serde::Serialize;
+
+/// A stack of identical items in the player's inventory.
+#[derive(Clone, Serialize)]
+pub(crate) struct Item {
+    pub(crate) name: String,
+    pub(crate) qty: u32,
+    /// Set when the model or a recipe describes the item; shown by `/inspect`.
+    pub(crate) description: String,
+    pub(crate) tags: Vec<String>,
+}
+
+impl Item {
+    pub(crate) fn new(name: &str) -> Self {
+        Self {
+            name: name.trim().to_string(),
+            qty: 1,
+            description: String::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// Names match whatever their case, including non-ASCII letters like `Épée`.
+    pub(crate) fn is(&self, name: &str) -> bool {
+        self.name.to_lowercase() == name.trim().to_lowercase()
+    }
+
+    /// Merges another stack of the same item into this one.
+    pub(crate) fn absorb(&mut self, other: Item) {
+        self.qty = self.qty.saturating_add(other.qty);
+        if self.description.is_empty() {
+            self.description = other.description;
+        }
+        for tag in other.tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+    }
+
+    /// `rope` or `rope x3`.
+    pub(crate) fn label(&self) -> String {
+        if self.qty > 1 {
+            format!("{} x{}", self.name, self.qty)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Multi-line text for `/inspect`.
+    pub(crate) fn details(&self) -> String {
+        let mut lines = vec![self.label()];
+        if self.description.is_empty() {
+            lines.push("No description yet.".to_string());
+        } else {
+            lines.push(self.description.clone());
+        }
+        if !self.tags.is_empty() {
+            lines.push(format!("Tags: {}", self.tags.join(", ")));
+        }
+        lines.join("\n")
+    }
+}
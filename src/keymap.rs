@@ -0,0 +1,246 @@
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+/// A key with modifiers, written in the config file like `ctrl+n`, `f12` or `pageup`.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    const fn ctrl(ch: char) -> Self {
+        Self::new(KeyCode::Char(ch), KeyModifiers::CONTROL)
+    }
+
+    const fn plain(code: KeyCode) -> Self {
+        Self::new(code, KeyModifiers::NONE)
+    }
+
+    /// Shift is ignored for characters, which terminals report as the shifted char itself;
+    /// `parse` drops it from character bindings the same way.
+    pub(crate) fn matches(&self, key: KeyEvent) -> bool {
+        let modifiers = match key.code {
+            KeyCode::Char(_) => key.modifiers - KeyModifiers::SHIFT,
+            _ => key.modifiers,
+        };
+        let code = match key.code {
+            KeyCode::Char(ch) => KeyCode::Char(ch.to_ascii_lowercase()),
+            code => code,
+        };
+        code == self.code && modifiers == self.modifiers
+    }
+
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        // `ctrl++` binds the plus key.
+        if text.ends_with("++") {
+            parts.truncate(parts.len() - 2);
+            parts.push("+");
+        }
+        let key = parts.pop()?;
+        let mut modifiers = KeyModifiers::NONE;
+        for part in parts {
+            modifiers |= match part {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return None,
+            };
+        }
+        let code = match key {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "space" => KeyCode::Char(' '),
+            _ => match key.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(ch), None) => KeyCode::Char(ch),
+                        _ => return None,
+                    }
+                }
+            },
+        };
+        if let KeyCode::Char(_) = code {
+            modifiers -= KeyModifiers::SHIFT;
+        }
+        Some(Self::new(code, modifiers))
+    }
+
+    /// A character without Ctrl or Alt, which typing in the input would trigger.
+    fn is_typed(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_))
+            && !self.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, label) in [
+            (KeyModifiers::CONTROL, "Ctrl+"),
+            (KeyModifiers::ALT, "Alt+"),
+            (KeyModifiers::SHIFT, "Shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{label}")?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(ch) => write!(f, "{}", ch.to_ascii_uppercase()),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+impl TryFrom<String> for KeyChord {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        Self::parse(&text).ok_or_else(|| format!("unknown key {text:?}"))
+    }
+}
+
+impl From<KeyChord> for String {
+    fn from(chord: KeyChord) -> Self {
+        chord.to_string().to_lowercase()
+    }
+}
+
+/// Global key bindings, set under `keys` in the config file.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Keymap {
+    pub(crate) quit: KeyChord,
+    pub(crate) new_game: KeyChord,
+    /// Puts the last sent action back in the input to retry it.
+    pub(crate) restore_input: KeyChord,
+    pub(crate) palette: KeyChord,
+    pub(crate) settings: KeyChord,
+    /// Cycles the Story pane's speaker filter.
+    pub(crate) filter: KeyChord,
+    pub(crate) inspector: KeyChord,
+    /// Types the next action at the terminal's own prompt, where input methods work.
+    pub(crate) line_input: KeyChord,
+    /// Asks for new scene art in a different composition, skipping the scene cache.
+    pub(crate) redraw_scene: KeyChord,
+    /// Cycles focus between the Input, Story and Scene panes for scrolling.
+    pub(crate) cycle_focus: KeyChord,
+    pub(crate) scroll_up: KeyChord,
+    pub(crate) scroll_down: KeyChord,
+    pub(crate) page_up: KeyChord,
+    pub(crate) page_down: KeyChord,
+    /// Home and End alone move the input cursor, so the story's ends take Ctrl.
+    pub(crate) scroll_top: KeyChord,
+    pub(crate) scroll_bottom: KeyChord,
+    pub(crate) grow_scene: KeyChord,
+    pub(crate) shrink_scene: KeyChord,
+    pub(crate) widen_side_panel: KeyChord,
+    pub(crate) narrow_side_panel: KeyChord,
+    pub(crate) toggle_side_panel: KeyChord,
+    /// Cycles the side panel through Inventory, Quests, NPCs and Flags.
+    pub(crate) next_side_tab: KeyChord,
+    pub(crate) new_tab: KeyChord,
+    pub(crate) next_tab: KeyChord,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            quit: KeyChord::ctrl('c'),
+            new_game: KeyChord::ctrl('n'),
+            restore_input: KeyChord::ctrl('r'),
+            palette: KeyChord::ctrl('p'),
+            settings: KeyChord::ctrl(','),
+            filter: KeyChord::ctrl('f'),
+            inspector: KeyChord::plain(KeyCode::F(12)),
+            line_input: KeyChord::plain(KeyCode::F(5)),
+            redraw_scene: KeyChord::ctrl('g'),
+            cycle_focus: KeyChord::plain(KeyCode::Tab),
+            scroll_up: KeyChord::plain(KeyCode::Up),
+            scroll_down: KeyChord::plain(KeyCode::Down),
+            page_up: KeyChord::plain(KeyCode::PageUp),
+            page_down: KeyChord::plain(KeyCode::PageDown),
+            scroll_top: KeyChord::new(KeyCode::Home, KeyModifiers::CONTROL),
+            scroll_bottom: KeyChord::new(KeyCode::End, KeyModifiers::CONTROL),
+            grow_scene: KeyChord::new(KeyCode::Down, KeyModifiers::CONTROL),
+            shrink_scene: KeyChord::new(KeyCode::Up, KeyModifiers::CONTROL),
+            widen_side_panel: KeyChord::new(KeyCode::Left, KeyModifiers::CONTROL),
+            narrow_side_panel: KeyChord::new(KeyCode::Right, KeyModifiers::CONTROL),
+            toggle_side_panel: KeyChord::ctrl('b'),
+            next_side_tab: KeyChord::ctrl('o'),
+            new_tab: KeyChord::ctrl('t'),
+            next_tab: KeyChord::new(KeyCode::Tab, KeyModifiers::CONTROL),
+        }
+    }
+}
+
+impl Keymap {
+    /// Rejects bindings that typing would trigger and keys bound to two actions.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let bindings = self.describe();
+        for (i, (name, chord, _)) in bindings.iter().enumerate() {
+            if chord.is_typed() {
+                return Err(format!("keys.{name} needs Ctrl or Alt with {chord}"));
+            }
+            if let Some((other, _, _)) = bindings[..i].iter().find(|(_, c, _)| c == chord) {
+                return Err(format!("keys.{other} and keys.{name} are both bound to {chord}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Config name, binding and what it does, for `/keys`.
+    pub(crate) fn describe(&self) -> Vec<(&'static str, KeyChord, &'static str)> {
+        vec![
+            ("quit", self.quit, "Quit the game"),
+            ("new_game", self.new_game, "Open the new game picker"),
+            ("restore_input", self.restore_input, "Restore the last action to retry it"),
+            ("palette", self.palette, "Open the command palette"),
+            ("settings", self.settings, "Change model, theme, timeout and more"),
+            ("filter", self.filter, "Cycle which speaker the story shows"),
+            ("inspector", self.inspector, "Show the last turn's raw JSON"),
+            ("line_input", self.line_input, "Type an action outside the TUI, for IME input"),
+            ("redraw_scene", self.redraw_scene, "Redraw the scene art differently"),
+            ("cycle_focus", self.cycle_focus, "Focus the Story, Scene or Input pane"),
+            ("scroll_up", self.scroll_up, "Scroll the focused pane up"),
+            ("scroll_down", self.scroll_down, "Scroll the focused pane down"),
+            ("page_up", self.page_up, "Scroll the focused pane up a page"),
+            ("page_down", self.page_down, "Scroll the focused pane down a page"),
+            ("scroll_top", self.scroll_top, "Jump to the top of the focused pane"),
+            ("scroll_bottom", self.scroll_bottom, "Jump to the bottom of the focused pane"),
+            ("grow_scene", self.grow_scene, "Move the Scene/Story divider down"),
+            ("shrink_scene", self.shrink_scene, "Move the Scene/Story divider up"),
+            ("widen_side_panel", self.widen_side_panel, "Widen the side panel"),
+            ("narrow_side_panel", self.narrow_side_panel, "Narrow the side panel"),
+            ("toggle_side_panel", self.toggle_side_panel, "Show or hide the side panel"),
+            ("next_side_tab", self.next_side_tab, "Switch the side panel's list"),
+            ("new_tab", self.new_tab, "Start another game in a new tab"),
+            ("next_tab", self.next_tab, "Switch to the next tab"),
+        ]
+    }
+}
@@ -0,0 +1,131 @@
+use unicode_width::UnicodeWidthStr;
+
+/// The input box's text and cursor, with readline-style editing.
+/// `cursor` is a byte offset that always sits on a char boundary.
+#[derive(Default)]
+pub(crate) struct LineEditor {
+    text: String,
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text, leaving the cursor at the end.
+    pub(crate) fn set(&mut self, text: String) {
+        self.cursor = text.len();
+        self.text = text;
+    }
+
+    /// Returns the text and clears the editor.
+    pub(crate) fn take(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.take();
+    }
+
+    /// Zero-based line and display column of the cursor, for placing the terminal cursor.
+    /// Columns count terminal cells, so CJK and emoji take two.
+    pub(crate) fn cursor_position(&self) -> (usize, usize) {
+        let before = &self.text[..self.cursor];
+        let line = before.matches('\n').count();
+        let column = before.rsplit('\n').next().unwrap_or_default().width();
+        (line, column)
+    }
+
+    pub(crate) fn insert(&mut self, ch: char) {
+        self.text.insert(self.cursor, ch);
+        self.cursor += ch.len_utf8();
+    }
+
+    pub(crate) fn insert_str(&mut self, text: &str) {
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    pub(crate) fn backspace(&mut self) {
+        let start = self.prev_boundary();
+        self.text.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    pub(crate) fn delete(&mut self) {
+        let end = self.next_boundary();
+        self.text.drain(self.cursor..end);
+    }
+
+    pub(crate) fn left(&mut self) {
+        self.cursor = self.prev_boundary();
+    }
+
+    pub(crate) fn right(&mut self) {
+        self.cursor = self.next_boundary();
+    }
+
+    /// Home: start of the current line.
+    pub(crate) fn line_start(&mut self) {
+        self.cursor = self.text[..self.cursor].rfind('\n').map_or(0, |i| i + 1);
+    }
+
+    /// End: end of the current line.
+    pub(crate) fn line_end(&mut self) {
+        self.cursor = self.text[self.cursor..]
+            .find('\n')
+            .map_or(self.text.len(), |i| self.cursor + i);
+    }
+
+    /// Alt+B: back to the start of the previous word.
+    pub(crate) fn word_left(&mut self) {
+        self.cursor = self.word_start();
+    }
+
+    /// Alt+F: forward past the end of the next word.
+    pub(crate) fn word_right(&mut self) {
+        let rest = &self.text[self.cursor..];
+        let word = rest.find(|ch: char| !ch.is_whitespace()).unwrap_or(rest.len());
+        let end = rest[word..]
+            .find(char::is_whitespace)
+            .map_or(rest.len(), |i| word + i);
+        self.cursor += end;
+    }
+
+    /// Ctrl+W: deletes the word before the cursor.
+    pub(crate) fn delete_word(&mut self) {
+        let start = self.word_start();
+        self.text.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    /// Ctrl+U: deletes from the start of the line to the cursor.
+    pub(crate) fn clear_line(&mut self) {
+        let end = self.cursor;
+        self.line_start();
+        self.text.drain(self.cursor..end);
+    }
+
+    fn word_start(&self) -> usize {
+        let before = self.text[..self.cursor].trim_end();
+        before
+            .rfind(char::is_whitespace)
+            .map_or(0, |i| i + before[i..].chars().next().map_or(1, char::len_utf8))
+    }
+
+    fn prev_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map_or(self.cursor, |ch| self.cursor + ch.len_utf8())
+    }
+}
@@ -1,127 +1,720 @@
+mod achievements;
 mod api;
 mod app;
+mod banner;
+mod capabilities;
+mod chapters;
+mod character;
+mod checks;
+mod clock;
 mod config;
+mod conflicts;
+mod crafting;
+mod dice;
+mod encounters;
+mod ending;
+mod equipment;
+mod error;
+mod flags;
+mod gallery;
+mod graphics;
+mod hash;
+mod hooks;
+mod hygiene;
 mod input;
+mod items;
+mod keymap;
+mod line_editor;
+mod map;
+mod memory;
+mod palette;
+mod quests;
+mod scenarios;
+mod scene_cache;
+mod sketch;
+mod structured;
+mod summary;
+mod tasks;
+mod theme;
+mod tokens;
+mod tools;
 mod ui;
+mod weather;
+mod wiki;
+mod wirelog;
 
 use std::env;
 use std::io;
-use std::sync::mpsc::{self, TryRecvError};
-use std::thread;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use anyhow::Result;
 use crossterm::{
-    event,
+    event::{
+        self, DisableBracketedPaste, DisableFocusChange, EnableBracketedPaste, EnableFocusChange,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::prelude::*;
 
-use crate::api::advance_turn;
-use crate::app::App;
-use crate::config::load_or_prompt_api_key;
-use crate::input::handle_key_event;
+use crate::api::{build_narrator, Narrator};
+use crate::app::{App, TabRequest};
+use crate::config::{
+    load_key_ring, KeyRing, SettingChange, Settings, DATA_DIR, ENV_FILE, SCENE_REDRAW_NOTE,
+};
+use crate::error::{Error, Result};
+use crate::graphics::InlineImages;
+use crate::hooks::{notify_turn, run_turn_hooks, turn_payload};
+use crate::hygiene::ensure_secret_hygiene;
+use crate::input::{handle_key_event, handle_paste};
+use crate::scenarios::Scenario;
+use crate::scene_cache::SceneCache;
+use crate::tasks::{TaskKind, TaskOutput};
 use crate::ui::draw_ui;
+use crate::wirelog::WireLog;
 
 fn main() -> Result<()> {
     let debug = env::args().any(|arg| arg == "--debug" || arg == "-d");
-    let api_key = load_or_prompt_api_key()?;
+    let log_file = parse_log_file_arg()?;
+    let seed = parse_seed_arg()?;
+    let scenario = match parse_scenario_arg()? {
+        Some(path) => Some(Scenario::load(&path)?),
+        None => None,
+    };
+    // Only a safeguard: an unwritable `.gitignore` should not stop the game.
+    if let Err(err) = ensure_secret_hygiene() {
+        println!("Warning: could not update .gitignore for {ENV_FILE} and {DATA_DIR}/: {err}");
+    }
+    let mut settings = Settings::load()?;
+    settings.no_color = env::args().any(|arg| arg == "--no-color")
+        || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let keys = Arc::new(load_key_ring(&settings)?);
+    let wire_log = match log_file {
+        Some(path) => Some(Arc::new(WireLog::open(&path, Arc::clone(&keys))?)),
+        None => None,
+    };
 
-    enable_raw_mode()?;
+    enable_raw_mode().map_err(Error::Terminal)?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste, EnableFocusChange)
+        .map_err(Error::Terminal)?;
     let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let mut terminal = Terminal::new(backend).map_err(Error::Terminal)?;
 
-    let res = run_app(&mut terminal, api_key, debug);
+    let res = run_app(&mut terminal, settings, keys, debug, wire_log, seed, scenario);
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    disable_raw_mode().map_err(Error::Terminal)?;
+    execute!(
+        terminal.backend_mut(),
+        DisableFocusChange,
+        DisableBracketedPaste,
+        LeaveAlternateScreen
+    )
+    .map_err(Error::Terminal)?;
+    terminal.show_cursor().map_err(Error::Terminal)?;
 
     res
 }
 
+/// Leaves the TUI and reads one line in the terminal's cooked mode, where the input
+/// method composes text as in any shell, then restores the TUI.
+fn read_line_outside_tui(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<String> {
+    disable_raw_mode().map_err(Error::Terminal)?;
+    execute!(
+        terminal.backend_mut(),
+        DisableFocusChange,
+        DisableBracketedPaste,
+        LeaveAlternateScreen
+    )
+    .map_err(Error::Terminal)?;
+    println!("Type your action and press Enter (an empty line cancels):");
+    let mut line = String::new();
+    let read = io::stdin().read_line(&mut line);
+    enable_raw_mode().map_err(Error::Terminal)?;
+    execute!(
+        terminal.backend_mut(),
+        EnterAlternateScreen,
+        EnableBracketedPaste,
+        EnableFocusChange
+    )
+    .map_err(Error::Terminal)?;
+    terminal.clear().map_err(Error::Terminal)?;
+    read?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// `--log-file <path>` or `--log-file=<path>`.
+fn parse_log_file_arg() -> Result<Option<PathBuf>> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if let Some(path) = arg.strip_prefix("--log-file=") {
+            return Ok(Some(PathBuf::from(path)));
+        }
+        if arg == "--log-file" {
+            return match args.next() {
+                Some(path) => Ok(Some(PathBuf::from(path))),
+                None => Err(Error::Config("--log-file needs a path.".to_string())),
+            };
+        }
+    }
+    Ok(None)
+}
+
+/// `--scenario <path>` or `--scenario=<path>`.
+fn parse_scenario_arg() -> Result<Option<PathBuf>> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if let Some(path) = arg.strip_prefix("--scenario=") {
+            return Ok(Some(PathBuf::from(path)));
+        }
+        if arg == "--scenario" {
+            return match args.next() {
+                Some(path) => Ok(Some(PathBuf::from(path))),
+                None => Err(Error::Config("--scenario needs a path.".to_string())),
+            };
+        }
+    }
+    Ok(None)
+}
+
+/// `--seed <n>` or `--seed=<n>`, fixing all local randomness for replays.
+fn parse_seed_arg() -> Result<Option<u64>> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix("--seed=") {
+            Some(value) => value.to_string(),
+            None if arg == "--seed" => args.next().unwrap_or_default(),
+            None => continue,
+        };
+        return value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| Error::Config("--seed needs a non-negative integer.".to_string()));
+    }
+    Ok(None)
+}
+
 fn run_app(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    api_key: String,
+    mut settings: Settings,
+    keys: Arc<KeyRing>,
     debug: bool,
+    wire_log: Option<Arc<WireLog>>,
+    seed: Option<u64>,
+    scenario: Option<Scenario>,
 ) -> Result<()> {
-    let mut app = App::new();
+    let mut narrator = build_narrator(&settings, Arc::clone(&keys), debug, wire_log.clone());
+    let mut first = App::new(&settings, narrator.capabilities(), seed);
+    if let Some(scenario) = scenario {
+        first.add_scenario(scenario.clone());
+        first.scenario_picker = None;
+        first.start_scenario(Some(scenario));
+    }
+    // Each tab is an independent game with its own history, state and tasks.
+    let mut tabs = vec![first];
+    let mut active = 0;
+
+    let mut images = settings.graphics.protocol().map(InlineImages::new);
+    // Terminals without focus reporting never send FocusLost, so they are never notified.
+    let mut focused = true;
 
     loop {
-        terminal.draw(|frame| draw_ui(frame, &mut app))?;
+        let titles: Vec<String> = tabs.iter().map(App::tab_title).collect();
+        terminal
+            .draw(|frame| draw_ui(frame, &mut tabs[active], &titles, active))
+            .map_err(Error::Terminal)?;
+        // Pictures go straight to the terminal, over the blank Scene pane ratatui drew.
+        if let Some(images) = &mut images {
+            let app = &tabs[active];
+            let want = app
+                .scene_image_area
+                .zip(app.scene_image.as_ref())
+                .map(|(area, image)| ((active, area, app.scene_image_id), image));
+            if images.sync(terminal.backend_mut(), want).map_err(Error::Terminal)? {
+                terminal.clear().map_err(Error::Terminal)?;
+            }
+        }
 
-        if event::poll(Duration::from_millis(200))? {
-            match event::read()? {
-                event::Event::Key(key) => {
-                    if handle_key_event(key, &mut app)? {
-                        break;
+        if event::poll(Duration::from_millis(200)).map_err(Error::Terminal)? {
+            match event::read().map_err(Error::Terminal)? {
+                event::Event::Key(key) if handle_key_event(key, &mut tabs[active])? => {
+                    tabs[active].confirm_quit = false;
+                    tabs[active].leaving = true;
+                    if std::mem::take(&mut tabs[active].closing) {
+                        tabs[active].tab_request = Some(TabRequest::Close);
+                    } else {
+                        // Every other game with unsaved progress asks before the program exits.
+                        let unsaved =
+                            tabs.iter().position(|app| !app.leaving && app.has_unsaved_progress());
+                        let Some(index) = unsaved else {
+                            break;
+                        };
+                        active = index;
+                        tabs[index].confirm_quit = true;
                     }
                 }
+                event::Event::Paste(text) => handle_paste(&text, &mut tabs[active]),
+                event::Event::FocusGained => focused = true,
+                event::Event::FocusLost => focused = false,
                 event::Event::Resize(_, _) => {}
                 _ => {}
             }
+        } else {
+            for app in &mut tabs {
+                app.tick_reveal();
+                app.tick_scene_transition();
+            }
         }
 
-        if app.busy {
-            if let Some(rx) = &app.pending_response {
-                match rx.try_recv() {
-                    Ok(result) => {
-                        app.pending_response = None;
-                        app.busy = false;
-                        app.thinking_started = None;
-                        match result {
-                            Ok((reply, output_items, debug_summary)) => {
-                                app.push_assistant_reply(&reply);
-                                app.push_history_chunk(output_items);
-                                if debug {
-                                    app.push_log(app::LogKind::System, debug_summary);
-                                }
-                                app.state.turn = app.state.turn.saturating_add(1);
-                                app.status = "Ready".to_string();
+        if std::mem::take(&mut tabs[active].line_input_requested) {
+            let line = read_line_outside_tui(terminal)?;
+            tabs[active].input.insert_str(&line);
+        }
+
+        for app in &mut tabs {
+            app.receive_hook_errors();
+        }
+
+        match tabs[active].tab_request.take() {
+            Some(TabRequest::New) => {
+                tabs.push(App::new(&settings, narrator.capabilities(), None));
+                active = tabs.len() - 1;
+            }
+            // Closing a game with unsaved progress asks first, like quitting.
+            Some(TabRequest::Close) if tabs.len() > 1 => {
+                let app = &mut tabs[active];
+                if !app.leaving && app.has_unsaved_progress() {
+                    app.closing = true;
+                    app.confirm_quit = true;
+                } else {
+                    tabs.remove(active);
+                    active = active.min(tabs.len() - 1);
+                }
+            }
+            Some(TabRequest::Close) => {
+                tabs[active].push_toast("This is the last tab; quit to close it.");
+            }
+            Some(TabRequest::Next) => active = (active + 1) % tabs.len(),
+            Some(TabRequest::Switch(index)) if index < tabs.len() => active = index,
+            Some(TabRequest::Switch(_)) => {
+                let count = tabs.len();
+                tabs[active].push_toast(format!("There are {count} tabs."));
+            }
+            None => {}
+        }
+
+        // Staying in any tab calls off the quit.
+        if !tabs.iter().any(|app| app.confirm_quit) {
+            for app in &mut tabs {
+                app.leaving = false;
+                app.closing = false;
+            }
+        }
+
+        let changes: Vec<SettingChange> = tabs
+            .iter_mut()
+            .flat_map(|app| std::mem::take(&mut app.pending_settings))
+            .collect();
+        if !changes.is_empty() {
+            for change in changes {
+                let message = settings.apply(&change);
+                tabs[active].push_log(app::LogKind::System, message);
+            }
+            narrator = build_narrator(&settings, Arc::clone(&keys), debug, wire_log.clone());
+            for app in &mut tabs {
+                app.model = settings.request_model().to_string();
+                app.price = settings.price();
+                app.capabilities = narrator.capabilities();
+                app.history_token_budget = settings.history_token_budget();
+                app.choice_mode = settings.choice_mode;
+                app.difficulty = settings.difficulty;
+                app.theme = settings.theme();
+                app.typewriter_chars = settings.typewriter_chars;
+                app.show_scene = settings.show_scene;
+                app.scene_style = settings.scene_style;
+                // Redraw in a newly chosen style once a scene has been drawn.
+                if app.scene_key.is_some() {
+                    app.refresh_scene_if_moved();
+                }
+                app.default_prompt = api::system_prompt(&settings);
+            }
+            if let Err(err) = settings.save() {
+                tabs[active].push_log(
+                    app::LogKind::Error,
+                    format!("Settings apply to this session only; saving failed: {err}"),
+                );
+            }
+        }
+
+        for app in &mut tabs {
+            app.retry_at = narrator.retry_at();
+            step_app(app, &narrator, &settings, debug)?;
+        }
+        for app in &mut tabs {
+            if std::mem::take(&mut app.turn_finished) && !focused {
+                notify_turn(settings.notifications, terminal.backend_mut(), &app.tab_title());
+            }
+        }
+    }
+
+    if let Some(images) = &mut images {
+        images.clear(terminal.backend_mut()).map_err(Error::Terminal)?;
+    }
+    Ok(())
+}
+
+/// Shows and caches generated scene art, or a local sketch when drawing failed.
+fn show_scene_art(app: &mut App, art: Result<String>) {
+    match art {
+        Ok(art) => {
+            if let Some(key) = app.scene_drawing.take()
+                && let Err(err) = SceneCache::store(key, art.clone())
+            {
+                app.push_log(app::LogKind::Error, format!("Scene cache failed: {err}"));
+            }
+            app.show_generated_scene(art);
+        }
+        Err(err) => {
+            app.push_log(app::LogKind::Error, format!("Scene art failed: {err}"));
+            app.scene_drawing = None;
+            let sketch = app.sketch_scene();
+            app.set_scene_ascii(sketch);
+        }
+    }
+}
+
+/// Collects one game's finished tasks and starts the work it has queued.
+fn step_app(
+    app: &mut App,
+    narrator: &Arc<dyn Narrator>,
+    settings: &Settings,
+    debug: bool,
+) -> Result<()> {
+    for finished in app.tasks.poll() {
+        match finished.output {
+            Some(TaskOutput::Turn(result)) => {
+                app.busy = false;
+                let latency = app.thinking_started.take().map(|start| start.elapsed());
+                match *result {
+                    Ok(output) => {
+                        app.save_retry_point();
+                        app.turn_finished = true;
+                        if let Some(usage) = &output.usage {
+                            app.tokens.calibrate(output.input_chars, usage.input_tokens);
+                        }
+                        app.session_stats.record_turn(
+                            latency.unwrap_or_default(),
+                            output.usage.as_ref(),
+                            app.price,
+                        );
+                        let reply = output.text;
+                        for call in &output.tool_calls {
+                            app.apply_tool_call(call);
+                        }
+                        let mut story_ended = false;
+                        app.begin_reveal();
+                        match output.structured {
+                            Some(turn) => {
+                                story_ended = turn.story_ended;
+                                app.push_structured_reply(turn);
                             }
-                            Err(err) => {
-                                if debug {
-                                    app.push_log(app::LogKind::Error, format!("{err:#}"));
-                                } else {
-                                    app.push_log(app::LogKind::Error, err.to_string());
+                            None => {
+                                app.push_assistant_reply(&reply);
+                                // Tool calls already reported this reply's state changes.
+                                if settings.state_extraction && output.tool_calls.is_empty() {
+                                    let narrator = Arc::clone(narrator);
+                                    let reply = reply.clone();
+                                    let state = app.state.clone();
+                                    app.tasks.spawn(TaskKind::Extraction, move || {
+                                        TaskOutput::StateChanges(
+                                            narrator.extract_state(&reply, &state),
+                                        )
+                                    });
                                 }
-                                app.status = "Error".to_string();
                             }
                         }
+                        if app.memory_enabled {
+                            let narrator = Arc::clone(narrator);
+                            let turn = app.state.turn;
+                            let input = app.last_sent_input.clone().unwrap_or_default();
+                            let text = format!("Player: {input}\n{reply}");
+                            app.tasks.spawn(TaskKind::Embedding, move || {
+                                let vector = narrator.embed(&text);
+                                TaskOutput::Embedded(turn, text, vector)
+                            });
+                        }
+                        app.refresh_scene_if_moved();
+                        app.push_history_chunk(output.output_items);
+                        app.record_reply_length(&reply);
+                        app.last_exchange = Some(output.exchange);
+                        if debug {
+                            app.push_log(app::LogKind::System, output.debug_summary);
+                        }
+                        app.state.turn = app.state.turn.saturating_add(1);
+                        app.state.minutes =
+                            app.state.minutes.saturating_add(settings.minutes_per_turn);
+                        app.advance_weather();
+                        app.status = "Ready".to_string();
+                        if app.epilogue_pending {
+                            app.epilogue_pending = false;
+                            app.story_over = true;
+                        } else if story_ended {
+                            app.begin_epilogue();
+                        } else {
+                            app.check_win();
+                        }
+                        app.check_achievements();
+                        if !settings.hooks.is_empty() {
+                            let payload = turn_payload(
+                                app.last_sent_input.as_deref(),
+                                &reply,
+                                &app.state,
+                            );
+                            run_turn_hooks(&settings.hooks, &payload, &app.hook_tx);
+                        }
                     }
-                    Err(TryRecvError::Empty) => {}
-                    Err(TryRecvError::Disconnected) => {
-                        app.pending_response = None;
-                        app.busy = false;
-                        app.thinking_started = None;
-                        app.push_log(app::LogKind::Error, "Response channel disconnected.");
+                    Err(err) => {
+                        if err.is_fatal() {
+                            return Err(err);
+                        }
+                        if err.is_retryable() {
+                            app.push_toast(
+                                "Temporary failure. Ctrl+R restores your input to retry.",
+                            );
+                        }
+                        app.push_log(app::LogKind::Error, err.to_string());
                         app.status = "Error".to_string();
                     }
                 }
             }
-            continue;
+            Some(TaskOutput::Models(Ok(models))) => {
+                if models.is_empty() {
+                    app.push_log(app::LogKind::System, "The provider returned no models.");
+                } else {
+                    let selected = models.iter().position(|m| *m == app.model).unwrap_or(0);
+                    app.model_picker = Some(app::ModelPicker { models, selected });
+                }
+            }
+            Some(TaskOutput::Models(Err(err))) => {
+                app.push_log(app::LogKind::Error, format!("Could not list models: {err}"));
+            }
+            Some(TaskOutput::Scene(art)) => show_scene_art(app, art),
+            Some(TaskOutput::SceneFallback(err, art)) => {
+                if !app.pictures_failed {
+                    app.pictures_failed = true;
+                    let message = format!("Scene pictures are off for this session: {err}");
+                    app.push_log(app::LogKind::Error, message);
+                }
+                show_scene_art(app, art);
+            }
+            Some(TaskOutput::SceneImage(image)) => app.set_scene_image(*image),
+            Some(TaskOutput::StateChanges(Ok(changes))) => {
+                app.apply_state_changes(&changes);
+                app.refresh_scene_if_moved();
+            }
+            Some(TaskOutput::StateChanges(Err(err))) => {
+                app.push_log(app::LogKind::Error, format!("State sync failed: {err}"));
+            }
+            Some(TaskOutput::Crafted(a, b, Ok(crafted))) => app.finish_craft(&a, &b, &crafted),
+            Some(TaskOutput::Crafted(_, _, Err(err))) => {
+                app.push_log(app::LogKind::Error, format!("Crafting failed: {err}"));
+            }
+            Some(TaskOutput::Embedded(turn, text, Ok(vector))) => {
+                app.memory.add(turn, text, vector);
+            }
+            Some(TaskOutput::Embedded(_, _, Err(err))) => {
+                if app.memory_enabled {
+                    app.memory_enabled = false;
+                    app.push_log(
+                        app::LogKind::Error,
+                        format!("Long-term memory is off for this session: {err}"),
+                    );
+                }
+            }
+            Some(TaskOutput::Summary(Ok(summary))) => {
+                app.state.summary = summary;
+                app.summarizing.clear();
+            }
+            Some(TaskOutput::Summary(Err(err))) => {
+                let chunks = std::mem::take(&mut app.summarizing);
+                app.trimmed.splice(0..0, chunks);
+                app.summary_paused = true;
+                app.push_log(app::LogKind::Error, format!("Summarizing history failed: {err}"));
+            }
+            Some(TaskOutput::ChapterSummary(Ok(summary))) => {
+                app.summarizing.clear();
+                app.end_chapter(summary);
+            }
+            Some(TaskOutput::ChapterSummary(Err(err))) => {
+                let chunks = std::mem::take(&mut app.summarizing);
+                app.trimmed.splice(0..0, chunks);
+                app.push_log(app::LogKind::Error, format!("Could not end the chapter: {err}"));
+            }
+            Some(TaskOutput::Illustration(Ok(path))) => {
+                let message = format!("Scene illustration saved to {}.", path.display());
+                app.push_log(app::LogKind::System, message);
+            }
+            Some(TaskOutput::Illustration(Err(err))) => {
+                app.push_log(app::LogKind::Error, format!("Illustration failed: {err}"));
+            }
+            Some(TaskOutput::TokenCount(chunk, Ok(tokens))) => {
+                app.tokens.record(&chunk, tokens);
+                app.trim_history();
+            }
+            Some(TaskOutput::TokenCount(_, Err(err))) => {
+                app.tokens.counting = false;
+                app.push_log(
+                    app::LogKind::System,
+                    format!("Token counts are estimated for this session: {err}"),
+                );
+            }
+            None => {
+                if finished.kind == TaskKind::Narration {
+                    app.busy = false;
+                    app.thinking_started = None;
+                    app.status = "Error".to_string();
+                }
+                app.push_log(
+                    app::LogKind::Error,
+                    format!("The {} task stopped without a result.", finished.kind.label()),
+                );
+            }
         }
+    }
+
+    if app.settings_requested {
+        app.settings_requested = false;
+        app.settings_menu = Some(app::SettingsMenu::new(settings));
+    }
+
+    if !app.tasks.is_running(TaskKind::TokenCount)
+        && let Some(chunk) = app.tokens.uncounted(&app.history).cloned()
+    {
+        let narrator = Arc::clone(narrator);
+        app.tasks.spawn(TaskKind::TokenCount, move || {
+            let tokens = narrator.count_tokens(&chunk);
+            TaskOutput::TokenCount(chunk, tokens)
+        });
+    }
 
-        if let Some(_user_input) = app.pending_input.take() {
-            let api_key = api_key.clone();
-            let history = app.history.clone();
-            let state = app.state.clone();
-            let (tx, rx) = mpsc::channel();
-            app.pending_response = Some(rx);
-            app.busy = true;
-            app.status = "Thinking...".to_string();
-            app.thinking_started = Some(Instant::now());
-            terminal.draw(|frame| draw_ui(frame, &mut app))?;
+    if app.models_requested {
+        app.models_requested = false;
+        let narrator = Arc::clone(narrator);
+        app.tasks.spawn(TaskKind::ModelList, move || {
+            TaskOutput::Models(narrator.list_models())
+        });
+    }
 
-            thread::spawn(move || {
-                let result = advance_turn(&api_key, &history, &state, debug);
-                let _ = tx.send(result);
+    // With the Scene pane hidden, skip the art request entirely.
+    if !app.show_scene {
+        app.scene_pending_response = false;
+    }
+    if app.scene_pending_response && !app.tasks.is_running(TaskKind::Scene) {
+        app.scene_pending_response = false;
+        let picture = app.graphics.is_some() && !app.pictures_failed;
+        let fresh = std::mem::take(&mut app.scene_fresh);
+        let cached = match &app.scene_key {
+            Some(key) if !picture && !fresh => SceneCache::get(key),
+            _ => None,
+        };
+        if let Some(art) = cached {
+            app.show_generated_scene(art);
+        } else {
+            app.scene_drawing = app.scene_key.clone();
+            let narrator = Arc::clone(narrator);
+            let mut context = app.build_scene_context();
+            if fresh {
+                context.push_str(&format!("\n{SCENE_REDRAW_NOTE}"));
+            }
+            // Without a picture (unsupported model or provider), fall back to text art.
+            app.tasks.spawn(TaskKind::Scene, move || {
+                let image = picture.then(|| {
+                    narrator.draw_scene_image(&context).and_then(graphics::Image::decode)
+                });
+                match image {
+                    Some(Ok(image)) => TaskOutput::SceneImage(Box::new(image)),
+                    Some(Err(err)) => TaskOutput::SceneFallback(err, narrator.draw_scene(&context)),
+                    None => TaskOutput::Scene(narrator.draw_scene(&context)),
+                }
             });
         }
     }
 
+    if app.illustration_requested && !app.tasks.is_running(TaskKind::Illustration) {
+        app.illustration_requested = false;
+        let narrator = Arc::clone(narrator);
+        let context = app.build_scene_context();
+        let turn = app.state.turn;
+        app.tasks.spawn(TaskKind::Illustration, move || {
+            let saved = narrator
+                .draw_scene_image(&context)
+                .and_then(|png| gallery::save_illustration(turn, &png));
+            TaskOutput::Illustration(saved)
+        });
+    }
+
+    if app.chapter_requested && !app.busy && !app.tasks.is_running(TaskKind::Summary) {
+        app.chapter_requested = false;
+        let narrator = Arc::clone(narrator);
+        let summary = app.state.summary.clone();
+        app.summarizing = std::mem::take(&mut app.trimmed);
+        let mut chunks = app.summarizing.clone();
+        chunks.extend(app.history.iter().cloned());
+        let transcript = summary::transcript(&chunks);
+        app.tasks.spawn(TaskKind::Chapter, move || {
+            TaskOutput::ChapterSummary(narrator.summarize(&summary, &transcript))
+        });
+    }
+
+    if !app.trimmed.is_empty()
+        && !app.summary_paused
+        && !app.tasks.is_running(TaskKind::Summary)
+        && !app.tasks.is_running(TaskKind::Chapter)
+    {
+        let narrator = Arc::clone(narrator);
+        let summary = app.state.summary.clone();
+        app.summarizing = std::mem::take(&mut app.trimmed);
+        let transcript = summary::transcript(&app.summarizing);
+        app.tasks.spawn(TaskKind::Summary, move || {
+            TaskOutput::Summary(narrator.summarize(&summary, &transcript))
+        });
+    }
+
+    if let Some((a, b)) = app.craft_requested.take() {
+        let narrator = Arc::clone(narrator);
+        let state = app.state.clone();
+        app.tasks.spawn(TaskKind::Crafting, move || {
+            let crafted = narrator.craft(&a, &b, &state);
+            TaskOutput::Crafted(a, b, crafted)
+        });
+    }
+
+    if app.busy {
+        return Ok(());
+    }
+
+    if let Some(user_input) = app.pending_input.take() {
+        let narrator = Arc::clone(narrator);
+        let mut history = app.history.clone();
+        let recall = app
+            .memory_enabled
+            .then(|| (app.memory.clone(), app.memory_top_k));
+        let state = app.state.clone();
+        app.busy = true;
+        app.status = "Thinking...".to_string();
+        app.thinking_started = Some(Instant::now());
+
+        app.tasks.spawn(TaskKind::Narration, move || {
+            if let Some((memory, k)) = recall
+                && let Some(item) = memory.recall_item(&*narrator, &user_input, state.turn, k)
+            {
+                history.push(vec![item]);
+            }
+            TaskOutput::Turn(Box::new(narrator.advance(&history, &state)))
+        });
+    }
+
     Ok(())
 }
@@ -0,0 +1,190 @@
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use crate::app::GameState;
+use crate::checks::inflects;
+
+const MOVE_VERBS: [&str; 10] = [
+    "go", "walk", "run", "head", "travel", "enter", "move", "return", "climb", "leave",
+];
+/// Compass and vertical directions; "in" and "out" are left out as they fill ordinary speech.
+const DIRECTIONS: [&str; 10] = [
+    "north", "south", "east", "west", "northeast", "northwest", "southeast", "southwest", "up",
+    "down",
+];
+
+/// A one-way connection between places; `direction` is how it is described from `from`.
+#[derive(Clone, Serialize)]
+pub(crate) struct Exit {
+    pub(crate) from: String,
+    pub(crate) to: String,
+    pub(crate) direction: Option<String>,
+}
+
+impl Exit {
+    fn describe(&self) -> String {
+        match &self.direction {
+            Some(direction) => format!("{direction} -> {}", self.to),
+            None => format!("-> {}", self.to),
+        }
+    }
+}
+
+/// Known exits grouped by origin, e.g. `Tavern: north -> Market, down -> Cellar`.
+pub(crate) fn describe_exits(state: &GameState) -> String {
+    let mut origins: Vec<&str> = state.exits.iter().map(|exit| exit.from.as_str()).collect();
+    origins.sort_unstable();
+    origins.dedup();
+    let groups: Vec<String> = origins
+        .iter()
+        .map(|origin| {
+            let exits: Vec<String> = state.exits_from(origin).map(Exit::describe).collect();
+            format!("{origin}: {}", exits.join(", "))
+        })
+        .collect();
+    if groups.is_empty() {
+        "None known".to_string()
+    } else {
+        groups.join(" | ")
+    }
+}
+
+/// Whether `input` reads as the player setting off somewhere, in any simple verb form
+/// (`walks`, `heading`, `returned`).
+pub(crate) fn is_travel(input: &str) -> bool {
+    input
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| MOVE_VERBS.iter().any(|verb| inflects(word, verb)))
+}
+
+/// Checks a movement command against known exits. Returns a note for the narrator when the
+/// player heads somewhere the established geography does not allow, `None` otherwise.
+pub(crate) fn check_travel(state: &GameState, input: &str) -> Option<String> {
+    let lower = input.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    if !is_travel(input) {
+        return None;
+    }
+    let here = state.location.as_str();
+    let exits: Vec<&Exit> = state.exits_from(here).collect();
+    let known = if exits.is_empty() {
+        "none known yet".to_string()
+    } else {
+        exits.iter().map(|exit| exit.describe()).collect::<Vec<_>>().join(", ")
+    };
+
+    if let Some(place) = state
+        .visited
+        .iter()
+        .filter(|place| place.as_str() != here)
+        .find(|place| lower.contains(&place.to_lowercase()))
+    {
+        if exits.iter().any(|exit| exit.to == *place) {
+            return None;
+        }
+        return Some(format!(
+            "Travel check: there is no known route from {here} directly to {place}. \
+             Exits from {here}: {known}. Keep geography consistent; reaching {place} \
+             needs a route through known places unless the story reveals a new path."
+        ));
+    }
+
+    let direction = words.iter().find(|word| DIRECTIONS.contains(word))?;
+    // Exits without a direction say nothing about which ways are open.
+    let mut directions = exits.iter().filter_map(|exit| exit.direction.as_deref()).peekable();
+    if directions.peek().is_none() || directions.any(|known| known.eq_ignore_ascii_case(direction))
+    {
+        return None;
+    }
+    Some(format!(
+        "Travel check: no known exit leads {direction} from {here}. Known exits: {known}. \
+         Only open a new way {direction} if it fits what has been described."
+    ))
+}
+
+/// Renders known locations as an ASCII tree rooted at the first place visited, with
+/// connections that do not fit the tree listed underneath. The current location is starred.
+pub(crate) fn render_map(state: &GameState) -> Vec<String> {
+    let Some(root) = state.visited.first() else {
+        return vec!["No locations yet.".to_string()];
+    };
+    let mut lines = Vec::new();
+    let mut placed = BTreeSet::new();
+    let mut tree_exits = BTreeSet::new();
+    placed.insert(root.as_str());
+    lines.push(label(state, root));
+    draw_children(state, root, "", &mut placed, &mut tree_exits, &mut lines);
+
+    // Places reached only by /set location have no exit into the tree.
+    for place in &state.visited {
+        if placed.insert(place.as_str()) {
+            lines.push(label(state, place));
+            draw_children(state, place, "", &mut placed, &mut tree_exits, &mut lines);
+        }
+    }
+
+    let extra: Vec<String> = state
+        .exits
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !tree_exits.contains(i))
+        .map(|(_, exit)| format!("{} {}", exit.from, exit.describe()))
+        .collect();
+    if !extra.is_empty() {
+        lines.push(String::new());
+        lines.push("Also:".to_string());
+        lines.extend(extra);
+    }
+    lines
+}
+
+fn draw_children<'a>(
+    state: &'a GameState,
+    place: &'a str,
+    prefix: &str,
+    placed: &mut BTreeSet<&'a str>,
+    tree_exits: &mut BTreeSet<usize>,
+    lines: &mut Vec<String>,
+) {
+    // Walk exits in both directions so places reached one-way still hang off the tree.
+    let mut children: Vec<(usize, &str)> = Vec::new();
+    for (i, exit) in state.exits.iter().enumerate() {
+        let next = if exit.from == place {
+            exit.to.as_str()
+        } else if exit.to == place {
+            exit.from.as_str()
+        } else {
+            continue;
+        };
+        if placed.insert(next) {
+            children.push((i, next));
+            tree_exits.insert(i);
+        }
+    }
+    for (n, (i, next)) in children.iter().enumerate() {
+        let last = n + 1 == children.len();
+        let exit = &state.exits[*i];
+        let arrow = match (&exit.direction, exit.from == place) {
+            (Some(direction), true) => format!("{direction}: "),
+            (_, true) => String::new(),
+            (_, false) => "<- ".to_string(),
+        };
+        let branch = if last { "`-- " } else { "|-- " };
+        lines.push(format!("{prefix}{branch}{arrow}{}", label(state, next)));
+        let child_prefix = format!("{prefix}{}", if last { "    " } else { "|   " });
+        draw_children(state, next, &child_prefix, placed, tree_exits, lines);
+    }
+}
+
+fn label(state: &GameState, place: &str) -> String {
+    if place == state.location {
+        format!("[{place}] *")
+    } else {
+        format!("[{place}]")
+    }
+}
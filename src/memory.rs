@@ -0,0 +1,91 @@
+use serde_json::{json, Value};
+
+use crate::api::Narrator;
+
+/// Turns this recent are still in history, so recalling them would only repeat context.
+const RECENT_TURNS: u32 = 6;
+/// Moments less similar than this to the player's input are not worth the tokens.
+const MIN_SIMILARITY: f32 = 0.25;
+
+/// One embedded exchange.
+#[derive(Clone)]
+pub(crate) struct MemoryEntry {
+    pub(crate) turn: u32,
+    pub(crate) text: String,
+    vector: Vec<f32>,
+}
+
+/// Embedded past turns, kept locally and searched by cosine similarity.
+#[derive(Clone, Default)]
+pub(crate) struct Memory {
+    entries: Vec<MemoryEntry>,
+}
+
+impl Memory {
+    pub(crate) fn add(&mut self, turn: u32, text: String, vector: Vec<f32>) {
+        if !vector.is_empty() {
+            self.entries.push(MemoryEntry { turn, text, vector });
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The `k` older moments most similar to `query`, best first.
+    pub(crate) fn recall(&self, query: &[f32], turn: u32, k: usize) -> Vec<&MemoryEntry> {
+        let mut scored: Vec<(f32, &MemoryEntry)> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.turn.saturating_add(RECENT_TURNS) <= turn)
+            .map(|entry| (cosine(query, &entry.vector), entry))
+            .filter(|(score, _)| *score >= MIN_SIMILARITY)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.into_iter().take(k).map(|(_, entry)| entry).collect()
+    }
+
+    /// Embeds `input` and builds a system item recalling relevant older moments.
+    /// Returns `None` when nothing qualifies or the embedding request fails.
+    pub(crate) fn recall_item(
+        &self,
+        narrator: &dyn Narrator,
+        input: &str,
+        turn: u32,
+        k: usize,
+    ) -> Option<Value> {
+        let eligible = self
+            .entries
+            .iter()
+            .any(|entry| entry.turn.saturating_add(RECENT_TURNS) <= turn);
+        if !eligible || k == 0 || input.trim().is_empty() {
+            return None;
+        }
+        let query = narrator.embed(input).ok()?;
+        let moments = self.recall(&query, turn, k);
+        if moments.is_empty() {
+            return None;
+        }
+        let lines: Vec<String> = moments
+            .iter()
+            .map(|entry| format!("[turn {}] {}", entry.turn, entry.text))
+            .collect();
+        Some(json!({
+            "role": "system",
+            "content": format!(
+                "Earlier moments that may be relevant (for continuity only):\n{}",
+                lines.join("\n")
+            )
+        }))
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denom = norm(a) * norm(b);
+    if denom == 0.0 { 0.0 } else { dot / denom }
+}
@@ -0,0 +1,160 @@
+use std::cmp::Reverse;
+
+/// A slash command as listed by `/help` and the Ctrl+P palette.
+pub(crate) struct CommandInfo {
+    pub(crate) usage: &'static str,
+    pub(crate) help: &'static str,
+}
+
+const fn command(usage: &'static str, help: &'static str) -> CommandInfo {
+    CommandInfo { usage, help }
+}
+
+pub(crate) static COMMANDS: &[CommandInfo] = &[
+    command("/new", "Start a new game from the scenario picker."),
+    command("/scenario load <path>", "Load a TOML scenario file and start it."),
+    command("/quit", "Leave the game, offering to export unsaved progress."),
+    command("/tab new", "Start another game in a new tab."),
+    command("/tab close", "Close this tab."),
+    command("/tab next", "Switch to the next tab."),
+    command("/tab <n>", "Switch to tab n."),
+    command("/help", "List every command in the log."),
+    command("/keys", "List the key bindings."),
+    command("/retry", "Regenerate the last reply."),
+    command("/edit", "Rewrite the last reply."),
+    command("/prompt", "View and edit the system prompt for this game."),
+    command("/choices on|off", "Ask for three numbered choices each turn."),
+    command("/fork <name>", "Branch the story into a parallel timeline."),
+    command("/branches", "Pick a timeline to switch to."),
+    command("/switch <name>", "Switch to another timeline."),
+    command("/stats", "Show session statistics."),
+    command("/chapter", "Show the current chapter."),
+    command("/chapter end", "Summarize and close the current chapter."),
+    command("/end", "Bring the story to an epilogue."),
+    command("/read", "Re-read the whole story full screen."),
+    command("/export", "Write the story to a Markdown file."),
+    command("/achievements", "List achievements and which are unlocked."),
+    command("/model <name>", "Switch the narration model."),
+    command("/models", "Pick a model from the provider's list."),
+    command("/compose", "Type an action at the terminal's own prompt (for IME input)."),
+    command("/settings", "Change model, theme, timeout, scene pane and difficulty."),
+    command("/gallery", "Browse every scene drawn this game and export them."),
+    command("/wiki", "Browse people, places and items met so far."),
+    command("/tasks", "List background tasks."),
+    command("/cancel <id>", "Cancel a background task."),
+    command("/sheet", "Show the character sheet."),
+    command("/sheet name <name>", "Rename the character."),
+    command("/sheet set <stat> <value>", "Set an attribute or skill."),
+    command("/sheet remove <skill>", "Remove a skill."),
+    command("/roll <dice>", "Roll dice the narrator must honor, e.g. 2d6+3."),
+    command("/quest add <title>: <objective>", "Add a quest."),
+    command("/quest complete <title|#>", "Mark a quest completed."),
+    command("/quest list", "List quests."),
+    command("/lore add <fact>", "Pin a world fact the narrator must keep."),
+    command("/lore list", "List pinned lore."),
+    command("/lore remove <#>", "Unpin a lore fact."),
+    command("/map", "Toggle the map pane."),
+    command("/panel inventory|quests|npcs|flags", "Pick what the side panel lists."),
+    command("/scene", "Redraw the scene art in a different composition."),
+    command("/scene image", "Save an illustration of the scene as a PNG."),
+    command("/scene style <name>", "Draw scene art as classic, dense, minimal, box or banner."),
+    command("/scene on|off", "Show or hide the Scene pane and its art requests."),
+    command("/goto turn <n>", "Scroll the story to a turn."),
+    command("/copy last", "Copy the latest narration to the clipboard."),
+    command("/copy turn <n>", "Copy a turn's narration to the clipboard."),
+    command("/turns", "Toggle turn numbers beside the story."),
+    command("/filter <speaker|off>", "Show only Narrator, You, System or one NPC's lines."),
+    command("/theme <name>", "Switch the color theme."),
+    command("/shop", "List what is for sale here."),
+    command("/buy <item>", "Buy an offered item."),
+    command("/craft <item> + <item>", "Combine two carried items."),
+    command("/inspect <item>", "Describe a carried or equipped item."),
+    command("/equip [slot] <item>", "Equip an item as weapon, armor or trinket."),
+    command("/unequip <slot|item>", "Return an equipped item to the inventory."),
+    command("/set timeout <secs>", "Set the request timeout."),
+    command("/set connect-timeout <secs>", "Set the connect timeout."),
+    command("/set max-tokens <n>", "Set the reply token limit."),
+    command("/set effort <level>", "Set reasoning effort: minimal, low, medium or high."),
+    command("/set temperature <t>", "Set sampling temperature, 0-2 or default."),
+    command("/set verbosity <level>", "Set verbosity: low, medium, high or default."),
+    command("/set difficulty <level>", "Set difficulty: easy, normal or brutal."),
+    command("/set pace <minutes>", "Set in-game minutes per turn."),
+    command("/set typewriter <chars|off>", "Set how fast replies type out, per 200ms."),
+    command("/set location <name>", "Move the player."),
+    command("/add item <name>", "Add an item to the inventory."),
+    command("/remove item <name>", "Remove an item from the inventory."),
+    command("/flag <name>[=<value>]", "Set a story flag."),
+    command("/unflag <name>", "Clear a story flag."),
+];
+
+impl CommandInfo {
+    /// What Enter puts in the input: the command up to its first argument.
+    pub(crate) fn stem(&self) -> &'static str {
+        match self.usage.find(['<', '[']) {
+            Some(i) => &self.usage[..i],
+            None if self.usage.contains('|') => {
+                self.usage.rfind(' ').map_or(self.usage, |i| &self.usage[..=i])
+            }
+            None => self.usage,
+        }
+    }
+
+    /// Whether the command runs as is, without arguments.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.stem() == self.usage
+    }
+}
+
+/// Command list overlay opened with Ctrl+P, filtered by a fuzzy query.
+#[derive(Default)]
+pub(crate) struct CommandPalette {
+    pub(crate) query: String,
+    pub(crate) selected: usize,
+}
+
+impl CommandPalette {
+    /// Commands matching the query, best match first.
+    pub(crate) fn filtered(&self) -> Vec<&'static CommandInfo> {
+        let query = self.query.trim().to_lowercase();
+        if query.is_empty() {
+            return COMMANDS.iter().collect();
+        }
+        let mut scored: Vec<(i32, &CommandInfo)> = COMMANDS
+            .iter()
+            .filter_map(|command| {
+                let usage = fuzzy_score(&query, &command.usage.to_lowercase());
+                let help = fuzzy_score(&query, &command.help.to_lowercase()).map(|s| s - 50);
+                Some((usage.max(help)?, command))
+            })
+            .collect();
+        // Stable, so equal scores keep the table's order.
+        scored.sort_by_key(|(score, _)| Reverse(*score));
+        scored.into_iter().map(|(_, command)| command).collect()
+    }
+
+    pub(crate) fn selected_command(&self) -> Option<&'static CommandInfo> {
+        self.filtered().get(self.selected).copied()
+    }
+}
+
+/// Scores `text` for containing `query` as a subsequence; consecutive and
+/// word-initial matches score higher. `None` when it does not match.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let mut score = 0;
+    let mut chars = text.char_indices();
+    let mut last: Option<usize> = None;
+    for wanted in query.chars().filter(|ch| !ch.is_whitespace()) {
+        let (index, _) = chars.by_ref().find(|(_, ch)| *ch == wanted)?;
+        score += 1;
+        if last.is_some_and(|last| last + 1 == index) {
+            score += 3;
+        }
+        let previous = text[..index].chars().next_back();
+        if previous.is_none_or(|ch| ch == ' ' || ch == '/' || ch == '-') {
+            score += 2;
+        }
+        last = Some(index);
+    }
+    // Shorter entries win ties, so `/map` ranks above `/models` for "m".
+    Some(score * 100 - text.len() as i32)
+}
@@ -0,0 +1,16 @@
+use serde::Serialize;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum QuestStatus {
+    Active,
+    Completed,
+}
+
+/// A goal tracked in the quest log, added with `/quest add` or proposed by the model.
+#[derive(Clone, Serialize)]
+pub(crate) struct Quest {
+    pub(crate) title: String,
+    pub(crate) objective: String,
+    pub(crate) status: QuestStatus,
+}
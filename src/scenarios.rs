@@ -0,0 +1,247 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::app::GameState;
+use crate::encounters::EncounterTable;
+use crate::error::{Error, Result};
+use crate::flags::parse_assignment;
+use crate::items::Item;
+
+/// A starting setup offered by the `/new` picker, built in or loaded from a TOML file.
+#[derive(Clone)]
+pub(crate) struct Scenario {
+    pub(crate) name: String,
+    /// One line shown in the picker.
+    pub(crate) description: String,
+    /// Sent to the narrator every turn ahead of the history.
+    pub(crate) prompt: String,
+    /// Blank keeps the "Unknown" starting location.
+    pub(crate) location: String,
+    pub(crate) inventory: Vec<Item>,
+    /// Starting gold and health; `None` keeps the game defaults.
+    pub(crate) gold: Option<u32>,
+    pub(crate) hp: Option<i32>,
+    /// Shown in the log when the game starts.
+    pub(crate) opening: String,
+    pub(crate) npcs: Vec<Npc>,
+    /// Replaces the configured encounter table while this scenario is played.
+    pub(crate) encounters: Option<EncounterTable>,
+    /// The story ends once any one of these is met.
+    pub(crate) win: Vec<WinCondition>,
+}
+
+/// A character the scenario introduces, described to the narrator from the first turn.
+#[derive(Clone, Deserialize)]
+pub(crate) struct Npc {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) description: String,
+    /// Starting attitude towards the player, -10 to 10.
+    #[serde(default)]
+    pub(crate) affinity: i32,
+}
+
+/// Requirements that together win the scenario; unset fields are not checked.
+#[derive(Clone, Deserialize)]
+pub(crate) struct WinCondition {
+    /// Shown in the log when the condition is met.
+    pub(crate) description: String,
+    /// `name` or `name=value`, as for `/flag`.
+    pub(crate) flag: Option<String>,
+    pub(crate) item: Option<String>,
+    pub(crate) location: Option<String>,
+}
+
+impl WinCondition {
+    /// Carried or equipped items both count towards `item`.
+    pub(crate) fn is_met(&self, state: &GameState) -> bool {
+        let flag_set = |flag: &str| {
+            parse_assignment(flag)
+                .is_some_and(|(name, value)| state.flags.get(&name) == Some(&value))
+        };
+        let has_item =
+            |item: &str| state.item(item).is_some() || state.equipment.find(item).is_some();
+        let is_at = |place: &str| state.location.eq_ignore_ascii_case(place.trim());
+        self.flag.as_deref().is_none_or(flag_set)
+            && self.item.as_deref().is_none_or(has_item)
+            && self.location.as_deref().is_none_or(is_at)
+    }
+
+    /// Why the condition could never be checked meaningfully, if it is malformed.
+    fn problem(&self) -> Option<&'static str> {
+        let blank = |field: &Option<String>| field.as_deref().is_some_and(|s| s.trim().is_empty());
+        if self.flag.is_none() && self.item.is_none() && self.location.is_none() {
+            Some("sets none of flag, item or location")
+        } else if self.flag.as_deref().is_some_and(|flag| parse_assignment(flag).is_none()) {
+            Some("has a flag that is not `name` or `name=value`")
+        } else if blank(&self.item) || blank(&self.location) {
+            Some("has a blank item or location")
+        } else {
+            None
+        }
+    }
+}
+
+/// On-disk layout of a scenario file.
+#[derive(Deserialize)]
+struct ScenarioFile {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    prompt: String,
+    #[serde(default)]
+    opening: String,
+    #[serde(default)]
+    start: StartFile,
+    #[serde(default)]
+    npcs: Vec<Npc>,
+    encounters: Option<EncounterTable>,
+    #[serde(default)]
+    win: Vec<WinCondition>,
+}
+
+#[derive(Default, Deserialize)]
+struct StartFile {
+    location: Option<String>,
+    #[serde(default)]
+    inventory: Vec<StartItem>,
+    gold: Option<u32>,
+    hp: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct StartItem {
+    name: String,
+    #[serde(default = "default_qty")]
+    qty: u32,
+}
+
+fn default_qty() -> u32 {
+    1
+}
+
+impl Scenario {
+    fn new(
+        name: &str,
+        description: &str,
+        prompt: &str,
+        location: &str,
+        inventory: &[(&str, u32)],
+        opening: &str,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            prompt: prompt.to_string(),
+            location: location.to_string(),
+            inventory: inventory
+                .iter()
+                .map(|(item, qty)| Item {
+                    qty: *qty,
+                    ..Item::new(item)
+                })
+                .collect(),
+            gold: None,
+            hp: None,
+            opening: opening.to_string(),
+            npcs: Vec::new(),
+            encounters: None,
+            win: Vec::new(),
+        }
+    }
+
+    /// Reads a scenario from a TOML file.
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).map_err(|err| {
+            Error::Config(format!("could not read scenario {}: {err}", path.display()))
+        })?;
+        let file: ScenarioFile = toml::from_str(&contents).map_err(|err| {
+            Error::Config(format!("invalid scenario {}: {}", path.display(), err.message()))
+        })?;
+        if file.name.trim().is_empty() {
+            return Err(Error::Config(format!("scenario {} needs a name", path.display())));
+        }
+        for (i, win) in file.win.iter().enumerate() {
+            if let Some(problem) = win.problem() {
+                return Err(Error::Config(format!(
+                    "win condition {} in scenario {} {problem}",
+                    i + 1,
+                    path.display()
+                )));
+            }
+        }
+        let inventory = file
+            .start
+            .inventory
+            .iter()
+            .filter(|item| !item.name.trim().is_empty() && item.qty > 0)
+            .map(|item| Item {
+                qty: item.qty,
+                ..Item::new(&item.name)
+            })
+            .collect();
+        Ok(Self {
+            name: file.name.trim().to_string(),
+            description: file.description,
+            prompt: file.prompt.trim().to_string(),
+            location: file.start.location.unwrap_or_default(),
+            inventory,
+            gold: file.start.gold,
+            hp: file.start.hp.filter(|hp| *hp > 0),
+            opening: file.opening.trim().to_string(),
+            npcs: file.npcs,
+            encounters: file.encounters,
+            win: file.win,
+        })
+    }
+
+    /// The scenario prompt followed by the NPC roster, sent to the narrator every turn.
+    pub(crate) fn premise(&self) -> String {
+        let mut premise = self.prompt.clone();
+        if !self.npcs.is_empty() {
+            let roster: Vec<String> = self
+                .npcs
+                .iter()
+                .map(|npc| match npc.description.trim() {
+                    "" => npc.name.clone(),
+                    description => format!("{}: {description}", npc.name),
+                })
+                .collect();
+            premise.push_str(&format!("\nCharacters in this story:\n{}", roster.join("\n")));
+        }
+        premise.trim().to_string()
+    }
+}
+
+/// The scenarios shipped with the game; the picker adds a blank "Custom" row after them.
+pub(crate) fn builtin() -> Vec<Scenario> {
+    vec![
+        Scenario::new(
+            "Fantasy dungeon",
+            "Delve beneath a ruined keep for a lost relic.",
+            "Setting: high fantasy. The player is a sellsword hired to recover the Ember Crown from the flooded vaults beneath Greyholt Keep. Traps, undead and rival treasure hunters wait below.",
+            "Greyholt Keep gatehouse",
+            &[("torch", 3), ("rope", 1), ("short sword", 1)],
+            "Rain hammers the broken gatehouse of Greyholt Keep. Somewhere below, the Ember Crown waits.",
+        ),
+        Scenario::new(
+            "Space station",
+            "Wake alone on a station that has gone quiet.",
+            "Setting: hard science fiction. The player is a maintenance engineer waking from cryosleep on Meridian Station, a research outpost orbiting a gas giant. The crew is missing, power is failing and something is moving in the vents.",
+            "Meridian Station cryo bay",
+            &[("multitool", 1), ("access card", 1), ("ration bar", 2)],
+            "Frost peels from the cryo pod glass. The bay lights flicker amber; nobody answers the intercom.",
+        ),
+        Scenario::new(
+            "Mystery",
+            "Solve a murder at a snowbound manor.",
+            "Setting: 1920s detective mystery. The player is a private investigator stranded by a blizzard at Ashcombe Manor, where the host was found dead in a locked study. Every guest has a secret; clues must be earned, never handed over.",
+            "Ashcombe Manor entrance hall",
+            &[("notebook", 1), ("magnifying glass", 1), ("revolver", 1)],
+            "Snow seals the drive behind you. Upstairs, the study door is still locked from the inside.",
+        ),
+    ]
+}
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{SceneStyle, DATA_DIR};
+use crate::error::Result;
+
+const SCENES_FILE: &str = "scenes.json";
+
+/// Generated text art by world, place and time of day, persisted in `.story-gen/scenes.json` so
+/// returning somewhere reuses its art instead of asking the model again.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SceneCache {
+    pub(crate) scenes: BTreeMap<String, String>,
+}
+
+/// e.g. `haunted manor: old mill (evening, dense)`, so locations match whatever their
+/// case but places of the same name in different worlds do not.
+pub(crate) fn key(world: &str, location: &str, part_of_day: &str, style: SceneStyle) -> String {
+    format!(
+        "{}: {} ({part_of_day}, {})",
+        world.trim().to_lowercase(),
+        location.trim().to_lowercase(),
+        style.label()
+    )
+}
+
+impl SceneCache {
+    fn path() -> PathBuf {
+        Path::new(DATA_DIR).join(SCENES_FILE)
+    }
+
+    /// Loads cached scenes; a missing or unreadable file starts empty.
+    pub(crate) fn load() -> Self {
+        fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        fs::create_dir_all(DATA_DIR)?;
+        let mut contents = serde_json::to_string_pretty(self)?;
+        contents.push('\n');
+        fs::write(Self::path(), contents)?;
+        Ok(())
+    }
+
+    pub(crate) fn get(key: &str) -> Option<String> {
+        Self::load().scenes.remove(key)
+    }
+
+    /// Adds `art` under `key`, rereading the file first so other tabs' scenes are kept.
+    pub(crate) fn store(key: String, art: String) -> Result<()> {
+        let mut cache = Self::load();
+        cache.scenes.insert(key, art);
+        cache.save()
+    }
+}
@@ -0,0 +1,357 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::hash::stable_hash;
+use crate::weather::Weather;
+
+const WIDTH: usize = 56;
+const HEIGHT: usize = 14;
+/// Row of the horizon in outdoor scenes.
+const HORIZON: usize = 7;
+
+const TREE: [&str; 4] = ["  ^  ", " /^\\ ", "/^^^\\", "  |  "];
+const SAPLING: [&str; 3] = [" ^ ", "/^\\", " | "];
+const HOUSE: [&str; 5] = ["  /\\  ", " /  \\ ", "/____\\", "| [] |", "|_||_|"];
+const TOWER: [&str; 5] = [" /\\ ", " || ", "|[]|", "|  |", "|_||"];
+const BOAT: [&str; 3] = ["  |\\ ", "  |_\\", "\\____/"];
+const CLOUD: [&str; 3] = [" .-~~-. ", "(      )", " `-~~-' "];
+const SUN: [&str; 3] = ["\\ | /", "- O -", "/ | \\"];
+const LOW_SUN: [&str; 2] = ["\\ | /", "-( )-"];
+const BOLT: [&str; 4] = [" /", "/_", " /", "/"];
+const DOOR: [&str; 4] = ["+--+", "|  |", "| o|", "|  |"];
+const TABLE: [&str; 3] = ["   i   ", "_______", "|     |"];
+
+/// The kind of place a location's name suggests.
+#[derive(Clone, Copy)]
+enum Terrain {
+    Plains,
+    Forest,
+    Mountains,
+    Coast,
+    Town,
+    Cave,
+    Room,
+}
+
+impl Terrain {
+    /// Guessed from words in the name; anything unrecognised is open country.
+    fn of(location: &str) -> Self {
+        let name = location.to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|word| name.contains(word));
+        if has(&["cave", "cavern", "dungeon", "crypt", "tunnel", "mine", "tomb", "sewer"]) {
+            Self::Cave
+        } else if has(&[
+            "room", "tavern", "inn", "hall", "house", "shop", "chamber", "library", "cellar",
+            "kitchen", "temple", "chapel", "study", "hut", "cabin", "tower", "castle", "keep",
+        ]) {
+            Self::Room
+        } else if has(&["forest", "wood", "grove", "jungle", "thicket", "glade"]) {
+            Self::Forest
+        } else if has(&["mountain", "peak", "cliff", "ridge", "summit", "hill", "crag"]) {
+            Self::Mountains
+        } else if has(&[
+            "sea", "coast", "shore", "beach", "harbor", "harbour", "dock", "lake", "river", "bay",
+        ]) {
+            Self::Coast
+        } else if has(&["town", "village", "city", "street", "square", "market", "alley"]) {
+            Self::Town
+        } else {
+            Self::Plains
+        }
+    }
+}
+
+/// Text art for a place drawn without the API, for when scene requests fail or before
+/// the first one. The same place always gets the same layout.
+pub(crate) fn draw(location: &str, part_of_day: &str, weather: Weather) -> String {
+    let mut canvas = Canvas::new(location);
+    match Terrain::of(location) {
+        Terrain::Cave => canvas.cave(),
+        Terrain::Room => canvas.room(part_of_day),
+        terrain => {
+            canvas.sky(part_of_day, weather);
+            match terrain {
+                Terrain::Forest => canvas.forest(),
+                Terrain::Mountains => canvas.mountains(),
+                Terrain::Coast => canvas.coast(),
+                Terrain::Town => canvas.town(),
+                _ => canvas.plains(),
+            }
+            canvas.weather(weather);
+        }
+    }
+    canvas.render()
+}
+
+struct Canvas {
+    cells: Vec<Vec<char>>,
+    rng: StdRng,
+}
+
+impl Canvas {
+    fn new(location: &str) -> Self {
+        Self {
+            cells: vec![vec![' '; WIDTH]; HEIGHT],
+            rng: StdRng::seed_from_u64(stable_hash(location)),
+        }
+    }
+
+    fn put(&mut self, x: usize, y: usize, ch: char) {
+        if let Some(cell) = self.cells.get_mut(y).and_then(|row| row.get_mut(x)) {
+            *cell = ch;
+        }
+    }
+
+    fn is_blank(&self, x: usize, y: usize) -> bool {
+        self.cells.get(y).and_then(|row| row.get(x)) == Some(&' ')
+    }
+
+    /// Draws `rows` with its top-left at `x`, `y`; spaces in the sprite are see-through.
+    fn sprite(&mut self, x: usize, y: usize, rows: &[&str]) {
+        for (dy, row) in rows.iter().enumerate() {
+            for (dx, ch) in row.chars().enumerate().filter(|&(_, ch)| ch != ' ') {
+                self.put(x + dx, y + dy, ch);
+            }
+        }
+    }
+
+    /// Like `sprite`, but clears the sprite's box first so it hides what is behind it.
+    fn solid(&mut self, x: usize, y: usize, rows: &[&str]) {
+        for (dy, row) in rows.iter().enumerate() {
+            for dx in 0..row.chars().count() {
+                self.put(x + dx, y + dy, ' ');
+            }
+        }
+        self.sprite(x, y, rows);
+    }
+
+    /// Sprinkles `count` of `chars` over blank cells in `rows`.
+    fn scatter(&mut self, rows: std::ops::Range<usize>, count: usize, chars: &[char]) {
+        for _ in 0..count {
+            let x = self.rng.random_range(0..WIDTH);
+            let y = self.rng.random_range(rows.clone());
+            let ch = chars[self.rng.random_range(0..chars.len())];
+            if self.is_blank(x, y) {
+                self.put(x, y, ch);
+            }
+        }
+    }
+
+    /// A wandering skyline around `base`, never more than `amplitude` rows off it.
+    fn ridge(&mut self, base: usize, amplitude: usize) {
+        let mut y = base;
+        for x in 0..WIDTH {
+            match self.rng.random_range(0..4) {
+                0 if y > base - amplitude => {
+                    self.put(x, y, '/');
+                    y -= 1;
+                }
+                1 if y < base + amplitude => {
+                    y += 1;
+                    self.put(x, y, '\\');
+                }
+                _ => self.put(x, y, '_'),
+            }
+        }
+    }
+
+    fn sky(&mut self, part_of_day: &str, weather: Weather) {
+        if part_of_day == "night" {
+            self.scatter(0..HORIZON - 1, 16, &['.', '.', '*', '+']);
+            let x = self.rng.random_range(2..WIDTH - 2);
+            self.put(x, 1, 'C');
+            return;
+        }
+        if weather != Weather::Clear {
+            return;
+        }
+        match part_of_day {
+            "dawn" => {
+                let x = 2 + self.rng.random_range(0..10);
+                self.sprite(x, HORIZON - 2, &LOW_SUN);
+            }
+            "evening" => {
+                let x = WIDTH - 8 - self.rng.random_range(0..10);
+                self.sprite(x, HORIZON - 2, &LOW_SUN);
+            }
+            _ => {
+                let x = self.rng.random_range(4..WIDTH - 8);
+                self.sprite(x, 0, &SUN);
+            }
+        }
+    }
+
+    fn weather(&mut self, weather: Weather) {
+        if matches!(weather, Weather::Cloudy | Weather::Rain | Weather::Storm) {
+            for _ in 0..self.rng.random_range(2..5) {
+                let x = self.rng.random_range(0..WIDTH - 8);
+                let y = self.rng.random_range(0..3);
+                self.solid(x, y, &CLOUD);
+            }
+        }
+        match weather {
+            Weather::Fog => {
+                for y in (HORIZON - 3..HEIGHT).step_by(2) {
+                    for x in 0..WIDTH {
+                        if self.is_blank(x, y) && self.rng.random_bool(0.4) {
+                            self.put(x, y, '~');
+                        }
+                    }
+                }
+            }
+            Weather::Rain | Weather::Storm => {
+                self.scatter(3..HEIGHT, 60, &['/', '\'']);
+                if weather == Weather::Storm {
+                    let x = self.rng.random_range(4..WIDTH - 4);
+                    self.sprite(x, 3, &BOLT);
+                }
+            }
+            Weather::Clear | Weather::Cloudy => {}
+        }
+    }
+
+    fn plains(&mut self) {
+        self.ridge(HORIZON, 1);
+        self.scatter(HORIZON + 2..HEIGHT, 40, &[',', '.', '"', '\'']);
+        for _ in 0..2 {
+            let x = self.rng.random_range(0..WIDTH - 3);
+            let y = self.rng.random_range(HORIZON + 3..HEIGHT);
+            self.solid(x, y, &["(@)"]);
+        }
+    }
+
+    fn forest(&mut self) {
+        self.ridge(HORIZON + 1, 1);
+        for _ in 0..10 {
+            let x = self.rng.random_range(0..WIDTH - 3);
+            self.solid(x, HORIZON - 1, &SAPLING);
+        }
+        self.scatter(HORIZON + 2..HEIGHT, 25, &[',', '.', '"']);
+        for _ in 0..5 {
+            let x = self.rng.random_range(0..WIDTH - 5);
+            let y = self.rng.random_range(HORIZON + 2..HEIGHT - 3);
+            self.solid(x, y, &TREE);
+        }
+    }
+
+    fn mountains(&mut self) {
+        let base = HORIZON + 2;
+        for _ in 0..self.rng.random_range(3..6) {
+            let center = self.rng.random_range(4..WIDTH - 4);
+            let peak = self.rng.random_range(1..HORIZON);
+            for y in peak..=base {
+                let offset = y - peak;
+                for x in center.saturating_sub(offset)..=center + 1 + offset {
+                    self.put(x, y, ' ');
+                }
+                if let Some(left) = center.checked_sub(offset) {
+                    self.put(left, y, '/');
+                }
+                self.put(center + 1 + offset, y, '\\');
+                // Snow on the first rows below the peak.
+                if (1..3).contains(&offset) {
+                    for x in center + 1 - offset..=center + offset {
+                        self.put(x, y, '^');
+                    }
+                }
+            }
+        }
+        self.scatter(base + 1..HEIGHT, 30, &['.', ',', 'o']);
+    }
+
+    fn coast(&mut self) {
+        for y in HORIZON..HORIZON + 4 {
+            for x in 0..WIDTH {
+                if self.rng.random_bool(0.5) {
+                    let ch = if self.rng.random_bool(0.7) { '~' } else { '-' };
+                    self.put(x, y, ch);
+                }
+            }
+        }
+        let x = self.rng.random_range(4..WIDTH - 10);
+        self.solid(x, HORIZON - 1, &BOAT);
+        self.ridge(HORIZON + 5, 1);
+        self.scatter(HORIZON + 6..HEIGHT, 30, &['.', ',', '.', '@']);
+    }
+
+    fn town(&mut self) {
+        let street = HORIZON + 4;
+        let mut x = self.rng.random_range(0..4);
+        while x + 6 < WIDTH {
+            let (sprite, width) = if self.rng.random_bool(0.25) {
+                (&TOWER[..], 4)
+            } else {
+                (&HOUSE[..], 6)
+            };
+            self.solid(x, street - sprite.len(), sprite);
+            x += width + self.rng.random_range(1..5);
+        }
+        for x in 0..WIDTH {
+            self.put(x, street, '=');
+        }
+        self.scatter(street + 1..HEIGHT, 30, &['.', ':', '\'']);
+    }
+
+    fn cave(&mut self) {
+        for x in 0..WIDTH {
+            let ceiling = self.rng.random_range(1..4);
+            for y in 0..ceiling {
+                self.put(x, y, '#');
+            }
+            if self.rng.random_bool(0.15) {
+                self.put(x, ceiling, 'V');
+            }
+            let floor = HEIGHT - self.rng.random_range(1..3);
+            for y in floor..HEIGHT {
+                self.put(x, y, '#');
+            }
+        }
+        self.scatter(HEIGHT - 4..HEIGHT - 2, 12, &['.', 'o', '.']);
+        let x = self.rng.random_range(2..WIDTH - 2);
+        self.sprite(x, HORIZON - 2, &["(", ")", "|"]);
+    }
+
+    fn room(&mut self, part_of_day: &str) {
+        let (left, right, top, bottom) = (8, WIDTH - 9, 2, HEIGHT - 5);
+        for x in left..=right {
+            self.put(x, top, '-');
+            self.put(x, bottom, '_');
+        }
+        for y in top + 1..=bottom {
+            self.put(left, y, '|');
+            self.put(right, y, '|');
+        }
+        self.put(left, top, '+');
+        self.put(right, top, '+');
+        // Ceiling and floor edges running out to the frame.
+        for step in 1..=top {
+            self.put(left - step, top - step, '\\');
+            self.put(right + step, top - step, '/');
+        }
+        for step in 1..HEIGHT - bottom {
+            self.put(left - step, bottom + step, '/');
+            self.put(right + step, bottom + step, '\\');
+        }
+
+        let door = self.rng.random_range(left + 2..right - 16);
+        self.sprite(door, bottom - 3, &DOOR);
+        let window = self.rng.random_range(door + 6..right - 8);
+        let pane = match part_of_day {
+            "night" => "|* |. |",
+            "dawn" | "evening" => "|--|--|",
+            _ => "|  |  |",
+        };
+        self.sprite(window, top + 2, &["+--+--+", pane, "+--+--+"]);
+        let table = self.rng.random_range(left..right - 6);
+        self.solid(table, bottom + 1, &TABLE);
+    }
+
+    fn render(&self) -> String {
+        let rows: Vec<String> = self
+            .cells
+            .iter()
+            .map(|row| row.iter().collect::<String>().trim_end().to_string())
+            .collect();
+        rows.join("\n")
+    }
+}
@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Appended to the system prompt when the model is asked for schema-constrained JSON.
+pub(crate) const STRUCTURED_PROMPT: &str = r#"
+Reply as JSON matching the provided schema instead of speaker-labelled text:
+- "narration": what the narrator says this turn; empty if only characters speak.
+- "dialogue": character lines in order, each with "speaker" and "text"; the text excludes the label.
+- "state_changes": changes this turn caused, including quests the player took on or resolved, gold gained or lost, and any items offered for sale with prices. Use null, 0 or empty lists when nothing changed.
+- "choices": suggested next actions for the player; leave empty unless told choice mode is on.
+- "story_ended": true only when this turn brings the whole story to a natural conclusion; the game then asks for an epilogue.
+Purchases are handled by the game: when offering goods, list them in "offers" and let the player /buy them; do not move gold yourself for purchases.
+This overrides the formatting rules above: the reply is this JSON object, not plain text, so the rule against JSON does not apply; never prefix lines with speaker labels or repeat a label inside "text", since "speaker" carries it; and leave quotation marks out of dialogue "text". Narration still goes under "narration" rather than a "Narrator" speaker.
+The rules on voice, dialogue, lore, time, exits, relationships, equipment, health and length still apply.
+"#;
+
+/// Appended after `STRUCTURED_PROMPT` while choice mode is on.
+pub(crate) const CHOICES_PROMPT: &str = r#"Choice mode is on: fill "choices" with exactly 3 short, distinct actions the player could take next, phrased as commands (e.g. "Search the cellar"). Do not list them in the narration.
+"#;
+
+pub(crate) const EXTRACTION_PROMPT: &str = r#"You track game state for a text adventure.
+Given the current state and the narrator's latest reply, report only what the reply changed:
+- "location": the player's new location, or null if they did not move.
+- "add_items" / "remove_items": items the player gained or lost.
+- "set_flags" / "clear_flags": short names of notable story events that became true or stopped being true; set a value with "name=value", e.g. "reputation=5".
+- "new_quests": goals the player clearly took on, each with "title" and "objective".
+- "completed_quests": titles of existing quests the reply resolved.
+- "gold_change": gold the player found, earned or lost outside of shop purchases (0 if none).
+- "offers": items a merchant offered for sale in the reply, each with "item" and "price" in gold.
+Reply with a single JSON object with exactly those keys and nothing else.
+"#;
+
+/// A narrator turn returned under the `turn` JSON schema.
+#[derive(Deserialize)]
+pub(crate) struct StructuredTurn {
+    pub(crate) narration: String,
+    pub(crate) dialogue: Vec<DialogueLine>,
+    pub(crate) state_changes: StateChanges,
+    #[serde(default)]
+    pub(crate) choices: Vec<String>,
+    #[serde(default)]
+    pub(crate) story_ended: bool,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DialogueLine {
+    pub(crate) speaker: String,
+    pub(crate) text: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct StateChanges {
+    pub(crate) location: Option<String>,
+    pub(crate) add_items: Vec<String>,
+    pub(crate) remove_items: Vec<String>,
+    pub(crate) set_flags: Vec<String>,
+    pub(crate) clear_flags: Vec<String>,
+    // Defaulted so prompt-only extraction replies that omit quests still parse.
+    #[serde(default)]
+    pub(crate) new_quests: Vec<QuestProposal>,
+    #[serde(default)]
+    pub(crate) completed_quests: Vec<String>,
+    #[serde(default)]
+    pub(crate) gold_change: i32,
+    #[serde(default)]
+    pub(crate) offers: Vec<OfferProposal>,
+}
+
+/// A price as the model reported it; negative prices are dropped when applied.
+#[derive(Deserialize)]
+pub(crate) struct OfferProposal {
+    pub(crate) item: String,
+    pub(crate) price: i64,
+}
+
+/// An item for sale at the current location, bought with `/buy`.
+#[derive(Clone, Serialize)]
+pub(crate) struct Offer {
+    pub(crate) item: String,
+    pub(crate) price: u32,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct QuestProposal {
+    pub(crate) title: String,
+    pub(crate) objective: String,
+}
+
+impl StructuredTurn {
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        serde_json::from_str(text.trim()).ok()
+    }
+
+    /// Speaker-labelled rendering used wherever plain reply text is expected.
+    pub(crate) fn to_labelled_text(&self) -> String {
+        let mut lines = Vec::new();
+        if !self.narration.trim().is_empty() {
+            lines.push(format!("Narrator: {}", self.narration.trim()));
+        }
+        for line in &self.dialogue {
+            lines.push(format!("{}: {}", line.speaker.trim(), line.text.trim()));
+        }
+        lines.join("\n")
+    }
+}
+
+impl StateChanges {
+    /// Parses an extraction reply, tolerating prose or fences around the object.
+    pub(crate) fn parse_lenient(text: &str) -> Option<Self> {
+        let start = text.find('{')?;
+        let end = text.rfind('}')?;
+        serde_json::from_str(text.get(start..=end)?).ok()
+    }
+}
+
+/// `text.format` value requesting a strict `state_changes` schema.
+pub(crate) fn state_changes_format() -> Value {
+    json!({
+        "type": "json_schema",
+        "name": "state_changes",
+        "strict": true,
+        "schema": state_changes_schema()
+    })
+}
+
+/// `text.format` value requesting a strict `turn` schema.
+pub(crate) fn turn_format() -> Value {
+    json!({
+        "type": "json_schema",
+        "name": "turn",
+        "strict": true,
+        "schema": {
+            "type": "object",
+            "additionalProperties": false,
+            "required": ["narration", "dialogue", "state_changes", "choices", "story_ended"],
+            "properties": {
+                "narration": { "type": "string" },
+                "dialogue": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "additionalProperties": false,
+                        "required": ["speaker", "text"],
+                        "properties": {
+                            "speaker": { "type": "string" },
+                            "text": { "type": "string" }
+                        }
+                    }
+                },
+                "state_changes": state_changes_schema(),
+                "choices": { "type": "array", "items": { "type": "string" } },
+                "story_ended": { "type": "boolean" }
+            }
+        }
+    })
+}
+
+fn state_changes_schema() -> Value {
+    let strings = json!({ "type": "array", "items": { "type": "string" } });
+    json!({
+        "type": "object",
+        "additionalProperties": false,
+        "required": [
+            "location",
+            "add_items",
+            "remove_items",
+            "set_flags",
+            "clear_flags",
+            "new_quests",
+            "completed_quests",
+            "gold_change",
+            "offers"
+        ],
+        "properties": {
+            "location": { "type": ["string", "null"] },
+            "add_items": strings,
+            "remove_items": strings,
+            "set_flags": strings,
+            "clear_flags": strings,
+            "new_quests": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "required": ["title", "objective"],
+                    "properties": {
+                        "title": { "type": "string" },
+                        "objective": { "type": "string" }
+                    }
+                }
+            },
+            "completed_quests": strings,
+            "gold_change": { "type": "integer" },
+            "offers": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "required": ["item", "price"],
+                    "properties": {
+                        "item": { "type": "string" },
+                        "price": { "type": "integer" }
+                    }
+                }
+            }
+        }
+    })
+}
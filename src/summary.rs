@@ -0,0 +1,30 @@
+use serde_json::Value;
+
+pub(crate) const SUMMARY_PROMPT: &str = r#"You keep the running summary of a text adventure.
+Merge the existing summary with the transcript of older turns that are about to leave the narrator's context.
+Keep names, places, promises, debts, injuries, items and unresolved threads; drop flavor text.
+Write plain prose in past tense, at most 200 words. Output only the updated summary."#;
+
+/// Flattens history chunks into `role: text` lines for the summarizer.
+/// Tool calls and other non-message items are skipped.
+pub(crate) fn transcript(chunks: &[Vec<Value>]) -> String {
+    let mut lines = Vec::new();
+    for item in chunks.iter().flatten() {
+        let Some(role) = item.get("role").and_then(Value::as_str) else {
+            continue;
+        };
+        let text = match item.get("content") {
+            Some(Value::String(text)) => text.clone(),
+            Some(Value::Array(parts)) => parts
+                .iter()
+                .filter_map(|part| part.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => continue,
+        };
+        if !text.trim().is_empty() {
+            lines.push(format!("{role}: {}", text.trim()));
+        }
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,154 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::api::TurnResult;
+use crate::crafting::Crafted;
+use crate::error::{Error, Result};
+use crate::graphics::Image;
+use crate::structured::StateChanges;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TaskKind {
+    Narration,
+    ModelList,
+    Scene,
+    Extraction,
+    Crafting,
+    Embedding,
+    Summary,
+    Chapter,
+    Illustration,
+    TokenCount,
+}
+
+impl TaskKind {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            TaskKind::Narration => "narration",
+            TaskKind::ModelList => "models",
+            TaskKind::Scene => "scene art",
+            TaskKind::Extraction => "state sync",
+            TaskKind::Crafting => "crafting",
+            TaskKind::Embedding => "memory",
+            TaskKind::Summary => "summary",
+            TaskKind::Chapter => "chapter summary",
+            TaskKind::Illustration => "illustration",
+            TaskKind::TokenCount => "token count",
+        }
+    }
+}
+
+pub(crate) enum TaskOutput {
+    Turn(Box<TurnResult>),
+    Models(Result<Vec<String>>),
+    Scene(Result<String>),
+    SceneImage(Box<Image>),
+    /// Text art drawn after the picture request failed with the error given.
+    SceneFallback(Error, Result<String>),
+    StateChanges(Result<StateChanges>),
+    /// Carries the two ingredients so the result can be applied to the inventory.
+    Crafted(String, String, Result<Crafted>),
+    /// An exchange from `turn` and its embedding, for long-term memory.
+    Embedded(u32, String, Result<Vec<f32>>),
+    Summary(Result<String>),
+    ChapterSummary(Result<String>),
+    /// Where `/scene image` saved its picture.
+    Illustration(Result<PathBuf>),
+    /// A history chunk and its input token count.
+    TokenCount(Vec<Value>, Result<u64>),
+}
+
+pub(crate) struct Task {
+    pub(crate) id: u64,
+    pub(crate) kind: TaskKind,
+    pub(crate) started: Instant,
+    rx: Receiver<TaskOutput>,
+}
+
+impl Task {
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+/// A finished task; `output` is `None` when the worker died without reporting.
+pub(crate) struct Finished {
+    pub(crate) kind: TaskKind,
+    pub(crate) output: Option<TaskOutput>,
+}
+
+/// Background work running on worker threads, polled from the main loop.
+#[derive(Default)]
+pub(crate) struct TaskRegistry {
+    next_id: u64,
+    tasks: Vec<Task>,
+}
+
+impl TaskRegistry {
+    pub(crate) fn spawn<F>(&mut self, kind: TaskKind, work: F) -> u64
+    where
+        F: FnOnce() -> TaskOutput + Send + 'static,
+    {
+        self.next_id += 1;
+        let id = self.next_id;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = tx.send(work());
+        });
+        self.tasks.push(Task {
+            id,
+            kind,
+            started: Instant::now(),
+            rx,
+        });
+        id
+    }
+
+    pub(crate) fn poll(&mut self) -> Vec<Finished> {
+        let mut finished = Vec::new();
+        self.tasks.retain(|task| match task.rx.try_recv() {
+            Ok(output) => {
+                finished.push(Finished {
+                    kind: task.kind,
+                    output: Some(output),
+                });
+                false
+            }
+            Err(TryRecvError::Empty) => true,
+            Err(TryRecvError::Disconnected) => {
+                finished.push(Finished {
+                    kind: task.kind,
+                    output: None,
+                });
+                false
+            }
+        });
+        finished
+    }
+
+    /// Stops tracking a task. The worker thread runs to completion but its result is dropped.
+    pub(crate) fn cancel(&mut self, id: u64) -> Option<TaskKind> {
+        let pos = self.tasks.iter().position(|task| task.id == id)?;
+        Some(self.tasks.remove(pos).kind)
+    }
+
+    pub(crate) fn cancel_kind(&mut self, kind: TaskKind) {
+        self.tasks.retain(|task| task.kind != kind);
+    }
+
+    pub(crate) fn is_running(&self, kind: TaskKind) -> bool {
+        self.tasks.iter().any(|task| task.kind == kind)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+use crate::hash::stable_hash;
+
+/// Colors the UI draws with. User themes go under `themes` in the config file;
+/// any color left out falls back to the default theme's.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Theme {
+    pub(crate) player: Color,
+    pub(crate) narrator: Color,
+    pub(crate) npc: Color,
+    /// Colors handed out to named characters, each keeping the same one; `npc` when empty.
+    pub(crate) speakers: Vec<Color>,
+    pub(crate) system: Color,
+    pub(crate) error: Color,
+    /// Quoted speech inside narration.
+    pub(crate) dialogue: Color,
+    /// Headings, labels and the selection bar in lists.
+    pub(crate) accent: Color,
+    /// Text drawn on the selection bar.
+    pub(crate) selected: Color,
+    /// Hints and secondary text.
+    pub(crate) muted: Color,
+    /// Numbered choices and toasts.
+    pub(crate) highlight: Color,
+    pub(crate) gold: Color,
+    pub(crate) good: Color,
+    pub(crate) warning: Color,
+    pub(crate) bad: Color,
+    pub(crate) cursor: Color,
+    /// Also tell speakers apart by prefix and bold/italic, not by hue alone.
+    pub(crate) marks: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            player: Color::Yellow,
+            narrator: Color::Green,
+            npc: Color::Cyan,
+            speakers: vec![
+                Color::Cyan,
+                Color::LightMagenta,
+                Color::LightBlue,
+                Color::LightYellow,
+                Color::LightRed,
+                Color::LightGreen,
+                Color::Rgb(255, 165, 80),
+                Color::Rgb(180, 140, 255),
+            ],
+            system: Color::Blue,
+            error: Color::Red,
+            dialogue: Color::White,
+            accent: Color::Cyan,
+            selected: Color::Black,
+            muted: Color::DarkGray,
+            highlight: Color::Magenta,
+            gold: Color::Yellow,
+            good: Color::Green,
+            warning: Color::Yellow,
+            bad: Color::Red,
+            cursor: Color::White,
+            marks: false,
+        }
+    }
+}
+
+impl Theme {
+    /// A character's color, picked by a hash of their name so it stays put across
+    /// turns and runs.
+    pub(crate) fn speaker(&self, name: &str) -> Color {
+        if self.speakers.is_empty() {
+            return self.npc;
+        }
+        let hash = stable_hash(name);
+        self.speakers[(hash % self.speakers.len() as u64) as usize]
+    }
+
+    /// The selected row in lists.
+    pub(crate) fn selection(&self) -> Style {
+        if self.accent == Color::Reset {
+            return Style::default().add_modifier(Modifier::REVERSED);
+        }
+        Style::default().fg(self.selected).bg(self.accent)
+    }
+
+    /// No colors at all, for `--no-color` and `NO_COLOR`.
+    pub(crate) fn plain() -> Self {
+        Self {
+            player: Color::Reset,
+            narrator: Color::Reset,
+            npc: Color::Reset,
+            speakers: Vec::new(),
+            system: Color::Reset,
+            error: Color::Reset,
+            dialogue: Color::Reset,
+            accent: Color::Reset,
+            selected: Color::Reset,
+            muted: Color::Reset,
+            highlight: Color::Reset,
+            gold: Color::Reset,
+            good: Color::Reset,
+            warning: Color::Reset,
+            bad: Color::Reset,
+            cursor: Color::Reset,
+            marks: true,
+        }
+    }
+}
+
+/// Name of the theme used when the config does not pick one.
+pub(crate) const DEFAULT_THEME: &str = "default";
+pub(crate) const NO_COLOR_THEME: &str = "no-color";
+
+/// Built-in themes by name.
+pub(crate) fn builtin() -> Vec<(&'static str, Theme)> {
+    vec![
+        (DEFAULT_THEME, Theme::default()),
+        (
+            "light",
+            Theme {
+                player: Color::Magenta,
+                narrator: Color::Blue,
+                npc: Color::Rgb(0, 110, 110),
+                speakers: vec![
+                    Color::Rgb(0, 110, 110),
+                    Color::Rgb(140, 0, 140),
+                    Color::Rgb(0, 90, 180),
+                    Color::Rgb(150, 90, 0),
+                    Color::Rgb(170, 30, 30),
+                    Color::Rgb(30, 120, 30),
+                ],
+                system: Color::Gray,
+                dialogue: Color::Black,
+                accent: Color::Blue,
+                selected: Color::White,
+                muted: Color::Gray,
+                highlight: Color::Rgb(160, 60, 0),
+                gold: Color::Rgb(160, 110, 0),
+                warning: Color::Rgb(160, 110, 0),
+                cursor: Color::Black,
+                ..Theme::default()
+            },
+        ),
+        (
+            "ocean",
+            Theme {
+                player: Color::LightCyan,
+                narrator: Color::Rgb(140, 200, 255),
+                npc: Color::Rgb(120, 220, 190),
+                system: Color::Rgb(100, 130, 200),
+                error: Color::LightRed,
+                dialogue: Color::Rgb(225, 240, 255),
+                accent: Color::Rgb(70, 160, 230),
+                muted: Color::Rgb(90, 105, 130),
+                highlight: Color::Rgb(255, 200, 120),
+                gold: Color::Rgb(255, 215, 100),
+                good: Color::Rgb(120, 220, 190),
+                warning: Color::Rgb(255, 200, 120),
+                bad: Color::LightRed,
+                ..Theme::default()
+            },
+        ),
+        (
+            "amber",
+            Theme {
+                player: Color::Rgb(255, 200, 90),
+                narrator: Color::Rgb(255, 176, 0),
+                npc: Color::Rgb(255, 215, 140),
+                system: Color::Rgb(200, 140, 40),
+                error: Color::Rgb(255, 110, 60),
+                dialogue: Color::Rgb(255, 230, 170),
+                accent: Color::Rgb(255, 176, 0),
+                muted: Color::Rgb(140, 100, 30),
+                highlight: Color::Rgb(255, 230, 170),
+                gold: Color::Rgb(255, 215, 100),
+                good: Color::Rgb(255, 200, 90),
+                warning: Color::Rgb(255, 150, 40),
+                bad: Color::Rgb(255, 110, 60),
+                cursor: Color::Rgb(255, 176, 0),
+                ..Theme::default()
+            },
+        ),
+        (
+            "high-contrast",
+            Theme {
+                player: Color::LightYellow,
+                narrator: Color::White,
+                npc: Color::LightCyan,
+                system: Color::LightMagenta,
+                error: Color::LightRed,
+                accent: Color::LightYellow,
+                muted: Color::Gray,
+                highlight: Color::LightMagenta,
+                gold: Color::LightYellow,
+                good: Color::LightGreen,
+                warning: Color::LightYellow,
+                bad: Color::LightRed,
+                marks: true,
+                ..Theme::default()
+            },
+        ),
+        (
+            // Okabe-Ito palette; good and bad are blue and vermillion rather than green and red.
+            "colorblind",
+            Theme {
+                player: Color::Rgb(230, 159, 0),
+                narrator: Color::Rgb(86, 180, 233),
+                npc: Color::Rgb(204, 121, 167),
+                speakers: vec![
+                    Color::Rgb(204, 121, 167),
+                    Color::Rgb(0, 158, 115),
+                    Color::Rgb(240, 228, 66),
+                    Color::Rgb(0, 114, 178),
+                    Color::Rgb(213, 94, 0),
+                ],
+                system: Color::Rgb(0, 158, 115),
+                error: Color::Rgb(213, 94, 0),
+                dialogue: Color::Rgb(240, 228, 66),
+                accent: Color::Rgb(86, 180, 233),
+                muted: Color::Gray,
+                highlight: Color::Rgb(240, 228, 66),
+                gold: Color::Rgb(240, 228, 66),
+                good: Color::Rgb(86, 180, 233),
+                warning: Color::Rgb(230, 159, 0),
+                bad: Color::Rgb(213, 94, 0),
+                marks: true,
+                ..Theme::default()
+            },
+        ),
+        (NO_COLOR_THEME, Theme::plain()),
+    ]
+}
+
+/// Looks a theme up by name, user themes first so they can override a built-in.
+pub(crate) fn find(name: &str, user: &BTreeMap<String, Theme>) -> Option<Theme> {
+    user.iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, theme)| theme.clone())
+        .or_else(|| {
+            builtin()
+                .into_iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, theme)| theme)
+        })
+}
+
+/// Every selectable theme name, built-ins first.
+pub(crate) fn names(user: &BTreeMap<String, Theme>) -> Vec<String> {
+    let mut names: Vec<String> = builtin().into_iter().map(|(name, _)| name.to_string()).collect();
+    for name in user.keys() {
+        if !names.iter().any(|known| known.eq_ignore_ascii_case(name)) {
+            names.push(name.clone());
+        }
+    }
+    names
+}
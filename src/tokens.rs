@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+/// Starting guess before any response has reported real usage.
+const DEFAULT_CHARS_PER_TOKEN: f64 = 4.0;
+/// Weight given to each new measurement when recalibrating.
+const CALIBRATION_WEIGHT: f64 = 0.3;
+
+/// Token usage reported by the API for one request.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Usage {
+    pub(crate) input_tokens: u64,
+    pub(crate) output_tokens: u64,
+}
+
+impl Usage {
+    pub(crate) fn from_response(value: &Value) -> Option<Self> {
+        let usage = value.get("usage")?;
+        Some(Self {
+            input_tokens: usage.get("input_tokens")?.as_u64()?,
+            output_tokens: usage.get("output_tokens").and_then(Value::as_u64).unwrap_or(0),
+        })
+    }
+}
+
+/// Token counts of history chunks as the provider's tokenizer reports them. Chunks not
+/// counted yet are estimated from serialized length, calibrated against the
+/// `usage.input_tokens` the API reports for each request.
+pub(crate) struct TokenEstimator {
+    chars_per_token: f64,
+    /// Counted tokens by serialized chunk.
+    counted: HashMap<String, u64>,
+    /// Off once the provider fails to count, leaving estimates for the session.
+    pub(crate) counting: bool,
+}
+
+impl Default for TokenEstimator {
+    fn default() -> Self {
+        Self {
+            chars_per_token: DEFAULT_CHARS_PER_TOKEN,
+            counted: HashMap::new(),
+            counting: true,
+        }
+    }
+}
+
+impl TokenEstimator {
+    pub(crate) fn estimate_items(&self, items: &[Value]) -> u64 {
+        if let Some(tokens) = self.counted.get(&chunk_key(items)) {
+            return *tokens;
+        }
+        (serialized_len(items) as f64 / self.chars_per_token).ceil() as u64
+    }
+
+    /// The first of `chunks` the provider has not counted, while counting is on.
+    pub(crate) fn uncounted<'a>(&self, chunks: &'a [Vec<Value>]) -> Option<&'a Vec<Value>> {
+        if !self.counting {
+            return None;
+        }
+        chunks
+            .iter()
+            .find(|chunk| !self.counted.contains_key(&chunk_key(chunk)))
+    }
+
+    pub(crate) fn record(&mut self, items: &[Value], tokens: u64) {
+        self.counted.insert(chunk_key(items), tokens);
+    }
+
+    pub(crate) fn calibrate(&mut self, input_chars: usize, input_tokens: u64) {
+        if input_chars == 0 || input_tokens == 0 {
+            return;
+        }
+        let measured = input_chars as f64 / input_tokens as f64;
+        self.chars_per_token =
+            self.chars_per_token * (1.0 - CALIBRATION_WEIGHT) + measured * CALIBRATION_WEIGHT;
+    }
+}
+
+fn chunk_key(items: &[Value]) -> String {
+    Value::Array(items.to_vec()).to_string()
+}
+
+pub(crate) fn serialized_len(items: &[Value]) -> usize {
+    items.iter().map(|item| item.to_string().len()).sum()
+}
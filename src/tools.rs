@@ -0,0 +1,168 @@
+use serde_json::{json, Value};
+
+use crate::items::Item;
+
+/// Tool rounds allowed per turn before the reply is taken as-is.
+pub(crate) const MAX_TOOL_ROUNDS: usize = 3;
+
+/// A game-state update the model requested through function calling.
+pub(crate) enum ToolCall {
+    AddItem(Item),
+    RemoveItem(String),
+    SetLocation(String),
+    SetFlag(String),
+    /// Damage (negative) or healing (positive) in hit points.
+    ChangeHealth(i32),
+    /// How much an NPC's opinion of the player moved.
+    ChangeAffinity { npc: String, amount: i32 },
+    /// A one-way exit the narration established between two places.
+    AddExit {
+        from: String,
+        direction: String,
+        to: String,
+    },
+}
+
+impl ToolCall {
+    /// Parses a `function_call` output item; unknown tools and bad arguments yield `None`.
+    pub(crate) fn from_item(item: &Value) -> Option<Self> {
+        let name = item.get("name")?.as_str()?;
+        let arguments: Value = serde_json::from_str(item.get("arguments")?.as_str()?).ok()?;
+        if name == "add_exit" {
+            let field = |key: &str| Some(arguments.get(key)?.as_str()?.trim().to_string());
+            return Some(Self::AddExit {
+                from: field("from")?,
+                direction: field("direction")?,
+                to: field("to")?,
+            });
+        }
+        if name == "add_item" {
+            let mut item = Item::new(arguments.get("name")?.as_str()?);
+            item.qty = arguments.get("quantity")?.as_u64()?.clamp(1, 999) as u32;
+            item.description = arguments.get("description")?.as_str()?.trim().to_string();
+            item.tags = arguments
+                .get("tags")?
+                .as_array()?
+                .iter()
+                .filter_map(|tag| Some(tag.as_str()?.trim().to_lowercase()))
+                .filter(|tag| !tag.is_empty())
+                .collect();
+            return (!item.name.is_empty()).then_some(Self::AddItem(item));
+        }
+        if name == "change_affinity" {
+            let npc = arguments.get("npc")?.as_str()?.trim().to_string();
+            let amount = arguments.get("amount")?.as_i64()?.clamp(-100, 100) as i32;
+            return (!npc.is_empty()).then_some(Self::ChangeAffinity { npc, amount });
+        }
+        if name == "change_health" {
+            let amount = arguments.get("amount")?.as_i64()?;
+            return Some(Self::ChangeHealth(amount.clamp(-1000, 1000) as i32));
+        }
+        let value = arguments.get("name")?.as_str()?.trim().to_string();
+        if value.is_empty() {
+            return None;
+        }
+        match name {
+            "remove_item" => Some(Self::RemoveItem(value)),
+            "set_location" => Some(Self::SetLocation(value)),
+            "set_flag" => Some(Self::SetFlag(value)),
+            _ => None,
+        }
+    }
+}
+
+/// The `tools` array sent with narration requests.
+pub(crate) fn tool_definitions() -> Value {
+    json!([
+        {
+            "type": "function",
+            "name": "add_item",
+            "description": "Add items the player just obtained to their inventory. Repeated items stack. Give a one-sentence description and a few short tags (e.g. weapon, food, key), or leave them empty.",
+            "strict": true,
+            "parameters": {
+                "type": "object",
+                "additionalProperties": false,
+                "required": ["name", "quantity", "description", "tags"],
+                "properties": {
+                    "name": { "type": "string" },
+                    "quantity": { "type": "integer" },
+                    "description": { "type": "string" },
+                    "tags": { "type": "array", "items": { "type": "string" } }
+                }
+            }
+        },
+        tool("remove_item", "Remove an item the player used up, lost or gave away."),
+        tool("set_location", "Move the player to a new location."),
+        tool(
+            "set_flag",
+            "Record a story event or fact that should be remembered, as a name (e.g. met_king) or name=value (e.g. reputation=5).",
+        ),
+        {
+            "type": "function",
+            "name": "change_health",
+            "description": "Apply damage (negative amount) or healing (positive amount) to the player.",
+            "strict": true,
+            "parameters": {
+                "type": "object",
+                "additionalProperties": false,
+                "required": ["amount"],
+                "properties": { "amount": { "type": "integer" } }
+            }
+        },
+        {
+            "type": "function",
+            "name": "change_affinity",
+            "description": "Adjust how an NPC feels about the player: positive when helped or flattered, negative when insulted or harmed. Typical steps are 1-3.",
+            "strict": true,
+            "parameters": {
+                "type": "object",
+                "additionalProperties": false,
+                "required": ["npc", "amount"],
+                "properties": {
+                    "npc": { "type": "string" },
+                    "amount": { "type": "integer" }
+                }
+            }
+        },
+        {
+            "type": "function",
+            "name": "add_exit",
+            "description": "Record a way out of a place once the narration establishes it, e.g. from Tavern, direction north, to Market. Use an empty direction when none applies; add the reverse exit separately if it is two-way.",
+            "strict": true,
+            "parameters": {
+                "type": "object",
+                "additionalProperties": false,
+                "required": ["from", "direction", "to"],
+                "properties": {
+                    "from": { "type": "string" },
+                    "direction": { "type": "string" },
+                    "to": { "type": "string" }
+                }
+            }
+        },
+    ])
+}
+
+fn tool(name: &str, description: &str) -> Value {
+    json!({
+        "type": "function",
+        "name": name,
+        "description": description,
+        "strict": true,
+        "parameters": {
+            "type": "object",
+            "additionalProperties": false,
+            "required": ["name"],
+            "properties": { "name": { "type": "string" } }
+        }
+    })
+}
+
+/// The `function_call_output` item acknowledging `call`, required before the next request.
+pub(crate) fn call_output(call: &Value) -> Value {
+    json!({
+        "type": "function_call_output",
+        "call_id": call.get("call_id").cloned().unwrap_or(Value::Null),
+        "output": "ok"
+    })
+}
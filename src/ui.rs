@@ -1,132 +1,1242 @@
+use std::time::Instant;
+
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Paragraph, Wrap},
+    widgets::{
+        Block, Borders, Clear, Gauge, List, ListItem, ListState, Padding, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Tabs, Wrap,
+    },
+};
+
+use crate::app::{
+    affinity_label, App, BranchPicker, Focus, LogEntry, LogKind, ModelPicker, ScenarioPicker,
+    SettingsMenu, SideTab,
 };
+use crate::api::build_state_block;
+use crate::banner;
+use crate::clock;
+use crate::conflicts::Conflict;
+use crate::ending::final_stats;
+use crate::equipment::Slot;
+use crate::flags::FlagValue;
+use crate::gallery::{GalleryScene, GalleryView, SceneArt};
+use crate::map::render_map;
+use crate::palette::{CommandPalette, COMMANDS};
+use crate::quests::QuestStatus;
+use crate::theme::Theme;
+use crate::wiki::WikiView;
+
+/// The Input pane grows with each line typed up to this many, then scrolls.
+const MAX_INPUT_LINES: usize = 6;
 
-use crate::app::{App, LogEntry, LogKind};
+/// Widest the `/read` column grows, in cells.
+const READER_WIDTH: u16 = 80;
 
-pub(crate) fn draw_ui(frame: &mut Frame, app: &mut App) {
-    let size = frame.size();
+/// Below this many rows the help line is dropped.
+const MIN_HEIGHT_FOR_HELP: u16 = 20;
+
+/// Draws the active game, under a tab bar when more than one game is open.
+pub(crate) fn draw_ui(frame: &mut Frame, app: &mut App, tabs: &[String], active: usize) {
+    let mut size = frame.size();
+    app.scene_image_area = None;
+    if tabs.len() > 1 {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(size);
+        let titles = tabs.iter().enumerate().map(|(i, title)| format!("{} {title}", i + 1));
+        let bar = Tabs::new(titles)
+            .select(active)
+            .style(Style::default().fg(app.theme.muted))
+            .highlight_style(app.theme.selection());
+        frame.render_widget(bar, rows[0]);
+        size = rows[1];
+    }
+    if app.reader.is_some() {
+        draw_reader(frame, app, size);
+        return;
+    }
+    let input_lines = app.input.text().split('\n').count().clamp(1, MAX_INPUT_LINES) as u16;
+    // Short terminals give the help row to the story; the status bar always stays.
+    let help_rows = u16::from(size.height >= MIN_HEIGHT_FOR_HELP);
 
     let vertical = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Min(8),
-            Constraint::Length(3),
-            Constraint::Length(1),
+            Constraint::Length(input_lines + 2),
             Constraint::Length(1),
+            Constraint::Length(help_rows),
         ])
         .split(size);
 
-    let (log_text, line_count) = build_log_text(&app.log);
-    let log_block = Block::default().borders(Borders::ALL).title("Story");
-    let max_scroll = line_count.saturating_sub(vertical[0].height as usize);
+    let layout = app.layout;
+    let side_width = if layout.side_panel { layout.side_width } else { 0 };
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(40), Constraint::Length(side_width)])
+        .split(vertical[0]);
+    if layout.side_panel {
+        draw_side_panel(frame, app, columns[1]);
+    }
+
+    let top_pane = app.show_scene || app.show_map;
+    if !app.show_scene && app.focus == Focus::Scene {
+        app.focus = Focus::Input;
+    }
+    let scene_percent = if top_pane { layout.scene_percent } else { 0 };
+    let panes = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(scene_percent),
+            Constraint::Percentage(100 - scene_percent),
+        ])
+        .split(columns[0]);
+
+    if top_pane {
+        draw_scene(frame, app, panes[0]);
+    }
+
+    let entries = app.visible_log();
+    let (mut log_text, turn_starts) = build_log_text(&entries, &app.theme, app.show_turns);
+    for (i, choice) in app.choices.iter().enumerate() {
+        log_text.lines.push(Line::from(vec![
+            Span::styled(format!("{}) ", i + 1), Style::default().fg(app.theme.highlight)),
+            Span::raw(choice.clone()),
+        ]));
+    }
+    let log_title = match &app.log_filter {
+        Some(filter) => format!("Story ({} only)", filter.label()),
+        None => "Story".to_string(),
+    };
+    let mut log_block = pane_block(log_title, app.focus == Focus::Story, &app.theme);
+    if !app.follow && app.log.len() > app.seen_log {
+        log_block = log_block.title_bottom(
+            Line::from(Span::styled(
+                format!(" new messages \u{2193} {} ", app.keys.scroll_bottom),
+                Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD),
+            ))
+            .alignment(Alignment::Right),
+        );
+    }
+    let log_inner = log_block.inner(panes[1]);
+    // Scroll offsets count wrapped rows, so measure the text as the pane wraps it.
+    if let Some(turn) = app.goto_turn.take() {
+        // The first line of the turn, or the end when it was filtered out.
+        let line = turn_starts
+            .iter()
+            .find(|(start_turn, _)| *start_turn >= turn)
+            .map_or(log_text.lines.len(), |(_, line)| *line);
+        app.scroll = wrapped_rows(&log_text.lines[..line], log_inner.width) as u16;
+    }
+    let log_widget = Paragraph::new(log_text).wrap(Wrap { trim: false });
+    let rows = log_widget.line_count(log_inner.width);
+    let max_scroll = rows.saturating_sub(log_inner.height as usize);
+    app.story_rows = log_inner.height;
+    if app.follow {
+        app.scroll = max_scroll as u16;
+    }
     app.scroll = app.scroll.min(max_scroll as u16);
+    // Scrolling back down to the end resumes following.
+    if usize::from(app.scroll) >= max_scroll {
+        app.follow = true;
+        app.seen_log = app.log.len();
+    }
 
-    let log_widget = Paragraph::new(log_text)
-        .block(log_block)
-        .wrap(Wrap { trim: false })
-        .scroll((app.scroll, 0));
-    frame.render_widget(log_widget, vertical[0]);
+    let log_widget = log_widget.block(log_block).scroll((app.scroll, 0));
+    frame.render_widget(log_widget, panes[1]);
+    if max_scroll > 0 {
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .thumb_style(Style::default().fg(app.theme.accent))
+            .track_style(Style::default().fg(app.theme.muted));
+        let mut state = ScrollbarState::new(max_scroll + 1)
+            .viewport_content_length(log_inner.height as usize)
+            .position(app.scroll as usize);
+        let track = panes[1].inner(&Margin { vertical: 1, horizontal: 0 });
+        frame.render_stateful_widget(scrollbar, track, &mut state);
+    }
 
-    let input_block = Block::default().borders(Borders::ALL).title("Input");
-    let input_widget = Paragraph::new(app.input.as_str())
+    let input_block = pane_block("Input", app.focus == Focus::Input, &app.theme);
+    // Past the height cap, keep the cursor's line in view; lines wider than the pane
+    // scroll sideways so the cursor stays on screen.
+    let (cursor_line, cursor_column) = app.input.cursor_position();
+    let input_scroll = (cursor_line + 1).saturating_sub(MAX_INPUT_LINES) as u16;
+    let input_width = usize::from(vertical[1].width.saturating_sub(2));
+    let input_hscroll = (cursor_column + 1).saturating_sub(input_width) as u16;
+    let input_widget = Paragraph::new(app.input.text())
         .block(input_block)
-        .wrap(Wrap { trim: false });
+        .scroll((input_scroll, input_hscroll));
     frame.render_widget(input_widget, vertical[1]);
 
-    let status_line = build_status_line(app);
+    let status_line = build_status_line(app, vertical[2].width);
     let status_widget = Paragraph::new(status_line);
     frame.render_widget(status_widget, vertical[2]);
 
-    let help_text =
-        "Enter send | Up/Down scroll | /new | /quit | Ctrl+C quit | /help for commands";
+    let keys = &app.keys;
+    let help_text = format!(
+        "Enter send | Alt+Enter newline | {}/{} scroll | {} commands | {} quit | /help",
+        keys.scroll_up, keys.scroll_down, keys.palette, keys.quit
+    );
     let help_widget = Paragraph::new(help_text);
     frame.render_widget(help_widget, vertical[3]);
 
-    let cursor_x = vertical[1].x + 1 + app.input.chars().count() as u16;
-    let cursor_y = vertical[1].y + 1;
-    frame.set_cursor(cursor_x, cursor_y);
+    if app.confirm_quit {
+        draw_confirm_quit(frame, app, size);
+        return;
+    }
+    if let Some(conflict) = app.conflicts.front() {
+        draw_conflict(frame, app, conflict, size);
+        return;
+    }
+    if let Some(picker) = &app.model_picker {
+        draw_model_picker(frame, picker, &app.theme, size);
+        return;
+    }
+    if let Some(menu) = &app.settings_menu {
+        draw_settings_menu(frame, menu, &app.theme, size);
+        return;
+    }
+    if let Some(picker) = &app.branch_picker {
+        draw_branch_picker(frame, app, picker, size);
+        return;
+    }
+    if let Some(wiki) = &app.wiki {
+        draw_wiki(frame, wiki, &app.theme, size);
+        return;
+    }
+    if let Some(gallery) = &app.gallery {
+        draw_gallery(frame, gallery, &app.scenes, size);
+        return;
+    }
+    if let Some(palette) = &app.palette {
+        draw_palette(frame, palette, &app.theme, size);
+        return;
+    }
+    if app.inspector.is_some() {
+        draw_inspector(frame, app, size);
+        return;
+    }
+    if let Some(text) = &app.reply_editor {
+        draw_reply_editor(frame, text, &app.theme, size);
+        return;
+    }
+    if let Some(text) = &app.prompt_editor {
+        draw_prompt_editor(frame, app, text, size);
+        return;
+    }
+    if app.show_stats {
+        draw_stats(frame, app, size);
+        return;
+    }
+    if let Some(picker) = &app.scenario_picker {
+        draw_scenario_picker(frame, app, picker, size);
+        return;
+    }
+    if app.game_over {
+        draw_game_over(frame, app, size);
+        return;
+    }
+    if app.story_over {
+        draw_story_end(frame, app, size);
+        return;
+    }
+
+    if app.focus == Focus::Input {
+        let cursor_x = vertical[1].x + 1 + cursor_column as u16 - input_hscroll;
+        let cursor_y = vertical[1].y + 1 + cursor_line as u16 - input_scroll;
+        frame.set_cursor(cursor_x, cursor_y);
+    }
+}
+
+/// The Scene pane, sharing its area with the map when that is open. With the
+/// Scene pane off, the map takes the whole area.
+fn draw_scene(frame: &mut Frame, app: &mut App, area: Rect) {
+    let (scene_area, map_area) = match (app.show_scene, app.show_map) {
+        (true, true) => {
+            let split = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(area);
+            (Some(split[0]), Some(split[1]))
+        }
+        (true, false) => (Some(area), None),
+        (false, _) => (None, Some(area)),
+    };
+    if let Some(map_area) = map_area {
+        let map: Vec<Line> = render_map(&app.state).into_iter().map(Line::from).collect();
+        let map_widget =
+            Paragraph::new(map).block(Block::default().borders(Borders::ALL).title("Map"));
+        frame.render_widget(map_widget, map_area);
+    }
+    // A picture is sent by the main loop after the frame; leave its cells blank.
+    let picture = app.graphics.is_some() && app.scene_image.is_some();
+    if let Some(scene_area) = scene_area.filter(|_| picture) {
+        let block = pane_block("Scene", app.focus == Focus::Scene, &app.theme);
+        if !app.has_overlay() {
+            app.scene_image_area = Some(block.inner(scene_area));
+        }
+        frame.render_widget(block, scene_area);
+        return;
+    }
+    if let Some(scene_area) = scene_area {
+        let art = app.scene_frame().unwrap_or_else(|| app.sketch_scene());
+        let block = pane_block("Scene", app.focus == Focus::Scene, &app.theme);
+        let inner = block.inner(scene_area);
+        let mut scene_text: Vec<Line> = Vec::new();
+        if let Some(title) = app.active_banner() {
+            let style = Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD);
+            for row in banner::render(title, usize::from(inner.width)) {
+                scene_text.push(Line::styled(row, style));
+            }
+            scene_text.push(Line::from(""));
+        }
+        scene_text.extend(art.lines().map(|line| Line::from(line.to_string())));
+        let rows = inner.height;
+        let max_scroll = scene_text.len().saturating_sub(usize::from(rows));
+        app.scene_rows = rows;
+        app.scene_scroll = app.scene_scroll.min(max_scroll as u16);
+        let scene_widget = Paragraph::new(scene_text).block(block).scroll((app.scene_scroll, 0));
+        frame.render_widget(scene_widget, scene_area);
+    }
 }
 
-fn build_log_text(entries: &[LogEntry]) -> (Text<'static>, usize) {
+/// A bordered pane whose border takes the accent color while it has focus.
+fn pane_block<'a>(title: impl Into<Line<'a>>, focused: bool, theme: &Theme) -> Block<'a> {
+    let block = Block::default().borders(Borders::ALL).title(title);
+    if focused {
+        block.border_style(Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))
+    } else {
+        block
+    }
+}
+
+fn draw_side_panel(frame: &mut Frame, app: &App, area: Rect) {
+    let sections = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(6),
+            Constraint::Min(3),
+            Constraint::Length(1),
+            Constraint::Min(6),
+        ])
+        .split(area);
+
+    let state = &app.state;
+    let theme = &app.theme;
+    let ratio = f64::from(state.hp.max(0)) / f64::from(state.max_hp.max(1));
+    let color = if ratio > 0.5 {
+        theme.good
+    } else if ratio > 0.25 {
+        theme.warning
+    } else {
+        theme.bad
+    };
+    let status = Block::default().borders(Borders::ALL).title("Status");
+    let status_rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(status.inner(sections[0]));
+    frame.render_widget(status, sections[0]);
+    let health = Gauge::default()
+        .gauge_style(Style::default().fg(color).bg(theme.muted))
+        .label(format!("HP {}/{}", state.hp, state.max_hp))
+        .ratio(ratio.clamp(0.0, 1.0));
+    frame.render_widget(health, status_rows[0]);
+    frame.render_widget(Paragraph::new(state.location.as_str()), status_rows[1]);
+    frame.render_widget(Paragraph::new(clock::describe(state.minutes)), status_rows[2]);
+    let weather = format!("Weather: {}", state.weather.label());
+    frame.render_widget(Paragraph::new(weather), status_rows[3]);
+
+    let sheet = &state.character;
+    let mut lines = vec![
+        Line::from(Span::styled(
+            sheet.name.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(format!("Strength {}", sheet.strength)),
+        Line::from(format!("Agility  {}", sheet.agility)),
+        Line::from(format!("Wits     {}", sheet.wits)),
+    ];
+    if !sheet.skills.is_empty() {
+        lines.push(Line::from(""));
+        for (skill, value) in &sheet.skills {
+            lines.push(Line::from(format!("{skill} +{value}")));
+        }
+    }
+    let widget = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Character"))
+        .wrap(Wrap { trim: true });
+    frame.render_widget(widget, sections[1]);
+
+    let titles = SideTab::ALL.iter().map(|tab| tab.label());
+    let tabs = Tabs::new(titles)
+        .select(SideTab::ALL.iter().position(|tab| *tab == app.side_tab).unwrap_or(0))
+        .padding("", "")
+        .divider(" ")
+        .style(Style::default().fg(theme.muted))
+        .highlight_style(Style::default().fg(theme.accent).add_modifier(Modifier::BOLD));
+    frame.render_widget(tabs, sections[2]);
+
+    let lines = match app.side_tab {
+        SideTab::Inventory => inventory_lines(app),
+        SideTab::Quests => quest_lines(app),
+        SideTab::Npcs => npc_lines(app),
+        SideTab::Flags => flag_lines(app),
+    };
+    let title = format!("{} ({} next)", app.side_tab.label(), app.keys.next_side_tab);
+    let widget = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .wrap(Wrap { trim: true });
+    frame.render_widget(widget, sections[3]);
+}
+
+fn npc_lines(app: &App) -> Vec<Line<'_>> {
+    let (state, theme) = (&app.state, &app.theme);
+    if state.affinity.is_empty() {
+        vec![Line::from(Span::styled(
+            "No one yet.",
+            Style::default().fg(theme.muted),
+        ))]
+    } else {
+        state
+            .affinity
+            .iter()
+            .map(|(npc, score)| {
+                let color = match score {
+                    s if *s >= 2 => theme.good,
+                    s if *s <= -2 => theme.bad,
+                    _ => Color::Reset,
+                };
+                Line::from(vec![
+                    Span::styled(format!("{npc} "), Style::default().fg(theme.speaker(npc))),
+                    Span::styled(
+                        format!("{score:+} {}", affinity_label(*score)),
+                        Style::default().fg(color),
+                    ),
+                ])
+            })
+            .collect()
+    }
+}
+
+fn quest_lines(app: &App) -> Vec<Line<'_>> {
+    let (state, theme) = (&app.state, &app.theme);
+    if state.quests.is_empty() {
+        vec![Line::from(Span::styled(
+            "No quests yet.",
+            Style::default().fg(theme.muted),
+        ))]
+    } else {
+        state
+            .quests
+            .iter()
+            .map(|quest| match quest.status {
+                QuestStatus::Active => Line::from(format!("- {}", quest.title)),
+                QuestStatus::Completed => Line::from(Span::styled(
+                    format!("x {}", quest.title),
+                    Style::default()
+                        .fg(theme.muted)
+                        .add_modifier(Modifier::CROSSED_OUT),
+                )),
+            })
+            .collect()
+    }
+}
+
+fn inventory_lines(app: &App) -> Vec<Line<'_>> {
+    let (state, theme) = (&app.state, &app.theme);
+    let mut items = vec![Line::from(Span::styled(
+        format!("Gold: {}", state.gold),
+        Style::default().fg(theme.gold),
+    ))];
+    for slot in Slot::ALL {
+        if let Some(item) = state.equipment.slot(slot) {
+            items.push(Line::from(Span::styled(
+                format!("[{}] {}", slot.label(), item.name),
+                Style::default().fg(theme.accent),
+            )));
+        }
+    }
+    items.extend(state.inventory.iter().map(|item| Line::from(format!("- {}", item.label()))));
+    items
+}
+
+fn flag_lines(app: &App) -> Vec<Line<'_>> {
+    let (state, theme) = (&app.state, &app.theme);
+    if state.flags.is_empty() {
+        vec![Line::from(Span::styled(
+            "No flags yet.",
+            Style::default().fg(theme.muted),
+        ))]
+    } else {
+        state
+            .flags
+            .iter()
+            .map(|(name, value)| match value {
+                FlagValue::Bool(true) => Line::from(name.as_str()),
+                FlagValue::Bool(false) => Line::from(Span::styled(
+                    name.as_str(),
+                    Style::default().fg(theme.muted),
+                )),
+                _ => Line::from(vec![
+                    Span::raw(format!("{name} ")),
+                    Span::styled(value.to_string(), Style::default().fg(theme.accent)),
+                ]),
+            })
+            .collect()
+    }
+}
+
+fn draw_confirm_quit(frame: &mut Frame, app: &App, area: Rect) {
+    let popup = centered_rect(area, 50, 30);
+    frame.render_widget(Clear, popup);
+    let (title, verb, leaving) = if app.closing {
+        ("Close tab", "close", "closing the tab")
+    } else {
+        ("Quit", "quit", "quitting")
+    };
+    let lines = vec![
+        Line::from(Span::styled(
+            format!("Save the story before {leaving}?"),
+            Style::default().fg(app.theme.warning).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(format!("S  export to Markdown and {verb}")),
+        Line::from(format!("Q  {verb} without saving ({} also)", app.keys.quit)),
+        Line::from("Esc  keep playing"),
+    ];
+    let widget = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title(title));
+    frame.render_widget(widget, popup);
+}
+
+fn draw_conflict(frame: &mut Frame, app: &App, conflict: &Conflict, area: Rect) {
+    let popup = centered_rect(area, 60, 40);
+    frame.render_widget(Clear, popup);
+    let title = match app.conflicts.len() {
+        1 => "State conflict".to_string(),
+        count => format!("State conflict (1 of {count})"),
+    };
+    let lines = vec![
+        Line::from(Span::styled(
+            "The narrator disagrees with what you set.",
+            Style::default().fg(app.theme.warning).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(format!("Yours: {}", conflict.mine)),
+        Line::from(format!("Narrator: {}", conflict.theirs)),
+        Line::from(""),
+        Line::from("A  accept the narrator's version"),
+        Line::from("K  keep mine (Esc)"),
+        Line::from("E  edit the change as a command"),
+    ];
+    let widget = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL).title(title));
+    frame.render_widget(widget, popup);
+}
+
+fn draw_game_over(frame: &mut Frame, app: &App, area: Rect) {
+    let popup = centered_rect(area, 50, 30);
+    frame.render_widget(Clear, popup);
+    let restore = if app.checkpoint.is_some() {
+        "R  restart from before your last action"
+    } else {
+        "R  (no checkpoint available)"
+    };
+    let lines = vec![
+        Line::from(Span::styled(
+            "You have died.",
+            Style::default().fg(app.theme.bad).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(restore),
+        Line::from("N  start a new game"),
+        Line::from("Q  quit"),
+    ];
+    let widget = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title("Game over"));
+    frame.render_widget(widget, popup);
+}
+
+fn draw_reply_editor(frame: &mut Frame, text: &str, theme: &Theme, area: Rect) {
+    let popup = centered_rect(area, 80, 60);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Edit last reply (Ctrl+S save, Esc cancel)");
+    let inner = block.inner(popup);
+    let mut lines: Vec<Line> = text.split('\n').map(|line| Line::from(line.to_string())).collect();
+    if let Some(last) = lines.last_mut() {
+        last.spans.push(Span::styled(" ", Style::default().bg(theme.cursor)));
+    }
+    // Keep the end of the text, where the cursor is, in view.
+    let wrapped = wrapped_rows(&lines, inner.width);
+    let scroll = wrapped.saturating_sub(inner.height as usize) as u16;
+    let widget = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0));
+    frame.render_widget(widget, popup);
+}
+
+/// The editable prompt, then the per-turn context the game adds after it, read-only.
+fn draw_prompt_editor(frame: &mut Frame, app: &App, text: &str, area: Rect) {
+    let popup = centered_rect(area, 90, 80);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("System prompt (Ctrl+S save, Ctrl+D default, Esc cancel)");
+    let inner = block.inner(popup);
+    let theme = &app.theme;
+    let mut lines: Vec<Line> = text.split('\n').map(|line| Line::from(line.to_string())).collect();
+    if let Some(last) = lines.last_mut() {
+        last.spans.push(Span::styled(" ", Style::default().bg(theme.cursor)));
+    }
+    // Keep the end of the editable text, where the cursor is, in view.
+    let wrapped = wrapped_rows(&lines, inner.width);
+    let scroll = wrapped.saturating_sub(inner.height as usize) as u16;
+
+    let injected = Style::default().fg(theme.muted);
+    let heading = injected.add_modifier(Modifier::BOLD);
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Added by the game each turn (read-only)", heading)));
+    if !app.state.premise.is_empty() {
+        for line in app.state.premise.lines() {
+            lines.push(Line::from(Span::styled(line.to_string(), injected)));
+        }
+    }
+    for line in build_state_block(&app.state).lines() {
+        lines.push(Line::from(Span::styled(line.to_string(), injected)));
+    }
+    let widget = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0));
+    frame.render_widget(widget, popup);
+}
+
+fn draw_stats(frame: &mut Frame, app: &App, area: Rect) {
+    let popup = centered_rect(area, 60, 60);
+    frame.render_widget(Clear, popup);
+    let words = &app.word_stats;
+    let session = &app.session_stats;
+    let average_words = words.total_words.checked_div(u64::from(words.replies)).unwrap_or(0);
+    let compliance = match app.word_target {
+        Some(target) => format!(
+            "{}-{}: {} within, {} long, {} short, {} corrections",
+            target.min,
+            target.max,
+            words.within,
+            words.too_long,
+            words.too_short,
+            words.corrections
+        ),
+        None => "off (set word_target in config)".to_string(),
+    };
+    let rows = [
+        ("Turns played", app.state.turn.to_string()),
+        ("Words generated", format!("{} (avg {average_words} per reply)", words.total_words)),
+        ("Tokens used", format!("{} in, {} out", session.input_tokens, session.output_tokens)),
+        ("Session cost", app.session_stats.cost_label()),
+        ("Avg latency", format!("{:.1}s", session.average_latency().as_secs_f64())),
+        ("Locations visited", app.state.visited.len().to_string()),
+        ("Items collected", app.state.items_collected.to_string()),
+        ("Word target", compliance),
+        ("Memory", format!("{} moments embedded", app.memory.len())),
+        ("Run seed", format!("{} (replay with --seed)", app.seed)),
+    ];
+    let label = Style::default().fg(app.theme.accent);
+    let lines: Vec<Line> = rows
+        .into_iter()
+        .map(|(name, value)| {
+            Line::from(vec![
+                Span::styled(format!("{name:<18}"), label),
+                Span::raw(value),
+            ])
+        })
+        .collect();
+    let widget = Paragraph::new(lines)
+        .wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL).title("Session stats (Esc close)"));
+    frame.render_widget(widget, popup);
+}
+
+fn draw_story_end(frame: &mut Frame, app: &App, area: Rect) {
+    let popup = centered_rect(area, 50, 50);
+    frame.render_widget(Clear, popup);
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "The End",
+            Style::default().fg(app.theme.gold).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    lines.extend(final_stats(&app.state).into_iter().map(Line::from));
+    lines.extend([
+        Line::from(""),
+        Line::from("E  export the story"),
+        Line::from("N  start a new game"),
+        Line::from("Esc  read the epilogue"),
+        Line::from("Q  quit"),
+    ]);
+    let widget = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title("Story complete"));
+    frame.render_widget(widget, popup);
+}
+
+fn draw_branch_picker(frame: &mut Frame, app: &App, picker: &BranchPicker, area: Rect) {
+    let theme = &app.theme;
+    let popup = centered_rect(area, 50, 50);
+    let mut items = vec![ListItem::new(format!(
+        "{} (current, turn {})",
+        app.branch, app.state.turn
+    ))];
+    items.extend(app.branches.iter().map(|branch| ListItem::new(branch.name.as_str())));
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Branches (Enter switch, Esc close)"),
+        )
+        .highlight_style(theme.selection());
+    let mut state = ListState::default().with_selected(Some(picker.selected));
+    frame.render_widget(Clear, popup);
+    frame.render_stateful_widget(list, popup, &mut state);
+}
+
+fn draw_scenario_picker(frame: &mut Frame, app: &App, picker: &ScenarioPicker, area: Rect) {
+    let theme = &app.theme;
+    let popup = centered_rect(area, 70, 50);
+    let mut items: Vec<ListItem> = app
+        .scenarios
+        .iter()
+        .map(|scenario| ListItem::new(format!("{} - {}", scenario.name, scenario.description)))
+        .collect();
+    items.push(ListItem::new("Custom - Start blank and describe your own setting."));
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("New game (Enter start, Esc cancel)"),
+        )
+        .highlight_style(theme.selection());
+    let mut state = ListState::default().with_selected(Some(picker.selected));
+    frame.render_widget(Clear, popup);
+    frame.render_stateful_widget(list, popup, &mut state);
+}
+
+fn draw_model_picker(frame: &mut Frame, picker: &ModelPicker, theme: &Theme, area: Rect) {
+    let popup = centered_rect(area, 60, 70);
+    let items: Vec<ListItem> = picker
+        .models
+        .iter()
+        .map(|model| ListItem::new(model.as_str()))
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Models (Enter select, Esc close)"),
+        )
+        .highlight_style(theme.selection());
+    let mut state = ListState::default().with_selected(Some(picker.selected));
+    frame.render_widget(Clear, popup);
+    frame.render_stateful_widget(list, popup, &mut state);
+}
+
+fn draw_settings_menu(frame: &mut Frame, menu: &SettingsMenu, theme: &Theme, area: Rect) {
+    let popup = centered_rect(area, 60, 50);
+    let label = Style::default().fg(theme.accent);
+    let items: Vec<ListItem> = SettingsMenu::ROWS
+        .iter()
+        .enumerate()
+        .map(|(row, name)| {
+            let value = match row {
+                0 => format!("{}_", menu.value(row)),
+                _ => format!("< {} >", menu.value(row)),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{name:<16}"), label),
+                Span::raw(value),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Settings (Left/Right change, type the model, Enter save, Esc cancel)"),
+        )
+        .highlight_style(theme.selection());
+    let mut state = ListState::default().with_selected(Some(menu.selected));
+    frame.render_widget(Clear, popup);
+    frame.render_stateful_widget(list, popup, &mut state);
+}
+
+fn draw_gallery(frame: &mut Frame, gallery: &GalleryView, scenes: &[GalleryScene], area: Rect) {
+    let Some(scene) = scenes.get(gallery.selected) else {
+        return;
+    };
+    let popup = centered_rect(area, 80, 80);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(
+            "Gallery {}/{}: turn {}, {}",
+            gallery.selected + 1,
+            scenes.len(),
+            scene.turn,
+            scene.location
+        ))
+        .title_bottom(
+            Line::from(" \u{2190}/\u{2192} browse \u{b7} e export \u{b7} Esc close ")
+                .alignment(Alignment::Center),
+        );
+    let art = match &scene.art {
+        SceneArt::Text(art) => art.as_str(),
+        SceneArt::Picture(_) => "A generated picture; press e to export it with the gallery.",
+    };
+    frame.render_widget(Paragraph::new(art).block(block), popup);
+}
+
+fn draw_wiki(frame: &mut Frame, wiki: &WikiView, theme: &Theme, area: Rect) {
+    let popup = centered_rect(area, 80, 80);
+    frame.render_widget(Clear, popup);
+    let outer = Block::default()
+        .borders(Borders::ALL)
+        .title("Encyclopedia (type to search, Enter follow link, Esc close)");
+    let inner = outer.inner(popup);
+    frame.render_widget(outer, popup);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(3)])
+        .split(inner);
+    let search = Paragraph::new(format!("Search: {}", wiki.query));
+    frame.render_widget(search, rows[0]);
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+        .split(rows[1]);
+    let filtered = wiki.filtered();
+    let items: Vec<ListItem> = filtered
+        .iter()
+        .map(|entry| ListItem::new(format!("{} ({})", entry.name, entry.category.label())))
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::RIGHT))
+        .highlight_style(theme.selection());
+    let mut state = ListState::default().with_selected(Some(wiki.selected));
+    frame.render_stateful_widget(list, columns[0], &mut state);
+
+    let Some(entry) = wiki.selected_entry() else {
+        frame.render_widget(Paragraph::new("No matching entries."), columns[1]);
+        return;
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(
+            entry.name.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    if entry.passages.is_empty() {
+        lines.push(Line::from("No passages mention this yet."));
+    }
+    for passage in &entry.passages {
+        for line in passage.lines() {
+            lines.push(Line::from(line.to_string()));
+        }
+        lines.push(Line::from(""));
+    }
+    if !entry.links.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!("See also: {}", entry.links.join(", ")),
+            Style::default().fg(theme.accent),
+        )));
+    }
+    let detail = Paragraph::new(lines)
+        .block(Block::default().padding(Padding::horizontal(1)))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(detail, columns[1]);
+}
+
+fn draw_palette(frame: &mut Frame, palette: &CommandPalette, theme: &Theme, area: Rect) {
+    let popup = centered_rect(area, 70, 70);
+    frame.render_widget(Clear, popup);
+    let outer = Block::default()
+        .borders(Borders::ALL)
+        .title("Commands (type to search, Enter run, Esc close)");
+    let inner = outer.inner(popup);
+    frame.render_widget(outer, popup);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(3)])
+        .split(inner);
+    frame.render_widget(Paragraph::new(format!("> {}", palette.query)), rows[0]);
+
+    let width = COMMANDS.iter().map(|command| command.usage.len()).max().unwrap_or(0);
+    let help = Style::default().fg(theme.muted);
+    let items: Vec<ListItem> = palette
+        .filtered()
+        .into_iter()
+        .map(|command| {
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{:<width$}  ", command.usage)),
+                Span::styled(command.help, help),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(theme.selection());
+    let mut state = ListState::default().with_selected(Some(palette.selected));
+    frame.render_stateful_widget(list, rows[1], &mut state);
+}
+
+/// The story alone, in a centered column of comfortable width.
+fn draw_reader(frame: &mut Frame, app: &mut App, area: Rect) {
+    let entries: Vec<(&LogEntry, &str)> = app
+        .log
+        .iter()
+        .filter(|entry| matches!(entry.kind, LogKind::User | LogKind::Assistant))
+        .map(|entry| (entry, entry.text.as_str()))
+        .collect();
+    let (text, _) = build_log_text(&entries, &app.theme, false);
+
+    let width = area.width.saturating_sub(4).min(READER_WIDTH);
+    let column = Rect {
+        x: area.x + (area.width - width) / 2,
+        width,
+        ..area
+    };
+    let block = Block::default()
+        .borders(Borders::TOP | Borders::BOTTOM)
+        .border_style(Style::default().fg(app.theme.muted))
+        .title(app.tab_title())
+        .title_alignment(Alignment::Center);
+    let inner = block.inner(column).inner(&Margin { vertical: 1, horizontal: 0 });
+    let widget = Paragraph::new(text).wrap(Wrap { trim: false });
+    let rows = widget.line_count(inner.width);
+    let max_scroll = rows.saturating_sub(inner.height as usize) as u16;
+    let Some(reader) = app.reader.as_mut() else {
+        return;
+    };
+    reader.scroll = reader.scroll.min(max_scroll);
+    reader.rows = inner.height;
+    let pages = rows.div_ceil(usize::from(inner.height.max(1))).max(1);
+    let page = if reader.scroll >= max_scroll {
+        pages
+    } else {
+        usize::from(reader.scroll / inner.height.max(1)) + 1
+    };
+    let block = block.title_bottom(
+        Line::from(format!(" page {page}/{pages} \u{b7} Space/b page \u{b7} Esc close "))
+            .alignment(Alignment::Center),
+    );
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, column);
+    frame.render_widget(widget.scroll((reader.scroll, 0)), inner);
+}
+
+fn draw_inspector(frame: &mut Frame, app: &mut App, area: Rect) {
+    let popup = centered_rect(area, 90, 90);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(
+            "Last exchange (Up/Down/PgUp/PgDn scroll, {} or Esc close)",
+            app.keys.inspector
+        ));
+
+    let heading = Style::default().fg(app.theme.accent).add_modifier(Modifier::BOLD);
+    let mut lines = Vec::new();
+    match &app.last_exchange {
+        Some(exchange) => {
+            lines.push(Line::from(Span::styled("Request", heading)));
+            lines.extend(exchange.request.lines().map(|line| Line::from(line.to_string())));
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("Response", heading)));
+            lines.extend(exchange.response.lines().map(|line| Line::from(line.to_string())));
+        }
+        None => lines.push(Line::from("No turn has completed yet.")),
+    }
+
+    let inner = block.inner(popup);
+    let wrapped = wrapped_rows(&lines, inner.width);
+    let max_scroll = wrapped.saturating_sub(inner.height as usize) as u16;
+    let Some(inspector) = app.inspector.as_mut() else {
+        return;
+    };
+    inspector.scroll = inspector.scroll.min(max_scroll);
+    let widget = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((inspector.scroll, 0));
+    frame.render_widget(widget, popup);
+}
+
+/// Rows `lines` take once wrapped to `width`, exactly as a wrapping Paragraph draws them.
+fn wrapped_rows(lines: &[Line], width: u16) -> usize {
+    Paragraph::new(lines.to_vec()).wrap(Wrap { trim: false }).line_count(width)
+}
+
+fn centered_rect(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = area.height * percent_y / 100;
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// The log as styled lines, plus the line each turn starts on. With `gutter`,
+/// turn numbers are drawn in a column on the left.
+fn build_log_text(
+    entries: &[(&LogEntry, &str)],
+    theme: &Theme,
+    gutter: bool,
+) -> (Text<'static>, Vec<(u32, usize)>) {
     let mut lines: Vec<Line<'static>> = Vec::new();
+    let mut turn_starts: Vec<(u32, usize)> = Vec::new();
 
-    for entry in entries {
-        let (prefix, style) = match entry.kind {
+    for (entry, text) in entries {
+        let new_turn = turn_starts.last().is_none_or(|(turn, _)| *turn != entry.turn);
+        if new_turn {
+            turn_starts.push((entry.turn, lines.len()));
+        }
+        let start = lines.len();
+        // With marks on, a symbol and weight tell speakers apart without hue.
+        let (mark, modifier, prefix, color) = match entry.kind {
             LogKind::User => {
                 let label = entry.speaker.as_deref().unwrap_or("You");
-                (
-                    format!("{label}: "),
-                    Style::default().fg(Color::Yellow),
-                )
+                ("> ", Modifier::empty(), format!("{label}: "), theme.player)
             }
             LogKind::Assistant => {
                 let label = entry.speaker.as_deref().unwrap_or("Narrator");
-                let color = if is_narrator_label(label) {
-                    Color::Green
+                if is_narrator_label(label) {
+                    ("", Modifier::BOLD, format!("{label}: "), theme.narrator)
                 } else {
-                    Color::Cyan
-                };
-                (format!("{label}: "), Style::default().fg(color))
+                    ("~ ", Modifier::ITALIC, format!("{label}: "), theme.speaker(label))
+                }
             }
-            LogKind::System => ("".to_string(), Style::default().fg(Color::Blue)),
-            LogKind::Error => ("Error: ".to_string(), Style::default().fg(Color::Red)),
+            LogKind::System => ("* ", Modifier::empty(), String::new(), theme.system),
+            LogKind::Error => ("! ", Modifier::BOLD, "Error: ".to_string(), theme.error),
+        };
+        let (prefix, style) = if theme.marks {
+            (format!("{mark}{prefix}"), Style::default().fg(color).add_modifier(modifier))
+        } else {
+            (prefix, Style::default().fg(color))
         };
         let indent = " ".repeat(prefix.len());
         let mut first = true;
-        for line in entry.text.lines() {
-            if first {
-                lines.push(Line::from(vec![
-                    Span::styled(prefix.clone(), style),
-                    Span::raw(line.to_string()),
-                ]));
-                first = false;
+        let markdown = matches!(entry.kind, LogKind::Assistant);
+        for line in text.lines() {
+            let lead = if first {
+                Span::styled(prefix.clone(), style)
+            } else {
+                Span::raw(indent.clone())
+            };
+            first = false;
+            let mut spans = vec![lead];
+            if markdown {
+                spans.extend(style_markdown(line, theme));
             } else {
-                lines.push(Line::from(vec![
-                    Span::raw(indent.clone()),
-                    Span::raw(line.to_string()),
-                ]));
+                spans.push(Span::raw(line.to_string()));
             }
+            lines.push(Line::from(spans));
         }
         lines.push(Line::from(""));
+        if gutter {
+            for (i, line) in lines[start..].iter_mut().enumerate() {
+                let number = if i == 0 && new_turn {
+                    format!("{:>4} ", entry.turn)
+                } else {
+                    " ".repeat(5)
+                };
+                line.spans.insert(0, Span::styled(number, Style::default().fg(theme.muted)));
+            }
+        }
     }
 
-    let line_count = lines.len();
-    (Text::from(lines), line_count)
+    (Text::from(lines), turn_starts)
+}
+
+/// Styles `**bold**`, `*emphasis*` and quoted dialogue, dropping the asterisks.
+/// Markers without a partner later in the line, or followed by a space, stay literal.
+fn style_markdown(line: &str, theme: &Theme) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let (mut bold, mut italic, mut quoted) = (false, false, false);
+    let style = |bold: bool, italic: bool, quoted: bool| {
+        let mut style = Style::default();
+        if quoted {
+            style = style.fg(theme.dialogue);
+        }
+        if bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if italic {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        style
+    };
+    let opens = |after: &str, marker: &str| {
+        after.chars().next().is_some_and(|ch| !ch.is_whitespace()) && after.contains(marker)
+    };
+    fn flush(spans: &mut Vec<Span<'static>>, current: &mut String, style: Style) {
+        if !current.is_empty() {
+            spans.push(Span::styled(std::mem::take(current), style));
+        }
+    }
+    let mut rest = line;
+    while let Some(ch) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("**")
+            && (bold || opens(after, "**"))
+        {
+            flush(&mut spans, &mut current, style(bold, italic, quoted));
+            bold = !bold;
+            rest = after;
+            continue;
+        }
+        if let Some(after) = rest.strip_prefix('*')
+            && (italic || opens(after, "*"))
+        {
+            flush(&mut spans, &mut current, style(bold, italic, quoted));
+            italic = !italic;
+            rest = after;
+            continue;
+        }
+        let after = &rest[ch.len_utf8()..];
+        let opening = !quoted && (ch == '\u{201c}' || (ch == '"' && after.contains('"')));
+        let closing = quoted && (ch == '"' || ch == '\u{201d}');
+        if opening {
+            flush(&mut spans, &mut current, style(bold, italic, quoted));
+            quoted = true;
+        }
+        current.push(ch);
+        if closing {
+            flush(&mut spans, &mut current, style(bold, italic, quoted));
+            quoted = false;
+        }
+        rest = after;
+    }
+    flush(&mut spans, &mut current, style(bold, italic, quoted));
+    spans
 }
 
 fn is_narrator_label(label: &str) -> bool {
     label.trim().eq_ignore_ascii_case("narrator")
 }
 
-fn build_status_line(app: &App) -> Line<'static> {
-    let (text, color) = if app.busy {
-        (build_thinking_indicator(app), Color::Yellow)
+/// Status text on the left; model, turn, context use and cost on the right, dropped
+/// from the end when the line is too narrow for them.
+fn build_status_line(app: &App, width: u16) -> Line<'static> {
+    let theme = &app.theme;
+    let (text, color) = if let Some(toast) = app.active_toast() {
+        (toast.text.clone(), theme.highlight)
+    } else if let Some(wait) = app.retry_at.filter(|_| app.busy) {
+        let secs = wait.saturating_duration_since(Instant::now()).as_secs_f32().ceil();
+        (format!("Rate limited, retrying in {secs}s"), theme.warning)
+    } else if app.busy {
+        (build_thinking_indicator(app), theme.warning)
     } else if app.status.eq_ignore_ascii_case("error") {
-        (app.status.clone(), Color::Red)
+        (app.status.clone(), theme.error)
     } else {
-        (app.status.clone(), Color::Green)
+        (app.status.clone(), theme.good)
     };
 
-    Line::from(Span::styled(text, Style::default().fg(color)))
+    let mut left = vec![Span::styled(text, Style::default().fg(color))];
+    if !app.tasks.is_empty() || (app.busy && app.pending_input.is_some()) {
+        left.push(Span::styled(
+            format!(" | tasks: {}", build_task_summary(app)),
+            Style::default().fg(theme.muted),
+        ));
+    }
+
+    let context = app.history_tokens();
+    let context_color = if context > app.history_token_budget * 9 / 10 {
+        theme.warning
+    } else {
+        theme.muted
+    };
+    let mut right = vec![
+        Span::styled(app.model.clone(), Style::default().fg(theme.accent)),
+        Span::styled(format!(" | turn {}", app.state.turn), Style::default().fg(theme.muted)),
+        Span::styled(
+            format!(
+                " | ctx {}/{}",
+                format_tokens(context),
+                format_tokens(app.history_token_budget)
+            ),
+            Style::default().fg(context_color),
+        ),
+        Span::styled(
+            format!(" | {}", app.session_stats.cost_label()),
+            Style::default().fg(theme.gold),
+        ),
+    ];
+    let used = |spans: &[Span]| spans.iter().map(Span::width).sum::<usize>();
+    let available = usize::from(width).saturating_sub(used(&left) + 1);
+    while !right.is_empty() && used(&right) > available {
+        right.pop();
+    }
+    let gap = usize::from(width).saturating_sub(used(&left) + used(&right));
+    left.push(Span::raw(" ".repeat(gap)));
+    left.extend(right);
+    Line::from(left)
+}
+
+/// Token counts as `850` or `12.3k`.
+fn format_tokens(tokens: u64) -> String {
+    if tokens < 1000 {
+        tokens.to_string()
+    } else {
+        format!("{:.1}k", tokens as f64 / 1000.0)
+    }
 }
 
+fn build_task_summary(app: &App) -> String {
+    let mut parts: Vec<String> = app
+        .tasks
+        .iter()
+        .map(|task| format!("{} {}s", task.kind.label(), task.elapsed().as_secs()))
+        .collect();
+    if app.busy && app.pending_input.is_some() {
+        parts.push("narration queued".to_string());
+    }
+    parts.join(", ")
+}
+
+/// Spinner plus time waited, so a slow model is distinguishable from a hang.
 fn build_thinking_indicator(app: &App) -> String {
-    const FRAMES: [&str; 8] = [
-        "[>     ]",
-        "[>>    ]",
-        "[>>>   ]",
-        "[ >>>  ]",
-        "[  >>> ]",
-        "[   >>>]",
-        "[    >>]",
-        "[     >]",
-    ];
+    const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
     let Some(start) = app.thinking_started else {
         return "Thinking...".to_string();
     };
-    let elapsed_ms = start.elapsed().as_millis() as u64;
-    let idx = ((elapsed_ms / 120) % FRAMES.len() as u64) as usize;
-    format!("Thinking {}", FRAMES[idx])
+    let elapsed = start.elapsed();
+    // The main loop redraws every 200ms at the slowest, so step the spinner at that rate.
+    let idx = ((elapsed.as_millis() / 200) % FRAMES.len() as u128) as usize;
+    let secs = elapsed.as_secs();
+    let waited = if secs < 60 {
+        format!("{secs}s")
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    };
+    format!("{} Thinking\u{2026} {waited}", FRAMES[idx])
 }
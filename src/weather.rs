@@ -0,0 +1,54 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::Serialize;
+
+/// Ambient weather, advanced once per turn by a seeded Markov chain.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Weather {
+    #[default]
+    Clear,
+    Cloudy,
+    Fog,
+    Rain,
+    Storm,
+}
+
+impl Weather {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Weather::Clear => "clear",
+            Weather::Cloudy => "cloudy",
+            Weather::Fog => "fog",
+            Weather::Rain => "rain",
+            Weather::Storm => "storm",
+        }
+    }
+
+    /// Picks the next condition; weather mostly persists and worsens or clears gradually.
+    pub(crate) fn next(self, rng: &mut StdRng) -> Self {
+        // Weights for Clear, Cloudy, Fog, Rain, Storm.
+        let weights: [u32; 5] = match self {
+            Weather::Clear => [80, 15, 5, 0, 0],
+            Weather::Cloudy => [20, 55, 5, 20, 0],
+            Weather::Fog => [25, 15, 60, 0, 0],
+            Weather::Rain => [5, 20, 0, 60, 15],
+            Weather::Storm => [0, 10, 0, 40, 50],
+        };
+        let mut roll = rng.random_range(0..weights.iter().sum::<u32>());
+        let states = [
+            Weather::Clear,
+            Weather::Cloudy,
+            Weather::Fog,
+            Weather::Rain,
+            Weather::Storm,
+        ];
+        for (state, weight) in states.into_iter().zip(weights) {
+            if roll < weight {
+                return state;
+            }
+            roll -= weight;
+        }
+        self
+    }
+}
@@ -0,0 +1,146 @@
+use crate::app::{App, LogKind};
+
+/// Passages quoted per entry; older mentions are dropped.
+const MAX_PASSAGES: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WikiCategory {
+    Person,
+    Place,
+    Item,
+    Event,
+}
+
+impl WikiCategory {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            WikiCategory::Person => "Person",
+            WikiCategory::Place => "Place",
+            WikiCategory::Item => "Item",
+            WikiCategory::Event => "Event",
+        }
+    }
+}
+
+pub(crate) struct WikiEntry {
+    pub(crate) category: WikiCategory,
+    pub(crate) name: String,
+    pub(crate) passages: Vec<String>,
+    /// Names of other entries mentioned in this entry's passages.
+    pub(crate) links: Vec<String>,
+}
+
+/// Browsable encyclopedia overlay opened with `/wiki`.
+pub(crate) struct WikiView {
+    pub(crate) entries: Vec<WikiEntry>,
+    pub(crate) query: String,
+    pub(crate) selected: usize,
+}
+
+impl WikiView {
+    pub(crate) fn new(app: &App) -> Self {
+        Self {
+            entries: build_entries(app),
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    pub(crate) fn filtered(&self) -> Vec<&WikiEntry> {
+        let query = self.query.trim().to_lowercase();
+        self.entries
+            .iter()
+            .filter(|entry| query.is_empty() || entry.name.to_lowercase().contains(&query))
+            .collect()
+    }
+
+    pub(crate) fn selected_entry(&self) -> Option<&WikiEntry> {
+        self.filtered().get(self.selected).copied()
+    }
+
+    /// Jumps to the first entry linked from the selected one.
+    pub(crate) fn follow_link(&mut self) {
+        let Some(link) = self
+            .selected_entry()
+            .and_then(|entry| entry.links.first().cloned())
+        else {
+            return;
+        };
+        self.query = link;
+        self.selected = 0;
+    }
+}
+
+fn build_entries(app: &App) -> Vec<WikiEntry> {
+    let mut seeds: Vec<(WikiCategory, String)> = Vec::new();
+    for entry in &app.log {
+        if let (LogKind::Assistant, Some(speaker)) = (entry.kind, entry.speaker.as_deref())
+            && !speaker.eq_ignore_ascii_case("narrator")
+        {
+            push_seed(&mut seeds, WikiCategory::Person, speaker);
+        }
+    }
+    for place in &app.state.visited {
+        push_seed(&mut seeds, WikiCategory::Place, place);
+    }
+    for item in &app.state.inventory {
+        push_seed(&mut seeds, WikiCategory::Item, &item.name);
+    }
+    for flag in app.state.flags.keys() {
+        push_seed(&mut seeds, WikiCategory::Event, flag);
+    }
+
+    let mut entries: Vec<WikiEntry> = seeds
+        .iter()
+        .map(|(category, name)| WikiEntry {
+            category: *category,
+            name: name.clone(),
+            passages: collect_passages(app, *category, name),
+            links: Vec::new(),
+        })
+        .collect();
+
+    let names: Vec<String> = entries.iter().map(|entry| entry.name.clone()).collect();
+    for entry in &mut entries {
+        let text = entry.passages.join("\n").to_lowercase();
+        entry.links = names
+            .iter()
+            .filter(|name| **name != entry.name && text.contains(&name.to_lowercase()))
+            .cloned()
+            .collect();
+    }
+    entries.sort_by_key(|entry| entry.name.to_lowercase());
+    entries
+}
+
+fn push_seed(seeds: &mut Vec<(WikiCategory, String)>, category: WikiCategory, name: &str) {
+    let name = name.trim();
+    if name.is_empty() || seeds.iter().any(|(_, seen)| seen.eq_ignore_ascii_case(name)) {
+        return;
+    }
+    seeds.push((category, name.to_string()));
+}
+
+fn collect_passages(app: &App, category: WikiCategory, name: &str) -> Vec<String> {
+    let needle = name.to_lowercase();
+    let mut passages: Vec<String> = app
+        .log
+        .iter()
+        .filter(|entry| matches!(entry.kind, LogKind::Assistant))
+        .filter(|entry| {
+            let spoke = category == WikiCategory::Person
+                && entry
+                    .speaker
+                    .as_deref()
+                    .is_some_and(|speaker| speaker.eq_ignore_ascii_case(name));
+            spoke || entry.text.to_lowercase().contains(&needle)
+        })
+        .map(|entry| match entry.speaker.as_deref() {
+            Some(speaker) => format!("{speaker}: {}", entry.text),
+            None => entry.text.clone(),
+        })
+        .collect();
+    let skip = passages.len().saturating_sub(MAX_PASSAGES);
+    passages.drain(..skip);
+    passages
+}
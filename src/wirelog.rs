@@ -0,0 +1,63 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use crate::config::KeyRing;
+use crate::error::Result;
+
+/// Appends every request body and response to a file for offline debugging (`--log-file`).
+pub(crate) struct WireLog {
+    file: Mutex<File>,
+    keys: Arc<KeyRing>,
+}
+
+impl WireLog {
+    pub(crate) fn open(path: &Path, keys: Arc<KeyRing>) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+            keys,
+        })
+    }
+
+    pub(crate) fn request(&self, method: &str, url: &str, body: Option<&Value>) {
+        let body = body
+            .map(|body| serde_json::to_string_pretty(body).unwrap_or_default())
+            .unwrap_or_default();
+        self.write(&format!(">>> {method} {url}"), &body);
+    }
+
+    pub(crate) fn response(&self, url: &str, outcome: std::result::Result<&Value, String>) {
+        match outcome {
+            Ok(value) => {
+                let body = serde_json::to_string_pretty(value).unwrap_or_default();
+                self.write(&format!("<<< {url}"), &body);
+            }
+            Err(message) => self.write(&format!("<<< {url} failed"), &message),
+        }
+    }
+
+    /// Write failures are ignored; the log must never break a turn.
+    fn write(&self, header: &str, body: &str) {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let entry = self.redact(&format!("=== {header} (unix {secs})\n{body}\n"));
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = file.write_all(entry.as_bytes());
+    }
+
+    /// Providers sometimes echo the key back in error messages.
+    fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for key in self.keys.iter() {
+            text = text.replace(key, "[redacted]");
+        }
+        text
+    }
+}